@@ -0,0 +1,417 @@
+//! Compilation of expressions to the code of the stack machine, see `vm`
+
+use crate::{
+    eval::{
+        integer::Integer,
+        resolver::Scope,
+        text::Str,
+        value::{Argument, Value, ValueRef},
+    },
+    parser::{format::FormatSpec, Expression, LetPattern, MatchPattern, Span},
+};
+use std::{cell::OnceCell, rc::Rc};
+
+/// Instruction of the stack machine
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    Number(f64),
+    // value of `integers[i]`
+    Integer(usize),
+    // new value with the text of `strings[i]`, which is shared, values can be changed in place
+    String(usize),
+    Null,
+    // value of `names[i]`, a function without arguments is called
+    Load(usize),
+    // same for a local variable resolved to its scope and slot
+    LoadSlot {
+        depth: usize,
+        slot: usize,
+        name: usize,
+    },
+    Pop,
+    // executes pending special forms and variadic lambdas of the top value
+    Expand,
+
+    // collections of the values on top, an object takes key-value pairs
+    List(usize),
+    Object(usize),
+    // `templates[i]` filled with the values on top, the last interpolation is the deepest one
+    Interpolate(usize),
+
+    // function is on top: a special form is curried with `args[i]` and
+    // the code of the argument, which ends at `end`, is skipped
+    Arg { arg: usize, end: usize },
+    // applies the function under the argument
+    Call,
+    // composition of two functions on top
+    Compose,
+    // macro with the expander on top
+    Macro,
+    // `~` outside of quote
+    Unquote,
+
+    Lambda(usize),
+    Thunk(usize),
+    // destructures the top value by `lets[i]`, the value stays on the stack
+    Let(usize),
+    // enters the scope of the first arm of `matches[i]` which matches the top value
+    Match(usize),
+    // leaves the scope of a match arm
+    PopEnv,
+    Jump(usize),
+    Return,
+}
+
+/// Compiled expression
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    // scope of a function or lazy block body, its environment is made when the body runs
+    pub scope: Option<Rc<Scope>>,
+
+    pub strings: Vec<Str>,
+    pub integers: Vec<ValueRef>,
+    pub names: Vec<String>,
+    pub templates: Vec<Template>,
+    pub args: Vec<ArgSlot>,
+    pub lambdas: Vec<Rc<LambdaCode>>,
+    pub thunks: Vec<Rc<Chunk>>,
+    pub lets: Vec<LetPattern>,
+    pub matches: Vec<Vec<MatchEntry>>,
+    pub sites: Vec<Site>,
+}
+
+impl Chunk {
+    /// Whether the value produced before `pc` is the result of the chunk
+    pub fn is_tail(&self, pc: usize) -> bool {
+        let mut pc = pc;
+        loop {
+            match self.code[pc] {
+                Op::Expand | Op::PopEnv => pc += 1,
+                Op::Jump(to) => pc = to,
+                Op::Return => return true,
+                _ => return false,
+            }
+        }
+    }
+
+    /// Call sites around the instruction, innermost first
+    pub fn sites_at(&self, pc: usize) -> impl Iterator<Item = &Site> {
+        self.sites
+            .iter()
+            .filter(move |site| site.start <= pc && pc < site.end)
+    }
+}
+
+/// Interpolated string, with all interpolations replaced with placeholder "_"
+#[derive(Debug)]
+pub struct Template {
+    pub string: String,
+    pub positions: Vec<usize>,
+    // spec of each interpolation, if it has one
+    pub formats: Vec<Option<FormatSpec>>,
+}
+
+/// Arm of a match: the pattern, the scope of the arm and where its body starts
+#[derive(Debug)]
+pub struct MatchEntry {
+    pub pattern: MatchPattern,
+    pub scope: Rc<Scope>,
+    pub body: usize,
+}
+
+/// Argument of an application, which is given to a special form as an expression
+#[derive(Debug)]
+pub struct ArgSlot {
+    pub expr: Rc<Expression>,
+    // the argument is evaluated in the environment of its scope
+    scope: Option<Rc<Scope>>,
+    // compiled once the argument goes to a special form
+    code: OnceCell<Rc<Chunk>>,
+}
+
+impl ArgSlot {
+    pub fn argument(&self) -> Argument {
+        let code = self
+            .code
+            .get_or_init(|| compile_in(&self.expr, self.scope.clone()));
+        Argument::with_code(Rc::clone(&self.expr), Rc::clone(code))
+    }
+}
+
+/// Compiled `fn`, shared by all closures made from it
+#[derive(Debug)]
+pub struct LambdaCode {
+    // tried in order, all clauses take the same number of params
+    pub clauses: Vec<Clause>,
+    // collects arguments after params, makes lambda variadic
+    pub rest: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Clause {
+    pub parameters: Vec<MatchPattern>,
+    pub body: Rc<Chunk>,
+}
+
+/// Application, errors raised by its instructions are reported at its place
+#[derive(Debug)]
+pub struct Site {
+    pub start: usize,
+    pub end: usize,
+    pub span: Span,
+    pub callee: String,
+}
+
+/// Compiles the expression, which is not inside any scope, all its free names are looked up
+pub fn compile(expr: &Expression) -> Rc<Chunk> {
+    compile_in(expr, None)
+}
+
+/// Compiles the expression to run in the environment of the scope
+pub fn compile_in(expr: &Expression, scope: Option<Rc<Scope>>) -> Rc<Chunk> {
+    let mut compiler = Compiler::new(scope);
+    compiler.expr(expr);
+    compiler.finish()
+}
+
+/// Body of a function or a lazy block, which runs in a new environment of the scope
+fn compile_body(scope: Rc<Scope>, body: impl FnOnce(&mut Compiler)) -> Rc<Chunk> {
+    let mut compiler = Compiler::new(Some(Rc::clone(&scope)));
+    compiler.chunk.scope = Some(scope);
+    body(&mut compiler);
+    compiler.finish()
+}
+
+struct Compiler {
+    chunk: Chunk,
+    scope: Option<Rc<Scope>>,
+}
+
+impl Compiler {
+    fn new(scope: Option<Rc<Scope>>) -> Self {
+        Self {
+            chunk: Chunk::default(),
+            scope,
+        }
+    }
+
+    fn finish(mut self) -> Rc<Chunk> {
+        self.emit(Op::Return);
+        Rc::new(self.chunk)
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.chunk.code.push(op);
+        self.chunk.code.len() - 1
+    }
+
+    fn here(&self) -> usize {
+        self.chunk.code.len()
+    }
+
+    fn expr(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Number(x) => {
+                self.emit(Op::Number(*x));
+            }
+            Expression::Integer(x) => {
+                let val = Value::Integer(Integer::from(x.clone()));
+                self.chunk.integers.push(Rc::new(val));
+                self.emit(Op::Integer(self.chunk.integers.len() - 1));
+            }
+            Expression::String(s) => {
+                self.chunk.strings.push(s.as_str().into());
+                self.emit(Op::String(self.chunk.strings.len() - 1));
+            }
+            Expression::StringInterpolation(si) => {
+                for entry in si.entries.iter().rev() {
+                    self.expr(&entry.expression);
+                    self.emit(Op::Expand);
+                }
+
+                self.chunk.templates.push(Template {
+                    string: si.string.clone(),
+                    positions: si.entries.iter().map(|entry| entry.position).collect(),
+                    formats: si.entries.iter().map(|entry| entry.format.clone()).collect(),
+                });
+                self.emit(Op::Interpolate(self.chunk.templates.len() - 1));
+            }
+            Expression::Identifier(name) => {
+                self.chunk.names.push(name.clone());
+                let name_at = self.chunk.names.len() - 1;
+
+                match self.scope.as_ref().and_then(|scope| scope.resolve(name)) {
+                    Some((depth, slot)) => self.emit(Op::LoadSlot {
+                        depth,
+                        slot,
+                        name: name_at,
+                    }),
+                    None => self.emit(Op::Load(name_at)),
+                };
+            }
+            Expression::Pipe { left, right, span } => self.application(right, left, *span),
+            Expression::Chain { left, right, span } => self.application(left, right, *span),
+            Expression::Application {
+                function,
+                argument,
+                span,
+            } => self.application(function, argument, *span),
+            Expression::Flow { left, right } => {
+                self.expr(left);
+                self.expr(right);
+                self.emit(Op::Compose);
+            }
+            Expression::List(items) => {
+                for it in items.iter() {
+                    self.expr(it);
+                    self.emit(Op::Expand);
+                }
+                self.emit(Op::List(items.len()));
+            }
+            Expression::Object(items) => {
+                for it in items.iter() {
+                    self.expr(&it.key);
+                    self.emit(Op::Expand);
+                    self.expr(&it.value);
+                    self.emit(Op::Expand);
+                }
+                self.emit(Op::Object(items.len()));
+            }
+            Expression::Let { pattern, value } => {
+                self.expr(value);
+                self.emit(Op::Expand);
+                self.chunk.lets.push(pattern.clone());
+                self.emit(Op::Let(self.chunk.lets.len() - 1));
+            }
+            Expression::Lambda {
+                parameters,
+                rest,
+                body,
+            } => self.lambda(vec![(parameters, body)], rest.clone()),
+            Expression::MultiLambda { clauses } => self.lambda(
+                clauses
+                    .iter()
+                    .map(|clause| (&clause.parameters, &clause.body))
+                    .collect(),
+                None,
+            ),
+            Expression::Match { subject, arms } => {
+                self.expr(subject);
+                self.emit(Op::Expand);
+                self.emit(Op::Match(self.chunk.matches.len()));
+
+                let mut table = Vec::new();
+                let mut exits = Vec::new();
+                for arm in arms.iter() {
+                    let scope = Scope::arm(&arm.pattern, &arm.body, self.scope.clone());
+                    table.push(MatchEntry {
+                        pattern: arm.pattern.clone(),
+                        scope: Rc::clone(&scope),
+                        body: self.here(),
+                    });
+
+                    let outer = self.scope.replace(scope);
+                    self.expr(&arm.body);
+                    self.scope = outer;
+
+                    self.emit(Op::Expand);
+                    self.emit(Op::PopEnv);
+                    exits.push(self.emit(Op::Jump(0)));
+                }
+
+                let end = self.here();
+                for exit in exits {
+                    self.chunk.code[exit] = Op::Jump(end);
+                }
+                self.chunk.matches.push(table);
+            }
+            Expression::Macro(lambda) => {
+                self.expr(lambda);
+                self.emit(Op::Macro);
+            }
+            Expression::Unquote(_) => {
+                self.emit(Op::Unquote);
+            }
+            Expression::Parenthesized(expr) => self.expr(expr),
+            Expression::Lazy(exprs) => {
+                let scope = Scope::block(exprs, self.scope.clone());
+                self.chunk.thunks.push(compile_body(scope, |c| c.block(exprs)));
+                self.emit(Op::Thunk(self.chunk.thunks.len() - 1));
+            }
+            Expression::Block(exprs) => self.block(exprs),
+        }
+    }
+
+    fn block(&mut self, exprs: &[Expression]) {
+        if exprs.is_empty() {
+            self.emit(Op::Null);
+        }
+
+        for (i, it) in exprs.iter().enumerate() {
+            if i > 0 {
+                self.emit(Op::Pop);
+            }
+            self.expr(it);
+            self.emit(Op::Expand);
+        }
+    }
+
+    /// Argument is evaluated only if the function is not a special form
+    fn application(&mut self, function: &Expression, argument: &Rc<Expression>, span: Span) {
+        let start = self.here();
+
+        self.expr(function);
+        let arg = self.emit(Op::Arg {
+            arg: self.chunk.args.len(),
+            end: 0,
+        });
+        self.chunk.args.push(ArgSlot {
+            expr: Rc::clone(argument),
+            scope: self.scope.clone(),
+            code: OnceCell::new(),
+        });
+        self.expr(argument);
+        self.emit(Op::Expand);
+        self.emit(Op::Call);
+
+        let end = self.here();
+        if let Op::Arg { arg: slot, .. } = self.chunk.code[arg] {
+            self.chunk.code[arg] = Op::Arg { arg: slot, end };
+        }
+        self.chunk.sites.push(Site {
+            start,
+            end,
+            span,
+            callee: callee_name(function),
+        });
+    }
+
+    fn lambda(&mut self, clauses: Vec<(&Vec<MatchPattern>, &Expression)>, rest: Option<String>) {
+        let clauses = clauses
+            .into_iter()
+            .map(|(parameters, body)| {
+                let scope = Scope::lambda(parameters, rest.as_ref(), body, self.scope.clone());
+                Clause {
+                    parameters: parameters.clone(),
+                    body: compile_body(scope, |c| c.expr(body)),
+                }
+            })
+            .collect();
+
+        self.chunk.lambdas.push(Rc::new(LambdaCode { clauses, rest }));
+        self.emit(Op::Lambda(self.chunk.lambdas.len() - 1));
+    }
+}
+
+/// Name of the function called by the expression, for stack traces
+fn callee_name(function: &Expression) -> String {
+    match function {
+        Expression::Identifier(name) => name.clone(),
+        Expression::Application { function, .. } => callee_name(function),
+        Expression::Chain { left, .. } => callee_name(left),
+        Expression::Pipe { right, .. } => callee_name(right),
+        Expression::Parenthesized(expr) => callee_name(expr),
+        _ => "<lambda>".to_string(),
+    }
+}
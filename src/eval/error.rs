@@ -1,17 +1,24 @@
-use crate::{eval::value::ValueType, parser::Expression};
+use crate::{
+    eval::value::{Value, ValueRef, ValueType},
+    parser::{Expression, Span},
+};
+use std::rc::Rc;
 
 #[derive(Debug)]
 pub struct ShikError {
-    title: String,
-    msg: String,
+    pub title: String,
+    pub msg: String,
+    // any value attached to the error
+    pub data: ValueRef,
 }
 
 impl ShikError {
     pub fn default_error(msg: String) -> RuntimeError {
-        RuntimeError::Custom(Self {
-            title: "RuntimeError".to_string(),
-            msg,
-        })
+        Self::raise("RuntimeError".to_string(), msg, Rc::new(Value::Null))
+    }
+
+    pub fn raise(title: String, msg: String, data: ValueRef) -> RuntimeError {
+        RuntimeError::Custom(Self { title, msg, data })
     }
 }
 
@@ -21,10 +28,179 @@ pub enum RuntimeError {
     NotYetImplemented(Expression),
 
     MissmatchedTypes { got: ValueType, expected: ValueType },
+    // annotation names a type which does not exist
+    UnknownType(String),
     InvalidApplication,
     IndexOutOfBounds { index: usize },
+    NoMatchingPattern(String),
+    NoMatchingClause(String),
+    PatternMismatch { pattern: String, reason: String },
+    MacroError(String),
+    // division by zero and other operations without a result
+    Arithmetic(String),
 
     Custom(ShikError),
+
+    ImportError(String),
+    // error raised while evaluating an imported file
+    Module {
+        file: String,
+        source: String,
+        error: Box<RuntimeError>,
+    },
+
+    // error with the place it was raised at
+    Traced(Box<Trace>),
+}
+
+/// Call of a shik function, which the error went through
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub name: String,
+    pub span: Span,
+    // tail calls after this one, which are not kept
+    pub elided: usize,
+}
+
+#[derive(Debug)]
+pub struct Trace {
+    pub error: RuntimeError,
+    pub span: Span,
+    // innermost call first
+    pub stack: Vec<Frame>,
+    // the error left a function body, the next call site goes to the stack
+    unwinding: bool,
+}
+
+impl RuntimeError {
+    /// The error without location
+    pub fn into_root(self) -> RuntimeError {
+        match self {
+            RuntimeError::Traced(trace) => trace.error.into_root(),
+            RuntimeError::Module { error, .. } => error.into_root(),
+            error => error,
+        }
+    }
+
+    /// Remembers where the error was raised, if it is not known yet
+    pub fn at(self, span: Span) -> Self {
+        match self {
+            RuntimeError::Traced(_) => self,
+            error => RuntimeError::Traced(Box::new(Trace {
+                error,
+                span,
+                stack: Vec::new(),
+                unwinding: false,
+            })),
+        }
+    }
+
+    /// Marks the error as leaving a function body
+    pub fn unwind(self) -> Self {
+        match self {
+            RuntimeError::Traced(mut trace) => {
+                trace.unwinding = true;
+                RuntimeError::Traced(trace)
+            }
+            error => error,
+        }
+    }
+
+    /// Passes the error through a call site: it becomes the error place, if it is not known yet,
+    /// or a stack frame, if the error left the body of the called function
+    pub fn at_call(self, span: Span, callee: impl FnOnce() -> String) -> Self {
+        match self {
+            RuntimeError::Traced(mut trace) => {
+                if trace.unwinding {
+                    trace.stack.push(Frame {
+                        name: callee(),
+                        span,
+                        elided: 0,
+                    });
+                    trace.unwinding = false;
+                }
+                RuntimeError::Traced(trace)
+            }
+            error => error.at(span),
+        }
+    }
+
+    /// Counts tail calls left out of the stack after the last frame
+    pub fn elide(self, calls: usize) -> Self {
+        match self {
+            RuntimeError::Traced(mut trace) if calls > 0 => {
+                if let Some(frame) = trace.stack.last_mut() {
+                    frame.elided += calls;
+                }
+                RuntimeError::Traced(trace)
+            }
+            error => error,
+        }
+    }
+
+    /// Renders the error with the source line it was raised at and the call stack
+    pub fn report(&self, file: &str, source: &str) -> String {
+        let RuntimeError::Traced(trace) = self else {
+            return self.to_string();
+        };
+        let Span { line, column } = trace.span;
+
+        let mut out = match &trace.error {
+            // the error is shown in the imported file, the import goes to the stack
+            RuntimeError::Module {
+                file: module,
+                source,
+                error,
+            } => format!(
+                "{}\n  at import ({}:{}:{})\n",
+                error.report(module, source),
+                file,
+                line,
+                column
+            ),
+            error => format!(
+                "{}\n  --> {}:{}:{}\n{}",
+                error,
+                file,
+                line,
+                column,
+                snippet(source, trace.span)
+            ),
+        };
+
+        for frame in trace.stack.iter() {
+            out += &format!(
+                "  at {} ({}:{}:{})\n",
+                frame.name, file, frame.span.line, frame.span.column
+            );
+            if frame.elided > 0 {
+                out += &format!("  ... {} tail calls\n", frame.elided);
+            }
+        }
+
+        out.trim_end().to_string()
+    }
+}
+
+/// Source line with a caret under the column
+fn snippet(source: &str, span: Span) -> String {
+    let Some(code) = source.lines().nth(span.line.wrapping_sub(1)) else {
+        return String::new();
+    };
+
+    let number = span.line.to_string();
+    let gutter = " ".repeat(number.len());
+    // keep tabs, so the caret is aligned with the code
+    let indent: String = code
+        .chars()
+        .take(span.column.saturating_sub(1))
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    format!(
+        "{} |\n{} | {}\n{} | {}^\n",
+        gutter, number, code, gutter, indent
+    )
 }
 
 impl std::fmt::Display for RuntimeError {
@@ -40,6 +216,7 @@ impl std::fmt::Display for RuntimeError {
                     expected, got
                 )
             }
+            RuntimeError::UnknownType(name) => write!(f, "TypeError: Unknown type '{}'", name),
             RuntimeError::NotYetImplemented(expr) => {
                 write!(
                     f,
@@ -56,6 +233,30 @@ impl std::fmt::Display for RuntimeError {
                 )
             }
 
+            RuntimeError::NoMatchingPattern(value) => {
+                write!(f, "MatchError: no pattern matches the value '{}'", value)
+            }
+
+            RuntimeError::NoMatchingClause(args) => {
+                write!(
+                    f,
+                    "MatchError: no function clause matches the arguments '{}'",
+                    args
+                )
+            }
+
+            RuntimeError::PatternMismatch { pattern, reason } => {
+                write!(f, "PatternError: cannot bind {} - {}", pattern, reason)
+            }
+
+            RuntimeError::MacroError(msg) => {
+                write!(f, "MacroError: {}", msg)
+            }
+
+            RuntimeError::Arithmetic(msg) => {
+                write!(f, "ArithmeticError: {}", msg)
+            }
+
             RuntimeError::InvalidApplication => {
                 write!(
                     f,
@@ -65,6 +266,19 @@ impl std::fmt::Display for RuntimeError {
             RuntimeError::Custom(err) => {
                 write!(f, "{}: {}", err.title, err.msg,)
             }
+            RuntimeError::ImportError(msg) => {
+                write!(f, "ImportError: {}", msg)
+            }
+            RuntimeError::Module { file, error, .. } => {
+                write!(f, "{} in {}", error, file)
+            }
+            RuntimeError::Traced(trace) => {
+                write!(
+                    f,
+                    "{} at line {}, column {}",
+                    trace.error, trace.span.line, trace.span.column
+                )
+            }
         }
     }
 }
@@ -1,24 +1,46 @@
-/// Tree-walk interpretator
+/// Interpretator: programs are compiled by `compiler` and run by the stack machine in `vm`
 use crate::{
     eval::{
-        error::RuntimeError,
+        compiler::{compile, Chunk},
+        gc::Heap,
+        module::Modules,
+        resolver::{check, Warning},
         native_functions::{
-            bool::bind_bool_module, branching::bind_special_module, file::bind_file_module,
-            keywords::bind_keywords_module, list::bind_list_module, misc::bind_misc_module,
-            number::bind_number_module, polymorphic::bind_poly_module, print::bind_print_module,
-            shell::bind_shell_module, string::bind_string_module, variables::bind_variable_module,
+            bool::bind_bool_module, branching::bind_special_module,
+            code::bind_code_module,
+            error::bind_error_module,
+            file::bind_file_module,
+            keywords::bind_keywords_module, lazy::bind_lazy_module, list::bind_list_module,
+            misc::bind_misc_module, import::bind_import_module, reference::bind_ref_module,
+            result::bind_result_module,
+            number::bind_number_module, object::bind_object_module, polymorphic::bind_poly_module, print::bind_print_module,
+            shell::bind_shell_module, stream::bind_stream_module, string::bind_string_module,
+            variables::bind_variable_module,
         },
-        value::{Closure, Env, EnvRef, NativeClosure, SpecialClosure, Value, ValueRef},
+        value::{Argument, Env, EnvRef, NativeClosure, SpecialClosure, Value, ValueRef},
         EvalResult,
     },
-    parser::{Expression, LetPattern, Program},
+    parser::{Expression, Program, Span},
+};
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
 };
-use std::{collections::HashMap, rc::Rc};
 
 #[derive(Debug)]
 pub struct Interpretator {
     // global context
     pub ctx: EnvRef,
+    // native functions and special forms, the parent of the global context
+    pub builtins: EnvRef,
+    // handle to itself, for values created during evaluation
+    this: Weak<Interpretator>,
+    // files loaded by `import`
+    pub modules: RefCell<Modules>,
+    // places which may be in reference cycles, see `gc`
+    pub heap: Heap,
+    // warnings of checked programs, which are not taken by the caller yet
+    warnings: RefCell<Vec<Warning>>,
 }
 
 impl Interpretator {
@@ -26,16 +48,22 @@ impl Interpretator {
         let env = Rc::new(Env::new(None));
 
         // Create interpretator with the environment
-        let inter = Self {
-            ctx: Rc::clone(&env),
-        };
-        let inter = Rc::new(inter);
+        let inter = Rc::new_cyclic(|this| Self {
+            ctx: Rc::new(Env::new(Some(Rc::clone(&env)))),
+            builtins: Rc::clone(&env),
+            this: Weak::clone(this),
+            modules: RefCell::new(Modules::default()),
+            heap: Heap::default(),
+            warnings: RefCell::new(Vec::new()),
+        });
 
         // Bind all modules with access to interpretator
         bind_number_module(&env, Rc::clone(&inter));
         bind_bool_module(&env, Rc::clone(&inter));
         bind_string_module(&env, Rc::clone(&inter));
         bind_list_module(&env, Rc::clone(&inter));
+        bind_object_module(&env, Rc::clone(&inter));
+        bind_ref_module(&env, Rc::clone(&inter));
         bind_poly_module(&env, Rc::clone(&inter));
         bind_print_module(&env, Rc::clone(&inter));
         bind_keywords_module(&env, Rc::clone(&inter));
@@ -44,201 +72,81 @@ impl Interpretator {
         bind_variable_module(&env, Rc::clone(&inter));
         bind_special_module(&env, Rc::clone(&inter));
         bind_misc_module(&env, Rc::clone(&inter));
+        bind_lazy_module(&env, Rc::clone(&inter));
+        bind_error_module(&env, Rc::clone(&inter));
+        bind_result_module(&env, Rc::clone(&inter));
+        bind_import_module(&env, Rc::clone(&inter));
+        bind_code_module(&env, Rc::clone(&inter));
+        bind_stream_module(&env, Rc::clone(&inter));
 
         inter
     }
 
+    /// Strong reference to this interpretator
+    pub fn rc(&self) -> Rc<Interpretator> {
+        self.this
+            .upgrade()
+            .expect("interpretator is used after being dropped")
+    }
+
+    /// Warnings found in the programs and imported files since the last call
+    pub fn take_warnings(&self) -> Vec<Warning> {
+        self.warnings.take()
+    }
+
     pub fn interpretate(&self, program: &Program) -> EvalResult {
-        let mut last = Rc::new(Value::Null);
+        self.interpretate_in(program, &self.ctx)
+    }
 
-        for stmt in &program.statements {
-            last = self.expand(self.eval_expr(&stmt.expression, &self.ctx)?)?;
+    /// Checks names of the program, compiles it and evaluates its statements
+    /// in the given environment
+    pub fn interpretate_in(&self, program: &Program, env: &EnvRef) -> EvalResult {
+        let file = self.modules.borrow().current_file().map(|file| file.display().to_string());
+        let warnings = check(program, env, &self.builtins)?;
+        self.warnings.borrow_mut().extend(warnings.into_iter().map(|warning| Warning {
+            file: file.clone(),
+            ..warning
+        }));
+
+        let statements: Vec<(Rc<Chunk>, Span)> = program
+            .statements
+            .iter()
+            .map(|stmt| (compile(&stmt.expression), Span::new(stmt.line, stmt.column)))
+            .collect();
+
+        let mut last = Rc::new(Value::Null);
+        for (code, span) in statements.iter() {
+            last = self
+                .run_code(code, env)
+                .and_then(|v| self.expand(v))
+                .map_err(|e| e.at(*span))?;
         }
 
         Ok(last)
     }
 
     pub fn eval_expr(&self, expr: &Expression, ctx: &EnvRef) -> EvalResult {
-        // println!("---");
-        // println!("eval expr: {:?}", expr);
-        // println!("With env: {:?}", ctx);
-        match expr {
-            Expression::Number(x) => Ok(Rc::new(Value::Number(*x))),
-            Expression::String(s) => Ok(Rc::new(Value::String(s.clone()))),
-            Expression::StringInterpolation(si) => {
-                let mut str = si.string.clone();
-                let entries = &si.entries;
-
-                for inter in entries.into_iter().rev() {
-                    let i = inter.position;
-                    let val = self.expand(self.eval_expr(&inter.expression, &ctx)?)?;
-                    let val_str = match val.as_ref() {
-                        Value::String(s) => s,
-                        val => &val.to_string(),
-                    };
-                    str.replace_range(i..i + 1, val_str);
-                }
-
-                return Ok(Rc::new(Value::String(str)));
-            }
-            Expression::List(lst) => {
-                let mut res: Vec<ValueRef> = Vec::new();
-
-                for it in lst.into_iter() {
-                    let val = self.expand(self.eval_expr(it, ctx)?)?;
-                    res.push(val);
-                }
-
-                Ok(Rc::new(Value::List(res)))
-            }
-            Expression::Object(obj) => {
-                let mut res: HashMap<String, ValueRef> = HashMap::new();
-
-                for it in obj.iter() {
-                    let key = self.expand(self.eval_expr(&it.key, ctx)?)?;
-                    let key = key.expect_string()?;
-                    let val = self.expand(self.eval_expr(&it.value, ctx)?)?;
-                    res.insert(key.to_string(), val);
-                }
-
-                Ok(Rc::new(Value::Object(res)))
-            }
-            Expression::Pipe { left, right } => {
-                let f = self.eval_expr(right.as_ref(), ctx)?;
-
-                match f.as_ref() {
-                    Value::SpecialForm(closure) => {
-                        let mut curried = SpecialClosure::new(
-                            Rc::clone(&closure.logic),
-                            Rc::clone(&closure.interpretator),
-                            Rc::clone(&ctx),
-                        );
-                        curried.params.extend_from_slice(&closure.params);
-                        curried.params.push(*left.clone());
-                        let f = Value::SpecialForm(curried);
-
-                        Ok(Rc::new(f))
-                    }
-                    _ => {
-                        let a = self.expand(self.eval_expr(left.as_ref(), ctx)?)?;
-
-                        self.apply_fn(&f, &a)
-                    }
-                }
-            }
-            Expression::Chain { left, right } => {
-                let f = self.eval_expr(left.as_ref(), ctx)?;
-                match f.as_ref() {
-                    Value::SpecialForm(closure) => {
-                        let mut curried = SpecialClosure::new(
-                            Rc::clone(&closure.logic),
-                            Rc::clone(&closure.interpretator),
-                            Rc::clone(&ctx),
-                        );
-                        curried.params.extend_from_slice(&closure.params);
-                        curried.params.push(*right.clone());
-                        let f = Value::SpecialForm(curried);
-
-                        Ok(Rc::new(f))
-                    }
-                    _ => {
-                        let a = self.expand(self.eval_expr(right.as_ref(), ctx)?)?;
-
-                        self.apply_fn(&f, &a)
-                    }
-                }
-            }
-            Expression::Application { function, argument } => {
-                let f = self.eval_expr(function.as_ref(), ctx)?;
-                match f.as_ref() {
-                    Value::SpecialForm(closure) => {
-                        let mut curried = SpecialClosure::new(
-                            Rc::clone(&closure.logic),
-                            Rc::clone(&closure.interpretator),
-                            Rc::clone(&ctx),
-                        );
-                        curried.params.extend_from_slice(&closure.params);
-                        curried.params.push(*argument.clone());
-                        let f = Value::SpecialForm(curried);
-
-                        Ok(Rc::new(f))
-                    }
-                    _ => {
-                        let a = self.expand(self.eval_expr(argument.as_ref(), ctx)?)?;
-
-                        self.apply_fn(&f, &a)
-                    }
-                }
-            }
-            Expression::Parenthesized(expr) => self.eval_expr(expr, ctx),
-            Expression::Block(expr_lst) => {
-                let mut last = Rc::new(Value::Null);
-
-                for it in expr_lst.iter() {
-                    last = self.expand(self.eval_expr(it, ctx)?)?;
-                }
+        self.run_code(&compile(expr), ctx)
+    }
 
-                Ok(last)
-            }
-            Expression::Lambda {
-                parameters,
-                #[allow(unused_variables)] // rest still not supported
-                rest,
-                body,
-            } => Ok(Rc::new(Value::Lambda(Closure::new(
-                parameters.clone(),
-                body.clone(),
-                Rc::new(Env::new(Some(Rc::clone(ctx)))),
-            )))),
-            Expression::Let { pattern, value } => match pattern {
-                LetPattern::Identifier(name) => {
-                    let val = self.expand(self.eval_expr(value, ctx)?)?;
-                    ctx.define(name.to_string(), Rc::clone(&val));
-                    Ok(val)
-                }
-                _ => Err(RuntimeError::NotYetImplemented(expr.clone())),
-            },
-            Expression::Identifier(name) => {
-                ctx.lookup(name)
-                    .map_or(
-                        Err(RuntimeError::UndefinedVariable(name.clone())),
-                        |val| match val.as_ref() {
-                            Value::Lambda(closure) => {
-                                if closure.params.len() == 0 {
-                                    return self.eval_expr(&closure.body, &closure.env);
-                                }
-                                Ok(val)
-                            }
-                            Value::NativeLambda(closure) => {
-                                if closure.params_count == 0 {
-                                    return closure.exec();
-                                }
-                                Ok(val)
-                            }
-                            _ => Ok(val),
-                        },
-                    )
-            }
-            e => Err(RuntimeError::NotYetImplemented(e.clone())),
-        }
+    /// Evaluates the argument of a special form
+    pub fn eval_arg(&self, arg: &Argument, ctx: &EnvRef) -> EvalResult {
+        self.run_code(&arg.code, ctx)
     }
 
     pub fn apply_fn(&self, f: &ValueRef, a: &ValueRef) -> EvalResult {
         match f.as_ref() {
             Value::Lambda(closure) => {
-                if closure.params.len() == 0 {
-                    return self.eval_expr(&closure.body, &closure.env);
+                if closure.takes_no_args() {
+                    return self.call_closure(closure);
                 }
 
                 let mut curried = closure.clone();
                 curried.binded.push(a.clone());
 
-                if curried.binded.len() == curried.params.len() {
+                if curried.rest().is_none() && curried.binded.len() >= curried.arity() {
                     // All params are binded, let's evaluate
-                    curried.bind_variables();
-                    // println!("<--apply body");
-
-                    self.eval_expr(&curried.body, &curried.env)
+                    self.call_closure(&curried)
                 } else {
                     // Make a new curried lambda
                     Ok(Rc::new(Value::Lambda(curried)))
@@ -265,14 +173,55 @@ impl Interpretator {
                     Ok(Rc::new(Value::NativeLambda(curried)))
                 }
             }
+            // Applying a lazy value forces it, the argument is ignored like with `fn []`
+            Value::Lazy(_) => self.force(Rc::clone(f)),
+            Value::SpecialForm(closure) => {
+                // Special forms work with expressions, so the value is passed by a name
+                // which cannot clash with user variables
+                let name = format!("#arg{}", closure.params.len());
+                let env = Rc::new(Env::new(Some(Rc::clone(&closure.env))));
+                env.define(name.clone(), Rc::clone(a));
+
+                let mut curried = SpecialClosure::new(
+                    Rc::clone(&closure.logic),
+                    Rc::clone(&closure.interpretator),
+                    env,
+                );
+                curried.params.extend_from_slice(&closure.params);
+                curried.params.push(Argument::new(Expression::Identifier(name)));
+
+                self.expand(curried.exec()?)
+            }
             _ => Ok(f.clone()),
         }
     }
 
-    fn expand(&self, v: ValueRef) -> EvalResult {
-        match v.as_ref() {
-            Value::SpecialForm(closure) => closure.exec(),
-            _ => Ok(v),
+    /// Evaluates lazy values, until a strict one is produced
+    pub fn force(&self, v: ValueRef) -> EvalResult {
+        let mut v = v;
+        while let Value::Lazy(thunk) = v.as_ref() {
+            v = match thunk.cached() {
+                Some(val) => val,
+                None => {
+                    let env = Env::child(&thunk.env, thunk.body.scope.as_ref());
+                    let last = self.run_code(&thunk.body, &env)?;
+                    thunk.store(&last);
+                    last
+                }
+            };
+        }
+        Ok(v)
+    }
+
+    /// Executes pending special forms and variadic lambdas, until a plain value is produced
+    pub fn expand(&self, v: ValueRef) -> EvalResult {
+        let mut v = v;
+        loop {
+            v = match v.as_ref() {
+                Value::SpecialForm(closure) if closure.is_ready() => closure.exec()?,
+                Value::Lambda(closure) if closure.is_pending() => self.call_closure(closure)?,
+                _ => return Ok(v),
+            };
         }
     }
 }
@@ -0,0 +1,1369 @@
+#[cfg(test)]
+mod tests {
+    use crate::eval::error::RuntimeError;
+    use crate::eval::evaluator::Interpretator;
+    use crate::eval::value::{Value, ValueRef};
+    use crate::lang::{evaluate, EvalError};
+
+    /// Helper function to evaluate a script in a fresh interpretator
+    fn eval(input: &str) -> Result<ValueRef, EvalError> {
+        let interpretator = Interpretator::new();
+        evaluate(input, &interpretator)
+    }
+
+    /// Helper function to evaluate a script and render the result
+    fn eval_str(input: &str) -> String {
+        match eval(input) {
+            Ok(val) => val.to_string(),
+            Err(e) => panic!("Evaluation of {:?} failed: {}", input, e),
+        }
+    }
+
+    fn eval_err(input: &str) -> RuntimeError {
+        match eval(input) {
+            Err(EvalError::Runtime(e)) => e.into_root(),
+            Err(e) => panic!("Expected runtime error, got {}", e),
+            Ok(val) => panic!("Expected runtime error, got value {}", val),
+        }
+    }
+
+    // ==================== Match ====================
+
+    mod matching {
+        use super::*;
+
+        #[test]
+        fn literal_patterns() {
+            let input = "match :Linux-x86_64 {\n  :Darwin-arm64 :macos\n  :Linux-x86_64 :linux\n}";
+            assert_eq!(eval_str(input), "linux");
+            assert_eq!(eval_str("match 2 { 1 :one 2 :two }"), "two");
+        }
+
+        #[test]
+        fn identifier_binds_value() {
+            assert_eq!(eval_str("match 10 { 0 :zero n (+ n 1) }"), "11");
+        }
+
+        #[test]
+        fn first_match_wins() {
+            assert_eq!(eval_str("match 1 { _ :first 1 :second }"), "first");
+        }
+
+        #[test]
+        fn list_patterns_with_rest() {
+            assert_eq!(eval_str("match [1 2 3] { [] :empty [a ...rest] rest }"), "[ 2 3 ]");
+            assert_eq!(eval_str("match [] { [] :empty [a ...rest] rest }"), "empty");
+            assert_eq!(eval_str("match [1 [2 3]] { [a [b c]] (+ a c) }"), "4");
+            assert_eq!(eval_str("match [1 2] { [a] :one [a b] :two }"), "two");
+        }
+
+        #[test]
+        fn bindings_do_not_leak() {
+            let err = eval_err("match 1 { x x }\nx");
+            assert!(matches!(err, RuntimeError::UndefinedVariable(name) if name == "x"));
+        }
+
+        #[test]
+        fn no_matching_arm_is_an_error() {
+            let err = eval_err("match 5 { 0 :zero [x] x }");
+            assert!(matches!(err, RuntimeError::NoMatchingPattern(_)));
+        }
+
+        #[test]
+        fn subject_value() {
+            let res = eval("match (+ 1 2) { 3 true _ false }").unwrap();
+            assert!(matches!(res.as_ref(), Value::Bool(true)));
+        }
+    }
+
+    // ==================== Composition ====================
+
+    mod composition {
+        use super::*;
+
+        #[test]
+        fn applies_left_to_right() {
+            assert_eq!(eval_str("(string.upper #> (string.+ :x)) :a"), "xA");
+            assert_eq!(eval_str(":a $> string.upper #> (string.+ :x)"), "xA");
+        }
+
+        #[test]
+        fn chains_several_functions() {
+            let input = "let f (string.trim #> string.upper #> (string.+ :x))\nf \"  ab \"";
+            assert_eq!(eval_str(input), "xAB");
+        }
+
+        #[test]
+        fn works_with_curried_functions() {
+            assert_eq!(eval_str("((+ 1) #> (* 2)) 3"), "8");
+            // the result of composition is curried further
+            assert_eq!(eval_str("(string.upper #> string.+) :a :b"), "Ab");
+        }
+
+        #[test]
+        fn works_with_higher_order_functions() {
+            let input = "list.map (string.upper #> (string.+ :x)) [:ab :cd]";
+            assert_eq!(eval_str(input), "[ xAB xCD ]");
+        }
+
+        #[test]
+        fn works_with_special_forms() {
+            assert_eq!(eval_str("null $> (or? :x) #> string.upper"), "X");
+            assert_eq!(eval_str(":y $> (or? :x) #> string.upper"), "Y");
+        }
+
+        #[test]
+        fn lambda_returning_special_form_is_evaluated() {
+            let input = "list.map (fn [x] if (> x 1) :a :b) [1 2]";
+            assert_eq!(eval_str(input), "[ b a ]");
+        }
+    }
+
+    // ==================== Lazy ====================
+
+    mod lazy {
+        use super::*;
+
+        #[test]
+        fn is_not_evaluated_until_forced() {
+            let res = eval("let n 0\nlet t #(set n 1)\nn").unwrap();
+            assert!(matches!(res.as_ref(), Value::Integer(x) if x.is_zero()));
+            assert_eq!(eval_str("let t #(+ 1 2)\nforce t"), "3");
+            assert_eq!(eval_str("lazy? #(1)"), "true");
+        }
+
+        #[test]
+        fn evaluated_on_each_force() {
+            let input = "let n 0\nlet t #(set n (+ 1 n))\nforce t\nforce t";
+            assert_eq!(eval_str(input), "2");
+        }
+
+        #[test]
+        fn memoized_evaluated_once() {
+            let input = "let n 0\nlet t (lazy.memo #(set n (+ 1 n)))\nforce t\nforce t\nn";
+            assert_eq!(eval_str(input), "1");
+        }
+
+        #[test]
+        fn applying_forces_value() {
+            assert_eq!(eval_str("list.map #(:const) [1 2]"), "[ const const ]");
+        }
+
+        #[test]
+        fn forced_by_special_forms() {
+            assert_eq!(eval_str("if #(= 1 1) #(:yes) #(:no)"), "yes");
+            assert_eq!(eval_str("null $> or? #(:default)"), "default");
+
+            let input = "let x 3\nwhile #(\n  set x (- 1 x)\n  > x 0\n)\nx";
+            assert_eq!(eval_str(input), "0");
+        }
+
+        #[test]
+        fn has_own_scope() {
+            let err = eval_err("force #(let inner 1)\ninner");
+            assert!(matches!(err, RuntimeError::UndefinedVariable(_)));
+        }
+    }
+
+    // ==================== Destructuring let ====================
+
+    mod destructuring {
+        use super::*;
+
+        #[test]
+        fn binds_list_elements() {
+            assert_eq!(eval_str("let [a b] [1 2]\n+ a b"), "3");
+        }
+
+        #[test]
+        fn binds_rest() {
+            let input = "let [head second ...others] [1 2 3 4]\n[head second others]";
+            assert_eq!(eval_str(input), "[ 1 2 [ 3 4 ] ]");
+            assert_eq!(eval_str("let [a #tail] [1]\ntail"), "[ ]");
+        }
+
+        #[test]
+        fn binds_nested_patterns() {
+            assert_eq!(eval_str("let [[a b] _ c] [[1 2] 3 4]\n[a b c]"), "[ 1 2 4 ]");
+        }
+
+        #[test]
+        fn returns_the_value() {
+            assert_eq!(eval_str("let [a] [1]"), "[ 1 ]");
+        }
+
+        #[test]
+        fn length_mismatch_is_an_error() {
+            let err = eval_err("let [x y z] [1 2]");
+            assert_eq!(
+                err.to_string(),
+                "PatternError: cannot bind [x y z] - expected 3 elements, got 2"
+            );
+
+            let err = eval_err("let [x y ...rest] [1]");
+            assert!(matches!(err, RuntimeError::PatternMismatch { .. }));
+        }
+
+        #[test]
+        fn non_list_is_an_error() {
+            let err = eval_err("let [a] 10");
+            assert!(matches!(err, RuntimeError::PatternMismatch { .. }));
+        }
+
+        #[test]
+        fn nothing_is_bound_on_error() {
+            let interpretator = Interpretator::new();
+            evaluate("let a 0", &interpretator).unwrap();
+            assert!(evaluate("let [a [b]] [1 2]", &interpretator).is_err());
+
+            let a = evaluate("a", &interpretator).unwrap();
+            assert!(matches!(a.as_ref(), Value::Integer(x) if x.is_zero()));
+        }
+    }
+
+    // ==================== Lambda parameters ====================
+
+    mod lambda_params {
+        use super::*;
+
+        #[test]
+        fn destructures_arguments() {
+            let input = "let f fn [[a b] _ c] [a b c]\nf [1 2] 3 4";
+            assert_eq!(eval_str(input), "[ 1 2 4 ]");
+        }
+
+        #[test]
+        fn literal_pattern_is_a_guard() {
+            assert_eq!(eval_str("let f fn [0 x] x\nf 0 7"), "7");
+
+            let err = eval_err("let f fn [0 x] x\nf 1 7");
+            assert_eq!(
+                err.to_string(),
+                "PatternError: cannot bind 0 - argument does not match, got 1"
+            );
+        }
+
+        #[test]
+        fn rest_collects_remaining_arguments() {
+            assert_eq!(eval_str("let f fn [a ...more] [a more]\nf 1 2 3"), "[ 1 [ 2 3 ] ]");
+            assert_eq!(eval_str("let f fn [a #more] more\nf 1"), "[ ]");
+            assert_eq!(eval_str("let f fn [#all] list.len all\nf 1 2 3"), "3");
+        }
+
+        #[test]
+        fn variadic_lambda_in_pipe() {
+            assert_eq!(eval_str("let f fn [a ...more] [a more]\n1 $> f"), "[ 1 [ ] ]");
+        }
+
+        #[test]
+        fn calls_do_not_share_scope() {
+            let input = "let pair fn [a b] fn [] [a b]\nlet x (pair 1 2)\nlet y (pair 3 4)\nx";
+            assert_eq!(eval_str(input), "[ 1 2 ]");
+        }
+    }
+
+    // ==================== Multi-clause functions ====================
+
+    mod clauses {
+        use super::*;
+
+        #[test]
+        fn first_matching_clause_runs() {
+            let input = "let fact fn [0] 1 | [n] * n (fact (- 1 n))\nfact 5";
+            assert_eq!(eval_str(input), "120");
+
+            let input =
+                "let f fn [0 _] :left | [_ 0] :right | [a b] + a b\n[(f 0 0) (f 1 0) (f 1 2)]";
+            assert_eq!(eval_str(input), "[ left right 3 ]");
+        }
+
+        #[test]
+        fn clauses_on_separate_lines() {
+            let input = "let len fn [[]] 0\n  | [[_ ...xs]] + 1 (len xs)\nlen [1 2 3]";
+            assert_eq!(eval_str(input), "3");
+        }
+
+        #[test]
+        fn works_with_currying() {
+            let input = "let f fn [1 x] x | [_ x] * 2 x\nlist.map (f 2) [1 2]";
+            assert_eq!(eval_str(input), "[ 2 4 ]");
+        }
+
+        #[test]
+        fn no_matching_clause_is_an_error() {
+            let err = eval_err("let f fn [0] :zero | [1] :one\nf 5");
+            assert!(matches!(err, RuntimeError::NoMatchingClause(_)));
+        }
+    }
+
+    // ==================== Tail calls ====================
+
+    mod tail_calls {
+        use super::*;
+
+        #[test]
+        fn through_if_branches() {
+            let input = "let count fn [n acc] if (= n 0) acc (count (- 1 n) (+ acc 1))\ncount 20000 0";
+            assert_eq!(eval_str(input), "20000");
+        }
+
+        #[test]
+        fn through_blocks_and_lazy_branches() {
+            let input = "let loop fn [n] '(\n  let m (- 1 n)\n  if (= m 0) :done #( loop m )\n)\nloop 20000";
+            assert_eq!(eval_str(input), "done");
+        }
+
+        #[test]
+        fn through_match_and_clauses() {
+            let input = "let down fn [n] match n { 0 :zero x (down (- 1 x)) }\ndown 20000";
+            assert_eq!(eval_str(input), "zero");
+
+            let input = "let down fn [0] :zero | [n] down (- 1 n)\ndown 20000";
+            assert_eq!(eval_str(input), "zero");
+        }
+
+        #[test]
+        fn lazy_result_of_if_branch_is_forced() {
+            let input = "let f fn [x] #( + x 1 )\nlet g fn [x] if true (f x)\ng 1";
+            assert_eq!(eval_str(input), "2");
+        }
+    }
+
+    // ==================== Error locations ====================
+
+    mod traces {
+        use super::*;
+        use crate::parser::Span;
+
+        fn eval_traced(input: &str) -> RuntimeError {
+            match eval(input) {
+                Err(EvalError::Runtime(e)) => e,
+                Err(e) => panic!("Expected runtime error, got {}", e),
+                Ok(val) => panic!("Expected runtime error, got value {}", val),
+            }
+        }
+
+        #[test]
+        fn error_has_location() {
+            let err = eval_traced("print :ok\n  string.upper 1");
+            match err {
+                RuntimeError::Traced(trace) => {
+                    assert_eq!(trace.span, Span::new(2, 3));
+                    assert!(trace.stack.is_empty());
+                    assert!(matches!(trace.error, RuntimeError::MissmatchedTypes { .. }));
+                }
+                e => panic!("Expected traced error, got {}", e),
+            }
+        }
+
+        #[test]
+        fn records_call_stack() {
+            let input = "let inner fn [x] string.upper x\nlet outer fn [y] '(\n  inner y\n  y\n)\nouter 5";
+            match eval_traced(input) {
+                RuntimeError::Traced(trace) => {
+                    assert_eq!(trace.span, Span::new(1, 18));
+                    let frames: Vec<(&str, Span)> = trace
+                        .stack
+                        .iter()
+                        .map(|f| (f.name.as_str(), f.span))
+                        .collect();
+                    assert_eq!(frames, vec![("inner", Span::new(3, 3)), ("outer", Span::new(6, 1))]);
+                }
+                e => panic!("Expected traced error, got {}", e),
+            }
+        }
+
+        #[test]
+        fn report_shows_source_line() {
+            let input = "let f fn [x] string.upper x\nf 1";
+            let report = eval_traced(input).report("script.shk", input);
+            let expected = [
+                "EvaluationError: Missmatched types: expected String, got Integer",
+                "  --> script.shk:1:14",
+                "  |",
+                "1 | let f fn [x] string.upper x",
+                "  |              ^",
+                "  at f (script.shk:2:1)",
+            ];
+            assert_eq!(report, expected.join("\n"));
+        }
+
+        #[test]
+        fn keeps_frames_of_tail_calls() {
+            let input = "let h fn [x] string.upper x\nlet g fn [x] h x\nlet f fn [x] g x\nf 1";
+            match eval_traced(input) {
+                RuntimeError::Traced(trace) => {
+                    let frames: Vec<&str> = trace.stack.iter().map(|f| f.name.as_str()).collect();
+                    assert_eq!(frames, vec!["h", "g", "f"]);
+                }
+                e => panic!("Expected traced error, got {}", e),
+            }
+        }
+
+        #[test]
+        fn counts_tail_calls_which_are_not_kept() {
+            let input = "let loop fn [n] if (= n 0) (string.upper n) (loop (- 1 n))\nloop 20";
+            let report = eval_traced(input).report("script.shk", input);
+            assert_eq!(report.matches("  at loop (script.shk:1:46)").count(), 8);
+            assert!(report.contains("  ... 12 tail calls\n  at loop (script.shk:2:1)"));
+        }
+    }
+
+    // ==================== Errors ====================
+
+    mod errors {
+        use super::*;
+
+        fn error_field(input: &str, key: &str) -> String {
+            let val = eval(input).unwrap_or_else(|e| panic!("Evaluation of {:?} failed: {}", input, e));
+            match val.as_ref() {
+                Value::Object(obj) => obj[key].to_string(),
+                val => panic!("Expected error object, got {}", val),
+            }
+        }
+
+        #[test]
+        fn error_raises_custom_error() {
+            let err = eval_err("error \"config is missing\"");
+            assert_eq!(err.to_string(), "Error: config is missing");
+
+            let err = eval_err("error { :title :NotFound :message \"no such file\" }");
+            assert_eq!(err.to_string(), "NotFound: no such file");
+        }
+
+        #[test]
+        fn try_passes_error_object_to_handler() {
+            let input = "try (error { :title :NotFound :message :gone :data 42 }) (fn [e] e)";
+            assert_eq!(error_field(input, "title"), "NotFound");
+            assert_eq!(error_field(input, "message"), "gone");
+            assert_eq!(error_field(input, "data"), "42");
+        }
+
+        #[test]
+        fn try_catches_runtime_errors() {
+            let input = "let f fn [x] string.upper x\ntry (f 1) (fn [e] e)";
+            assert_eq!(error_field(input, "title"), "EvaluationError");
+            assert_eq!(error_field(input, "data"), "null");
+        }
+
+        #[test]
+        fn try_returns_body_value_without_error() {
+            assert_eq!(eval_str("try (+ 1 2) (fn [e] 0)"), "3");
+            assert_eq!(eval_str("try (error :x) :default"), "default");
+        }
+
+        #[test]
+        fn cleanup_runs_in_any_case() {
+            let input = "var cleaned false\ntry (error :x) (fn [e] :handled) #( set cleaned true )\ncleaned";
+            assert_eq!(eval_str(input), "true");
+
+            let interpretator = Interpretator::new();
+            evaluate("var cleaned false", &interpretator).unwrap();
+            let input = "try (error :x) (fn [e] error e) #( set cleaned true )";
+            assert!(evaluate(input, &interpretator).is_err());
+            assert_eq!(evaluate("cleaned", &interpretator).unwrap().to_string(), "true");
+        }
+    }
+
+    // ==================== Results ====================
+
+    mod results {
+        use super::*;
+
+        #[test]
+        fn results_display_payload() {
+            assert_eq!(eval_str("result.ok 1"), "ok(1)");
+            assert_eq!(eval_str("result.err :gone"), "err(gone)");
+        }
+
+        #[test]
+        fn map_and_then_skip_errors() {
+            assert_eq!(eval_str("result.ok 1 $> result.map (+ 1)"), "ok(2)");
+            assert_eq!(eval_str("result.err :x $> result.map (+ 1)"), "err(x)");
+            assert_eq!(eval_str("result.ok 1 $> result.and-then (fn [x] result.err x)"), "err(1)");
+            assert_eq!(eval_str("result.ok 1 $> result.and-then (+ 1)"), "ok(2)");
+        }
+
+        #[test]
+        fn reading_results() {
+            assert_eq!(eval_str("result.unwrap-or 0 (result.err :x)"), "0");
+            assert_eq!(eval_str("result.unwrap-or 0 (result.ok 5)"), "5");
+            assert_eq!(eval_str("result.is-ok? (result.ok 5)"), "true");
+            assert_eq!(eval_str("result.is-err? (result.ok 5)"), "false");
+            assert_eq!(eval_err("result.unwrap (result.err :broken)").to_string(), "Error: broken");
+        }
+
+        fn error_field(input: &str, key: &str) -> String {
+            match eval(input).unwrap().as_ref() {
+                Value::Object(obj) => obj[key].to_string(),
+                val => panic!("Expected error object, got {}", val),
+            }
+        }
+
+        #[test]
+        fn fallible_variants_return_results() {
+            assert_eq!(eval_str("result.is-err? (file.read? \"/no/such/file\")"), "true");
+            assert_eq!(error_field("result.error (file.read? \"/no/such/file\")", "title"), "RuntimeError");
+            assert_eq!(eval_str("shell? \"echo hi\""), "ok(hi\n)");
+            assert_eq!(error_field("result.error (shell? \"exit 3\")", "title"), "ShellError");
+            assert_eq!(error_field("result.error (shell? \"exit 3\")", "data"), "3");
+        }
+
+        #[test]
+        fn commands_exiting_with_an_error_give_err() {
+            assert_eq!(eval_str("shell.lines? \"echo a; echo b\""), "ok([ a b ])");
+            assert_eq!(error_field("result.error (shell.lines? :false)", "title"), "ShellError");
+            assert_eq!(error_field("result.error (shell.full? \"exit 4\")", "data"), "4");
+            assert_eq!(eval_str("shell.full? \"echo a\" $> result.map (obj.get :code)"), "ok(0)");
+
+            let input = "shell.stream-lines? \"echo a; exit 5\" $> result.unwrap $> stream.collect";
+            let err = eval_err(input);
+            assert!(err.to_string().contains("command exited with code 5"), "{}", err);
+            assert_eq!(eval_str("shell.stream-lines? \"echo a\" $> result.map stream.collect"), "ok([ a ])");
+        }
+    }
+
+    // ==================== Imports ====================
+
+    mod imports {
+        use super::*;
+        use std::fs;
+        use std::path::PathBuf;
+
+        /// Writes the files to a fresh directory, returns the interpretator running `main.shk` there
+        fn project(name: &str, files: &[(&str, &str)]) -> std::rc::Rc<Interpretator> {
+            let dir = std::env::temp_dir().join(format!("shik-imports-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            for (file, content) in files {
+                let path = dir.join(file);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, content).unwrap();
+            }
+
+            let interpretator = Interpretator::new();
+            let main: PathBuf = dir.join("main.shk");
+            interpretator.modules.borrow_mut().set_main(&main);
+            interpretator
+        }
+
+        #[test]
+        fn import_defines_names_with_prefix() {
+            let inter = project("prefix", &[("lib/git.shk", "let branch fn [name] \"origin/{name}\"")]);
+            let val = evaluate("import \"lib/git.shk\"\ngit.branch :main", &inter).unwrap();
+            assert_eq!(val.to_string(), "origin/main");
+
+            let val = evaluate("import \"lib/git\" :g\ng.branch :dev", &inter).unwrap();
+            assert_eq!(val.to_string(), "origin/dev");
+        }
+
+        #[test]
+        fn module_is_evaluated_once() {
+            let inter = project("once", &[("util.shk", "let id fn [x] x")]);
+            let first = evaluate("import :util", &inter).unwrap();
+            let second = evaluate("import :util :u", &inter).unwrap();
+            match (first.as_ref(), second.as_ref()) {
+                (Value::Object(first), Value::Object(second)) => {
+                    assert!(std::rc::Rc::ptr_eq(&first["id"], &second["id"]))
+                }
+                _ => panic!("Expected module objects"),
+            }
+        }
+
+        #[test]
+        fn relative_imports_follow_the_importing_file() {
+            let inter = project(
+                "relative",
+                &[
+                    ("lib/paths.shk", "import \"./strings\"\nlet slug strings.dash"),
+                    ("lib/strings.shk", "let dash fn [s] \"-{s}-\""),
+                ],
+            );
+            let val = evaluate("import \"lib/paths\"\npaths.slug :a", &inter).unwrap();
+            assert_eq!(val.to_string(), "-a-");
+        }
+
+        #[test]
+        fn import_cycle_is_an_error() {
+            let inter = project("cycle", &[("a.shk", "import :b"), ("b.shk", "import :a")]);
+            match evaluate("import :a", &inter) {
+                Err(EvalError::Runtime(e)) => assert!(e.to_string().contains("import cycle")),
+                _ => panic!("Expected import cycle error"),
+            }
+        }
+
+        #[test]
+        fn missing_module_is_an_error() {
+            let inter = project("missing", &[]);
+            match evaluate("import :nowhere", &inter) {
+                Err(EvalError::Runtime(e)) => {
+                    assert_eq!(e.into_root().to_string(), "ImportError: cannot find module 'nowhere'")
+                }
+                _ => panic!("Expected missing module error"),
+            }
+        }
+    }
+
+    // ==================== Macros ====================
+
+    mod macros {
+        use super::*;
+
+        #[test]
+        fn macro_builds_code_from_arguments() {
+            let input = "let unless macro [cond body] quote (if (not ~cond) ~body)\nunless (= 1 2) :ran";
+            assert_eq!(eval_str(input), "ran");
+
+            let input = "let unless macro [cond body] quote (if (not ~cond) ~body)\nunless (= 1 1) (error :evaluated)";
+            assert_eq!(eval_str(input), "null");
+        }
+
+        #[test]
+        fn expansion_runs_in_caller_environment() {
+            let input = "let twice macro [body] quote '(\n~body\n~body\n)\nvar x 0\ntwice (set x (+ x 1))\nx";
+            assert_eq!(eval_str(input), "2");
+
+            let input = "let defn macro [name value] \"let {name} {value}\"\ndefn answer (+ 40 2)\nanswer";
+            assert_eq!(eval_str(input), "42");
+        }
+
+        #[test]
+        fn quote_puts_values_into_code() {
+            assert_eq!(eval_str("let n 3\nquote (list.range 1 ~n)"), "list.range 1 3");
+            assert_eq!(eval_str("let xs [1 :a]\nquote (f ~xs)"), "f [1 \"a\"]");
+            assert_eq!(eval_str("let all macro [...xs] quote (list.sum ~xs)\nall 1 2 3"), "6");
+        }
+
+        #[test]
+        fn macro_errors() {
+            let err = eval_err("let m macro [a b] quote ~a\nm 1");
+            assert!(matches!(err, RuntimeError::MacroError(_)));
+
+            let err = eval_err("let m macro [a] 10\nm 1");
+            assert_eq!(err.to_string(), "MacroError: macro must return code, got Integer");
+
+            let err = eval_err("~x");
+            assert!(matches!(err, RuntimeError::MacroError(_)));
+        }
+    }
+
+    // ==================== Objects ====================
+
+    mod objects {
+        use super::*;
+
+        #[test]
+        fn get_has_and_nested_paths() {
+            assert_eq!(eval_str("obj.get :name { :name :bob }"), "bob");
+            assert_eq!(eval_str("obj.get :age { :name :bob }"), "null");
+            assert_eq!(eval_str("obj.has? :name { :name :bob }"), "true");
+            assert_eq!(eval_str("obj.get-in [ :a :b 1 ] { :a { :b [ 10 20 ] } }"), "20");
+            assert_eq!(eval_str("obj.get-in [ :a :x ] { :a { :b 1 } }"), "null");
+        }
+
+        #[test]
+        fn updates_make_new_objects() {
+            let input = "let user { :name :bob }\nlet renamed (obj.set :name user :alice)\n[ (obj.get :name user) (obj.get :name renamed) ]";
+            assert_eq!(eval_str(input), "[ bob alice ]");
+
+            assert_eq!(eval_str("obj.remove :a { :a 1 } $> obj.keys"), "[ ]");
+            assert_eq!(eval_str("obj.merge { :a 1 :b 2 } { :b 3 } $> obj.get :b"), "3");
+
+            let input = "obj.set-in [ :server :port ] { :server { :host :localhost } } 8080";
+            assert_eq!(eval_str(&format!("{} $> obj.get-in [ :server :port ]", input)), "8080");
+            assert_eq!(eval_str(&format!("{} $> obj.get-in [ :server :host ]", input)), "localhost");
+        }
+
+        #[test]
+        fn set_in_follows_list_indexes() {
+            let input = "let config { :items [ { :name :a } { :name :b } ] }\nobj.set-in [ :items 1 :name ] config :c";
+            assert_eq!(eval_str(&format!("{} $> obj.get-in [ :items 1 :name ]", input)), "c");
+            assert_eq!(eval_str(&format!("{} $> obj.get-in [ :items 0 :name ]", input)), "a");
+
+            let err = eval_err("obj.set-in [ :items 2 ] { :items [ 1 ] } 0");
+            assert!(matches!(err, RuntimeError::IndexOutOfBounds { index: 2 }));
+            let err = eval_err("obj.set-in [ :a 0 ] { :a 1 } 0");
+            assert!(matches!(err, RuntimeError::MissmatchedTypes { .. }));
+        }
+
+        #[test]
+        fn path_indexes_are_whole_numbers_from_zero() {
+            assert_eq!(eval_str("obj.get-in [ :a 1.0 ] { :a [ 1 2 ] }"), "2");
+            for path in ["[ :a -1 ]", "[ :a 0.5 ]"] {
+                let err = eval_err(&format!("obj.get-in {} {{ :a [ 1 2 ] }}", path));
+                assert!(err.to_string().contains("path index must be a whole number"), "{}", err);
+                let err = eval_err(&format!("obj.set-in {} {{ :a [ 1 2 ] }} 0", path));
+                assert!(err.to_string().contains("path index must be a whole number"), "{}", err);
+            }
+        }
+
+        #[test]
+        fn entries_round_trip() {
+            assert_eq!(eval_str("obj.entries { :a 1 }"), "[ [ a 1 ] ]");
+            assert_eq!(eval_str("obj.from-entries [ [ :a 1 ] ] $> obj.get :a"), "1");
+            assert_eq!(eval_str("obj.values { :a 1 }"), "[ 1 ]");
+        }
+
+        #[test]
+        fn higher_order_functions() {
+            assert_eq!(eval_str("obj.map-values (+ 1) { :a 1 } $> obj.get :a"), "2");
+            assert_eq!(eval_str("obj.filter (fn [k v] < 1 v) { :a 1 :b 2 } $> obj.keys"), "[ b ]");
+            assert_eq!(eval_str("obj.filter (fn [k v] = k :a) { :a 1 :b 2 } $> obj.keys"), "[ a ]");
+        }
+    }
+
+    // ==================== Object order ====================
+
+    mod object_order {
+        use super::*;
+
+        #[test]
+        fn objects_keep_insertion_order() {
+            let input = "{ :zeta 1 :alpha 2 :mid 3 }";
+            assert_eq!(eval_str(&format!("obj.keys {}", input)), "[ zeta alpha mid ]");
+            assert_eq!(eval_str(input), "{zeta: 1,\nalpha: 2,\nmid: 3,\n}");
+
+            let input = "obj.set :c (obj.set :b { :c 1 :a 2 } 3) 4 $> obj.keys";
+            assert_eq!(eval_str(input), "[ c a b ]");
+            assert_eq!(eval_str("obj.remove :c { :c 1 :a 2 :b 3 } $> obj.keys"), "[ a b ]");
+        }
+
+        #[test]
+        fn sorted_view() {
+            assert_eq!(eval_str("obj.sorted { :b 1 :c 2 :a 3 } $> obj.keys"), "[ a b c ]");
+            assert_eq!(eval_str("obj.sorted { :b 1 :a 2 } $> obj.values"), "[ 2 1 ]");
+        }
+    }
+
+    // ==================== Refs ====================
+
+    mod refs {
+        use super::*;
+
+        #[test]
+        fn closures_share_the_cell() {
+            let input = "let counter (ref 0)\nlet inc (fn [] ref.update counter (+ 1))\ninc\ninc\nref.get counter";
+            assert_eq!(eval_str(input), "2");
+        }
+
+        #[test]
+        fn set_and_update_return_new_value() {
+            assert_eq!(eval_str("let r (ref 1)\nref.set r 5"), "5");
+            assert_eq!(eval_str("let r (ref [ 1 ])\nref.update r (list.concat [ 2 ])"), "[ 2 1 ]");
+            assert_eq!(eval_str("ref :x"), "ref(x)");
+        }
+
+        #[test]
+        fn accumulator_across_lambdas() {
+            let input = "let seen (ref [])\nlist.iterate (fn [x] ref.update seen (fn [acc] list.concat acc [ (* 2 x) ])) [ 1 2 3 ]\nref.get seen";
+            assert_eq!(eval_str(input), "[ 2 4 6 ]");
+        }
+
+        #[test]
+        fn expects_a_ref() {
+            let err = eval_err("ref.get 1");
+            assert!(matches!(err, RuntimeError::MissmatchedTypes { .. }));
+        }
+    }
+
+    // ==================== Lists ====================
+
+    mod lists {
+        use super::*;
+
+        #[test]
+        fn slicing() {
+            assert_eq!(eval_str("list.tail [ 1 2 3 ]"), "[ 2 3 ]");
+            assert_eq!(eval_str("list.init [ 1 2 3 ]"), "[ 1 2 ]");
+            assert_eq!(eval_str("list.take 2 [ 1 2 3 ]"), "[ 1 2 ]");
+            assert_eq!(eval_str("list.drop 2 [ 1 2 3 ]"), "[ 3 ]");
+            assert_eq!(eval_str("list.take 5 [ 1 2 ]"), "[ 1 2 ]");
+            assert_eq!(eval_str("list.drop 5 [ 1 2 ]"), "[ ]");
+            assert_eq!(eval_str("list.concat [ 1 2 ] [ 3 ]"), "[ 1 2 3 ]");
+        }
+
+        #[test]
+        fn mutation_does_not_leak_into_slices() {
+            let input = "var lst [ 1 2 3 ]\nlet t (list.tail lst)\nlist.push lst 4\nlist.set 0 lst 9\nlist.<push lst 0\nlist.concat t lst";
+            assert_eq!(eval_str(input), "[ 2 3 0 9 2 3 4 ]");
+        }
+
+        #[test]
+        fn head_tail_recursion_over_long_list() {
+            let input = "let count (fn [acc []] acc | [acc [x ...rest]] count (+ 1 acc) rest)\ncount 0 (list.range 0 20000)";
+            assert_eq!(eval_str(input), "20000");
+
+            let input = "let sum (fn [acc lst] if (list.empty? lst) acc (sum (+ acc (list.head lst)) (list.tail lst)))\nsum 0 (list.range 0 20000)";
+            assert_eq!(eval_str(input), "199990000");
+        }
+    }
+
+    // ==================== Virtual machine ====================
+
+    mod vm {
+        use super::*;
+
+        #[test]
+        fn deep_recursion_out_of_tail_position() {
+            let input = "let len (fn [[]] 0 | [[x ...rest]] + 1 (len rest))\nlen (list.range 0 50000)";
+            assert_eq!(eval_str(input), "50000");
+
+            let input = "let f (fn [n] if (= n 0) 0 #(+ 1 (f (- 1 n))))\nf 50000";
+            assert_eq!(eval_str(input), "50000");
+        }
+
+        #[test]
+        fn special_form_arguments_are_reevaluated() {
+            let input = "var i 0\nvar seen []\nwhile #(\n  list.push seen (if (= i 1) :one :other)\n  set i (+ i 1)\n  > 3 i\n)\nseen";
+            assert_eq!(eval_str(input), "[ other one other ]");
+        }
+
+        #[test]
+        fn closures_share_compiled_body() {
+            let input = "let adder (fn [n] fn [x] + n x)\nlet add1 (adder 1)\nlet add10 (adder 10)\n[ (add1 1) (add10 1) (add1 2) ]";
+            assert_eq!(eval_str(input), "[ 2 11 3 ]");
+        }
+
+        #[test]
+        fn string_literals_are_not_shared() {
+            let input = "let make (fn [] \"ab\")\nlet s (make)\nstring.push s :c\nmake";
+            assert_eq!(eval_str(input), "ab");
+        }
+
+        #[test]
+        fn special_form_arguments_share_the_program() {
+            use crate::eval::compiler::compile;
+            use crate::parser::{parse, Expression};
+            use std::rc::Rc;
+
+            let program = parse("if ready (list.map (fn [x] x) items)").unwrap();
+            let Expression::Application { argument, .. } = &program.statements[0].expression else {
+                panic!("expected an application");
+            };
+
+            let code = compile(&program.statements[0].expression);
+            let shared = code.args.iter().any(|slot| Rc::ptr_eq(&slot.argument().expr, argument));
+            assert!(shared);
+        }
+    }
+
+    // ==================== Resolver ====================
+
+    mod resolver {
+        use super::*;
+        use crate::eval::resolver::check;
+        use crate::parser::{parse, Span};
+
+        #[test]
+        fn undefined_names_are_reported_before_running() {
+            let interpretator = Interpretator::new();
+            let err = evaluate("var ran true\nlet f (fn [x] + x y)", &interpretator).unwrap_err();
+
+            assert!(err.to_string().contains("Undefined variable 'y'"));
+            assert!(err.to_string().contains("line 2"));
+            assert!(interpretator.ctx.lookup("ran").is_none());
+        }
+
+        #[test]
+        fn names_defined_anywhere_are_known() {
+            assert_eq!(eval_str("let f (fn [] g)\nlet g 1\nf"), "1");
+            assert_eq!(eval_str("if true (var x 1) null\nset x 2\nx"), "2");
+            assert_eq!(eval_str("quote (nope 1)"), "nope 1");
+        }
+
+        #[test]
+        fn locals_are_addressed_by_slots() {
+            let input = "let x 1\nlet f (fn [x] fn [y] match y { [a b] (+ x (+ a b)) })\nf 10 [2 3]";
+            assert_eq!(eval_str(input), "15");
+
+            let input = "let count (fn [n] '(\n  var i 0\n  while #(\n    set i (+ i 1)\n    > n i\n  )\n  i\n))\ncount 5";
+            assert_eq!(eval_str(input), "5");
+        }
+
+        #[test]
+        fn slot_is_empty_until_defined() {
+            let input = "let x 1\nlet f (fn [] '(\n  let y x\n  let x 2\n  [y x]\n))\nf";
+            assert_eq!(eval_str(input), "[ 1 2 ]");
+        }
+
+        #[test]
+        fn names_defined_by_macros_are_found() {
+            let input = "let def macro [name] \"let {name} 5\"\nlet f (fn [a] '(\n  def x\n  + a x\n))\nf 1";
+            assert_eq!(eval_str(input), "6");
+        }
+
+        #[test]
+        fn special_forms_applied_to_values_see_locals() {
+            let input = "let f (fn [k xs] list.map (if (= k 1) :one) xs)\n[ (f 1 [:a]) (f 2 [:a :b]) ]";
+            assert_eq!(eval_str(input), "[ [ one ] [ null null ] ]");
+        }
+
+        #[test]
+        fn shadowing_builtins_is_a_warning() {
+            let interpretator = Interpretator::new();
+            let program = parse("let print 1\nlet f (fn [true] list.map (fn [x] x) [])").unwrap();
+            let warnings = check(&program, &interpretator.ctx, &interpretator.builtins).unwrap();
+
+            let found: Vec<(String, Span)> =
+                warnings.into_iter().map(|w| (w.message, w.span)).collect();
+            assert_eq!(
+                found,
+                vec![
+                    ("'print' shadows a builtin".to_string(), Span::new(1, 1)),
+                    ("'true' shadows a builtin".to_string(), Span::new(2, 1)),
+                ]
+            );
+        }
+
+        #[test]
+        fn warnings_are_kept_for_the_caller() {
+            let interpretator = Interpretator::new();
+            evaluate("let print 1\nprint", &interpretator).unwrap();
+
+            let warnings = interpretator.take_warnings();
+            assert_eq!(warnings.len(), 1);
+            assert_eq!(warnings[0].message, "'print' shadows a builtin");
+            assert!(interpretator.take_warnings().is_empty());
+        }
+    }
+
+    // ==================== Cycle collection ====================
+
+    mod gc {
+        use super::*;
+        use std::rc::Rc;
+
+        const MAKE: &str = "let make (fn [] '(\n  let loop (fn [n] if (= n 0) :done (loop (- 1 n)))\n  loop\n))";
+
+        #[test]
+        fn local_recursive_functions_are_collected() {
+            let interpretator = Interpretator::new();
+            let f = evaluate(&format!("{}\nmake", MAKE), &interpretator).unwrap();
+            let Value::Lambda(closure) = f.as_ref() else {
+                panic!("Expected lambda, got {}", f);
+            };
+            let env = Rc::downgrade(&closure.env);
+
+            drop(f);
+            assert!(env.upgrade().is_some(), "function keeps its environment alive");
+            assert_eq!(interpretator.heap.collect(), 1);
+            assert!(env.upgrade().is_none());
+        }
+
+        #[test]
+        fn reachable_functions_keep_working() {
+            let interpretator = Interpretator::new();
+            let f = evaluate(&format!("{}\nlet g make\nmake", MAKE), &interpretator).unwrap();
+
+            assert_eq!(interpretator.heap.collect(), 0);
+            let res = interpretator.apply_fn(&f, &Rc::new(Value::Number(3.0))).unwrap();
+            assert_eq!(res.to_string(), "done");
+            assert_eq!(evaluate("g 5", &interpretator).unwrap().to_string(), "done");
+        }
+
+        #[test]
+        fn cycles_through_ref_cells_are_collected() {
+            let interpretator = Interpretator::new();
+            let input = "let tie (fn [cell] ref.set cell (fn [] cell))\ntie (ref null)";
+            let f = evaluate(input, &interpretator).unwrap();
+            let Value::Lambda(closure) = f.as_ref() else {
+                panic!("Expected lambda, got {}", f);
+            };
+            let env = Rc::downgrade(&closure.env);
+
+            drop(f);
+            assert_eq!(interpretator.heap.collect(), 1);
+            assert!(env.upgrade().is_none());
+        }
+
+        #[test]
+        fn cycles_through_streams_are_collected() {
+            let interpretator = Interpretator::new();
+            let input = "let make (fn [] '(\n  let s (stream.from 0 $> stream.map (fn [x] s))\n  s\n))\nmake";
+            let s = evaluate(input, &interpretator).unwrap();
+            let Value::Stream(stream) = s.as_ref() else {
+                panic!("Expected stream, got {}", s);
+            };
+            let func = stream.values().remove(0);
+            let Value::Lambda(closure) = func.as_ref() else {
+                panic!("Expected lambda, got {}", func);
+            };
+            let env = Rc::downgrade(&closure.env);
+
+            drop(func);
+            drop(s);
+            assert!(env.upgrade().is_some(), "stream keeps its function alive");
+            assert_eq!(interpretator.heap.collect(), 1);
+            assert!(env.upgrade().is_none());
+        }
+
+        #[test]
+        fn collection_runs_while_the_program_runs() {
+            let interpretator = Interpretator::new();
+            let input = format!(
+                "{}\nvar i 0\nwhile #(\n  make 1\n  set i (+ i 1)\n  > 12000 i\n)\ni",
+                MAKE
+            );
+            assert_eq!(evaluate(&input, &interpretator).unwrap().to_string(), "12000");
+            assert!(interpretator.heap.len() < 12000);
+        }
+    }
+
+    // ==================== Types ====================
+
+    mod types {
+        use super::*;
+        use crate::eval::types::check;
+        use crate::eval::value::ValueType;
+        use crate::parser::{parse, Span};
+
+        /// Errors found by the type checker, with the places they are reported at
+        fn type_errors(input: &str) -> Vec<(String, Span)> {
+            let interpretator = Interpretator::new();
+            let program = parse(input).unwrap();
+            check(&program, &interpretator.ctx)
+                .into_iter()
+                .map(|e| match e {
+                    RuntimeError::Traced(trace) => (trace.error.to_string(), trace.span),
+                    e => panic!("Expected error with a place, got {}", e),
+                })
+                .collect()
+        }
+
+        #[test]
+        fn annotations_do_not_change_evaluation() {
+            assert_eq!(eval_str("let n:Number 5\nlet f (fn [x:Number y] + x y)\nf n 2"), "7");
+            assert_eq!(eval_str("match 3 { x:Number (* x 2) }"), "6");
+        }
+
+        #[test]
+        fn native_signatures_are_checked() {
+            let errors = type_errors("print :ok\nnumber.sqrt :nine\nlet s (string.len 12)");
+            assert_eq!(
+                errors,
+                vec![
+                    (
+                        "EvaluationError: Missmatched types: expected Number, got String"
+                            .to_string(),
+                        Span::new(2, 1)
+                    ),
+                    (
+                        "EvaluationError: Missmatched types: expected String, got Integer"
+                            .to_string(),
+                        Span::new(3, 8)
+                    ),
+                ]
+            );
+        }
+
+        #[test]
+        fn types_are_inferred_through_functions() {
+            let input = "let twice (fn [x:Number] * x 2)\nlet s (twice 3)\nstring.upper s";
+            assert_eq!(type_errors(input).len(), 1);
+
+            let input = "let f (fn [a:String b] string.+ a b)\nf 1 :x\nf :x 1";
+            assert_eq!(type_errors(input).len(), 1);
+
+            assert!(type_errors("let n (list.len [1 2])\nnumber.abs n $> string").is_empty());
+            assert!(type_errors("number.div 2 (shell! :true)").is_empty());
+            assert!(type_errors("number.div 2 (file.size.deep :.)").is_empty());
+        }
+
+        #[test]
+        fn annotated_names_are_checked() {
+            let errors = type_errors("let n:Number :five\nlet m:Numbr 5");
+            assert_eq!(errors.len(), 2);
+            assert!(errors[0].0.contains("expected Number, got String"));
+            assert_eq!(errors[1].0, "TypeError: Unknown type 'Numbr'");
+        }
+
+        #[test]
+        fn changed_names_may_have_any_type() {
+            let input = "var x 1\nset x :one\nstring.upper x";
+            assert!(type_errors(input).is_empty());
+            assert_eq!(eval_str(input), "ONE");
+        }
+
+        #[test]
+        fn integer_patterns_accept_floats() {
+            let input = "let zero (fn [0] :zero)\nzero 0.0";
+            assert!(type_errors(input).is_empty());
+            assert_eq!(eval_str(input), "zero");
+        }
+
+        #[test]
+        fn runtime_types_match_the_checked_ones() {
+            assert!(matches!(
+                eval_err("number.sqrt :nine"),
+                RuntimeError::MissmatchedTypes {
+                    got: ValueType::String,
+                    expected: ValueType::Number
+                }
+            ));
+        }
+    }
+
+    // ==================== String builders ====================
+
+    mod builders {
+        use super::*;
+        use crate::eval::value::ValueType;
+
+        #[test]
+        fn appends_in_place() {
+            let input = "let out string.builder\nlist.iterate (fn [x] string.append out \"{x},\") [ 1 2 3 ]\nstring.finish out";
+            assert_eq!(eval_str(input), "1,2,3,");
+        }
+
+        #[test]
+        fn appends_any_value() {
+            let input = "let out string.builder\nstring.append out [ 1 ]\nstring.append out 2\nstring.finish out";
+            assert_eq!(eval_str(input), "[ 1 ]2");
+        }
+
+        #[test]
+        fn every_reference_makes_a_new_builder() {
+            let input = "let a string.builder\nlet b string.builder\nstring.append a :x\nstring.finish b";
+            assert_eq!(eval_str(input), "");
+        }
+
+        #[test]
+        fn builders_are_interpolated() {
+            let input = "let out string.builder\nstring.append out :ab\n\"<{out}> {1}\"";
+            assert_eq!(eval_str(input), "<ab> 1");
+        }
+
+        #[test]
+        fn expects_a_builder() {
+            let err = eval_err("string.append :x :y");
+            assert!(matches!(
+                err,
+                RuntimeError::MissmatchedTypes {
+                    got: ValueType::String,
+                    expected: ValueType::Builder
+                }
+            ));
+        }
+    }
+
+    // ==================== Long strings ====================
+
+    mod long_strings {
+        use super::*;
+
+        #[test]
+        fn joins_many_pieces() {
+            let input = "var out \"\"\nstream.range 50000 $> stream.iterate (fn [_] set out (string.+ out \"line\\n\"))\nstring.len out";
+            assert_eq!(eval_str(input), "250000");
+        }
+
+        #[test]
+        fn interpolates_long_strings() {
+            let input = "var out :x\nstream.range 50000 $> stream.iterate (fn [_] set out \"<{out}>\")\nstring.len out";
+            assert_eq!(eval_str(input), "100001");
+        }
+
+        #[test]
+        fn joined_string_keeps_its_parts() {
+            let input = "var a :x\nstream.range 300 $> stream.iterate (fn [_] string.push a :x)\nlet b (string.+ a :y)\nstring.push a :zz\n\"{string.len a} {string.len b} {string.slice 300 302 b}\"";
+            assert_eq!(eval_str(input), "303 302 xy");
+        }
+    }
+
+    // ==================== Integers ====================
+
+    mod integers {
+        use super::*;
+
+        #[test]
+        fn literals_without_fraction_are_integers() {
+            assert!(matches!(eval("42").unwrap().as_ref(), Value::Integer(_)));
+            assert!(matches!(eval("4.0").unwrap().as_ref(), Value::Number(_)));
+            assert_eq!(eval_str("4.0"), "4.0");
+            assert_eq!(eval_str("quote (f 4.0 4)"), "f 4.0 4");
+        }
+
+        #[test]
+        fn integers_grow_without_losing_precision() {
+            assert_eq!(eval_str("* 9007199254740993 1"), "9007199254740993");
+            assert_eq!(eval_str("* 9223372036854775807 4"), "36893488147419103228");
+            assert_eq!(eval_str("^ 64 2"), "18446744073709551616");
+            assert_eq!(eval_str("- 18446744073709551616 (^ 64 2)"), "0");
+        }
+
+        #[test]
+        fn floats_make_the_result_float() {
+            assert_eq!(eval_str("+ 1 0.5"), "1.5");
+            assert_eq!(eval_str("* 2 1.5"), "3.0");
+            assert_eq!(eval_str("/ 2 7"), "3.5");
+            assert_eq!(eval_str("/ 2 10"), "5.0");
+            assert_eq!(eval_str("number.sqrt 16"), "4.0");
+            assert_eq!(eval_str("list.sum [ 1 2 3 ]"), "6");
+            assert_eq!(eval_str("list.sum [ 1 2.5 ]"), "3.5");
+        }
+
+        #[test]
+        fn integer_division() {
+            assert_eq!(eval_str("number.div 2 7"), "3");
+            assert_eq!(eval_str("number.div 2 -7"), "-3");
+            assert_eq!(eval_str("% 3 -7"), "-1");
+            assert!(matches!(eval_err("number.div 0 1"), RuntimeError::Arithmetic(_)));
+            assert!(matches!(eval_err("% 0 1"), RuntimeError::Arithmetic(_)));
+        }
+
+        #[test]
+        fn bitwise_operations() {
+            assert_eq!(eval_str("number.and 10 12"), "8");
+            assert_eq!(eval_str("number.or 10 12"), "14");
+            assert_eq!(eval_str("number.xor 10 12"), "6");
+            assert_eq!(eval_str("number.not 0"), "-1");
+            assert_eq!(eval_str("number.shl 64 1"), "18446744073709551616");
+            assert_eq!(eval_str("number.shr 2 -7"), "-2");
+            assert!(matches!(eval_err("number.shl -1 1"), RuntimeError::Arithmetic(_)));
+            assert!(matches!(eval_err("number.shl 10000000000 1"), RuntimeError::Arithmetic(_)));
+            assert_eq!(eval_str("number.shr 10000000000 -7"), "-1");
+            assert!(matches!(eval_err("^ 200000000 7"), RuntimeError::Arithmetic(_)));
+            assert_eq!(eval_str("^ 200000001 -1"), "-1");
+            assert_eq!(eval_str("string.len \"{(^ 10000 7)}\""), "8451");
+        }
+
+        #[test]
+        fn conversions() {
+            assert_eq!(eval_str("number.floor 2.7"), "2");
+            assert_eq!(eval_str("number.round -2.5"), "-3");
+            assert_eq!(eval_str("number.int -2.7"), "-2");
+            assert_eq!(eval_str("number.float 3"), "3.0");
+            assert!(matches!(eval("number.floor 2").unwrap().as_ref(), Value::Integer(_)));
+        }
+
+        #[test]
+        fn integers_and_floats_compare_by_value() {
+            assert_eq!(eval_str("= 1 1.0"), "true");
+            assert_eq!(eval_str("< 2.5 3"), "true");
+            assert_eq!(eval_str("> 18446744073709551617 18446744073709551616"), "true");
+            assert_eq!(eval_str("number.max 2 1.5"), "2");
+            assert_eq!(eval_str("match 2.0 { 1 :one 2 :two _ :other }"), "two");
+        }
+    }
+
+    // ==================== Streams ====================
+
+    mod streams {
+        use super::*;
+
+        #[test]
+        fn ranges_are_collected() {
+            assert_eq!(eval_str("stream.range 4 $> stream.collect"), "[ 0 1 2 3 ]");
+            assert_eq!(eval_str("stream.range 10 0 -4 $> stream.collect"), "[ 10 6 2 ]");
+            assert!(matches!(eval_err("stream.range 0 5 0"), RuntimeError::Arithmetic(_)));
+        }
+
+        #[test]
+        fn endless_streams_are_read_on_demand() {
+            let input = "stream.from 1 $> stream.map (* 3) $> stream.filter (fn [x] = 0 (% 2 x)) $> stream.take 3 $> stream.collect";
+            assert_eq!(eval_str(input), "[ 6 12 18 ]");
+        }
+
+        #[test]
+        fn values_are_pulled_when_needed() {
+            let input = "let seen (ref 0)\nlet s (stream.from 0 $> stream.map (fn [x] ref.update seen (+ 1)))\nlet first (stream.take 2 s $> stream.collect)\nref.get seen";
+            assert_eq!(eval_str(input), "2");
+        }
+
+        #[test]
+        fn streams_are_read_once() {
+            let input = "let s (stream.range 5)\nstream.take 2 s $> stream.collect\nstream.collect s";
+            assert_eq!(eval_str(input), "[ 2 3 4 ]");
+        }
+
+        #[test]
+        fn fold_and_iterate() {
+            assert_eq!(eval_str("stream.range 1 101 $> stream.fold 0 +"), "5050");
+            let input = "let out string.builder\nstream.range 3 $> stream.iterate (string.append out)\nstring.finish out";
+            assert_eq!(eval_str(input), "012");
+        }
+
+        #[test]
+        fn file_lines_are_streamed() {
+            let path = std::env::temp_dir().join(format!("shik-stream-{}.txt", std::process::id()));
+            std::fs::write(&path, "one\r\ntwo\nthree").unwrap();
+            let input = format!("file.stream-lines \"{}\" $> stream.collect", path.display());
+            let res = eval_str(&input);
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(res, "[ one two three ]");
+            assert!(eval_str("file.stream-lines? :no-such-file.txt").starts_with("err("));
+        }
+
+        #[test]
+        fn command_lines_are_streamed() {
+            let input = "shell.stream-lines \"yes line\" $> stream.take 2 $> stream.collect";
+            assert_eq!(eval_str(input), "[ line line ]");
+        }
+
+        #[test]
+        fn stream_cannot_be_read_while_it_gives_a_value() {
+            let input = "let s (stream.from 0 $> stream.map (fn [x] stream.collect s))\nstream.collect s";
+            assert!(matches!(eval_err(input), RuntimeError::Custom(_)));
+        }
+    }
+
+    // ==================== Formatting ====================
+
+    mod formatting {
+        use super::*;
+        use crate::eval::value::ValueType;
+
+        #[test]
+        fn aligns_to_width() {
+            let input = "let size 42\n\"[{size:>6}] [{size:<4}] [{size:^6}] [{:ab:*>4}] [{:ab:4}]\"";
+            assert_eq!(eval_str(input), "[    42] [42  ] [  42  ] [**ab] [ab  ]");
+        }
+
+        #[test]
+        fn formats_numbers() {
+            let input = "\"{0.12345:.2} {1234567:,} {5:+04} {- 5 0:05} {1234.5:,.1} {3:.1}\"";
+            assert_eq!(eval_str(input), "0.12 1,234,567 +005 -0005 1,234.5 3.0");
+        }
+
+        #[test]
+        fn formats_integers_in_other_bases() {
+            let input = "\"{255:x} {255:X} {8:o} {5:08b} {(number.shl 64 1):x}\"";
+            assert_eq!(eval_str(input), "ff FF 10 00000101 10000000000000000");
+        }
+
+        #[test]
+        fn truncates_strings_by_precision() {
+            assert_eq!(eval_str("\"{:abcdef:.3}|{:żółw:.2}\""), "abc|żó");
+        }
+
+        #[test]
+        fn format_function_uses_the_same_specs() {
+            assert_eq!(eval_str("string.format \">8.3\" 3.14159"), "   3.142");
+            assert_eq!(eval_str("12.5 $> string.format \"\""), "12.5");
+        }
+
+        #[test]
+        fn numeric_flags_need_numbers() {
+            let err = eval_err("\"{:abc:x}\"");
+            assert!(matches!(
+                err,
+                RuntimeError::MissmatchedTypes { got: ValueType::String, expected: ValueType::Number }
+            ));
+
+            let err = eval_err("\"{1.5:x}\"");
+            assert!(matches!(
+                err,
+                RuntimeError::MissmatchedTypes { got: ValueType::Number, expected: ValueType::Integer }
+            ));
+        }
+
+        #[test]
+        fn invalid_spec_of_format_function_is_an_error() {
+            assert!(eval_err("string.format :q 1").to_string().contains("invalid format spec 'q'"));
+            let err = eval_err("string.format :.99999 1.5").to_string();
+            assert!(err.contains("invalid format spec '.99999'"));
+            let err = eval_err("string.format :99999999999999999999999 1").to_string();
+            assert!(err.contains("invalid format spec"));
+        }
+    }
+}
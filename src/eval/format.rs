@@ -0,0 +1,127 @@
+//! Values written by a format spec, as in "{size:>10}" and `string.format`
+
+use crate::eval::{
+    error::{RuntimeError, ShikError},
+    integer::Integer,
+    value::{Value, ValueType},
+};
+use crate::parser::format::{Align, FormatSpec, Notation};
+
+/// Value written by the spec, numeric flags are allowed only for numbers
+pub fn format(val: &Value, spec: &FormatSpec) -> Result<String, RuntimeError> {
+    let numeric = spec.sign || spec.zero || spec.thousands || spec.notation.is_some();
+    let (sign, body) = match val {
+        Value::Integer(x) => integer(x, spec)?,
+        Value::Number(x) => float(*x, spec)?,
+        _ if numeric => {
+            return Err(RuntimeError::MissmatchedTypes {
+                got: val.get_type(),
+                expected: ValueType::Number,
+            })
+        }
+        Value::String(s) => ("", truncate(s, spec.precision)),
+        val => ("", truncate(&val.to_string(), spec.precision)),
+    };
+
+    let number = matches!(val, Value::Integer(_) | Value::Number(_));
+    Ok(pad(sign, body, spec, number))
+}
+
+fn integer(x: &Integer, spec: &FormatSpec) -> Result<(&'static str, String), RuntimeError> {
+    // precision and exponent are about fractions, the integer is written as a float
+    if spec.precision.is_some() || spec.notation == Some(Notation::Exponent) {
+        return float(x.to_f64(), spec);
+    }
+
+    let abs = x.abs().to_big();
+    let digits = match spec.notation {
+        Some(Notation::Hex) => format!("{:x}", abs),
+        Some(Notation::UpperHex) => format!("{:X}", abs),
+        Some(Notation::Octal) => format!("{:o}", abs),
+        Some(Notation::Binary) => format!("{:b}", abs),
+        _ => abs.to_string(),
+    };
+    let digits = match (spec.thousands, spec.notation) {
+        (false, _) => digits,
+        (true, None) => group(&digits),
+        (true, Some(_)) => {
+            return Err(ShikError::default_error(
+                "thousands separators are written only in decimal numbers".to_string(),
+            ))
+        }
+    };
+    Ok((sign(*x < Integer::Small(0), spec), digits))
+}
+
+fn float(x: f64, spec: &FormatSpec) -> Result<(&'static str, String), RuntimeError> {
+    let abs = x.abs();
+    let digits = match (spec.notation, spec.precision) {
+        (Some(Notation::Exponent), Some(precision)) => format!("{:.*e}", precision, abs),
+        (Some(Notation::Exponent), None) => format!("{:e}", abs),
+        (Some(_), _) => {
+            return Err(RuntimeError::MissmatchedTypes {
+                got: ValueType::Number,
+                expected: ValueType::Integer,
+            })
+        }
+        (None, Some(precision)) => format!("{:.*}", precision, abs),
+        (None, None) => format!("{:?}", abs),
+    };
+    let digits = match spec.thousands && spec.notation.is_none() {
+        true => match digits.split_once('.') {
+            Some((int, fraction)) => format!("{}.{}", group(int), fraction),
+            None => group(&digits),
+        },
+        false => digits,
+    };
+    Ok((sign(x < 0.0, spec), digits))
+}
+
+fn sign(negative: bool, spec: &FormatSpec) -> &'static str {
+    match (negative, spec.sign) {
+        (true, _) => "-",
+        (false, true) => "+",
+        (false, false) => "",
+    }
+}
+
+/// Digits grouped by three from the right with `,`
+fn group(digits: &str) -> String {
+    let len = digits.len();
+    let mut res = String::with_capacity(len + len / 3);
+    for (i, ch) in digits.chars().enumerate() {
+        if i > 0 && (len - i).is_multiple_of(3) {
+            res.push(',');
+        }
+        res.push(ch);
+    }
+    res
+}
+
+/// First `precision` characters of the string
+fn truncate(s: &str, precision: Option<usize>) -> String {
+    match precision.and_then(|precision| s.char_indices().nth(precision)) {
+        Some((end, _)) => s[..end].to_string(),
+        None => s.to_string(),
+    }
+}
+
+/// Text filled up to the width, which is counted in characters
+fn pad(sign: &str, body: String, spec: &FormatSpec, number: bool) -> String {
+    let len = sign.chars().count() + body.chars().count();
+    let missing = spec.width.saturating_sub(len);
+
+    if spec.zero && spec.align.is_none() {
+        return format!("{}{}{}", sign, "0".repeat(missing), body);
+    }
+
+    let default = if number { Align::Right } else { Align::Left };
+    let (left, right) = match spec.align.unwrap_or(default) {
+        Align::Left => (0, missing),
+        Align::Right => (missing, 0),
+        Align::Center => (missing / 2, missing - missing / 2),
+    };
+
+    let fill = |n: usize| std::iter::repeat_n(spec.fill, n).collect::<String>();
+    format!("{}{}{}{}", fill(left), sign, body, fill(right))
+}
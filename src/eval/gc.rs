@@ -0,0 +1,278 @@
+//! Collection of environments kept alive only by reference cycles
+//!
+//! Values are counted by `Rc`, which never frees a cycle: a function defined by `let`
+//! is stored in the environment it captures. A cycle is made only by putting a value
+//! into an existing environment or reference cell, so these are tracked, and from time
+//! to time the ones which are referenced only from each other, but not from anything
+//! else, are cleared, which breaks the cycles.
+//!
+//! Items of lists are shared by copies of the list, so references from them are
+//! not attributed to any list: an environment referenced by an item stays alive.
+
+use crate::eval::value::{Env, EnvRef, Value, ValueRef};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
+
+// tracked places, after which the first collection runs
+const THRESHOLD: usize = 10_000;
+
+/// Environments and reference cells, which got values that may reference them back
+#[derive(Debug)]
+pub struct Heap {
+    tracked: RefCell<Vec<Place>>,
+    // number of tracked places, after which the next collection runs
+    threshold: Cell<usize>,
+}
+
+#[derive(Debug)]
+enum Place {
+    Env(Weak<Env>),
+    Cell(Weak<Value>),
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self {
+            tracked: RefCell::new(Vec::new()),
+            threshold: Cell::new(THRESHOLD),
+        }
+    }
+}
+
+impl Heap {
+    /// Tracks the environment, which got the value
+    pub fn track_env(&self, env: &EnvRef, val: &Value) {
+        if may_reference(val) {
+            self.track(Place::Env(Rc::downgrade(env)));
+        }
+    }
+
+    /// Tracks the reference cell, which got the value
+    pub fn track_cell(&self, cell: &ValueRef, val: &Value) {
+        if may_reference(val) {
+            self.track(Place::Cell(Rc::downgrade(cell)));
+        }
+    }
+
+    fn track(&self, place: Place) {
+        self.tracked.borrow_mut().push(place);
+        if self.len() >= self.threshold.get() {
+            self.collect();
+        }
+    }
+
+    /// Number of tracked places, which may be alive
+    pub fn len(&self) -> usize {
+        self.tracked.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracked.borrow().is_empty()
+    }
+
+    /// Clears unreachable environments, returns how many of them were found.
+    ///
+    /// An environment is reachable if it is referenced from outside of the tracked places
+    /// and the values they hold, e.g. by a running frame or a value held by a native
+    /// function, or by anything reachable from such a reference.
+    pub fn collect(&self) -> usize {
+        let mut graph = Graph::default();
+        let mut tracked: Vec<usize> = self
+            .tracked
+            .take()
+            .iter()
+            .filter_map(|place| match place {
+                Place::Env(env) => env.upgrade().map(Node::Env),
+                Place::Cell(cell) => cell.upgrade().map(Node::Value),
+            })
+            .map(|node| graph.insert(node))
+            .collect();
+        tracked.sort_unstable();
+        tracked.dedup();
+
+        graph.explore();
+        let alive = graph.alive();
+
+        let mut cleared = Vec::new();
+        let mut found = 0;
+        for (i, node) in graph.nodes.iter().enumerate() {
+            match node {
+                _ if alive[i] => {}
+                Node::Env(env) => {
+                    cleared.extend(env.clear());
+                    found += 1;
+                }
+                Node::Value(val) => cleared.extend(clear_value(val)),
+            }
+        }
+
+        // alive places are tracked further, they may be left by their last references later
+        let survivors: Vec<Place> = tracked
+            .into_iter()
+            .filter(|&i| alive[i])
+            .map(|i| match &graph.nodes[i] {
+                Node::Env(env) => Place::Env(Rc::downgrade(env)),
+                Node::Value(cell) => Place::Cell(Rc::downgrade(cell)),
+            })
+            .collect();
+
+        // the next collection explores at least the alive nodes, it runs after as many new places
+        let reachable = alive.iter().filter(|&&alive| alive).count();
+        let threshold = survivors.len() + THRESHOLD.max(reachable);
+        self.threshold.set(threshold);
+        self.tracked.borrow_mut().extend(survivors);
+
+        // values are dropped after the graph, when the cycles are broken
+        drop(graph);
+        drop(cleared);
+        found
+    }
+}
+
+/// Whether a value put into a place may reference the place back
+fn may_reference(val: &Value) -> bool {
+    matches!(val, Value::List(_)) || holds_references(val)
+}
+
+/// Environment or value, which may reference environments
+enum Node {
+    Env(EnvRef),
+    Value(ValueRef),
+}
+
+impl Node {
+    fn key(&self) -> *const () {
+        match self {
+            Node::Env(env) => Rc::as_ptr(env) as *const (),
+            Node::Value(val) => Rc::as_ptr(val) as *const (),
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Node::Env(env) => Rc::strong_count(env),
+            Node::Value(val) => Rc::strong_count(val),
+        }
+    }
+}
+
+/// Tracked places with environments and values reachable from them
+#[derive(Default)]
+struct Graph {
+    nodes: Vec<Node>,
+    index: HashMap<*const (), usize>,
+    // referenced nodes
+    edges: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    fn insert(&mut self, node: Node) -> usize {
+        let key = node.key();
+        if let Some(&i) = self.index.get(&key) {
+            return i;
+        }
+
+        self.nodes.push(node);
+        self.edges.push(Vec::new());
+        self.index.insert(key, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    /// Adds everything referenced by the nodes
+    fn explore(&mut self) {
+        let mut i = 0;
+        while i < self.nodes.len() {
+            let mut envs = Vec::new();
+            let mut values = Vec::new();
+            match &self.nodes[i] {
+                Node::Env(env) => {
+                    envs.extend(env.parent.iter().cloned());
+                    values.extend(env.values());
+                }
+                Node::Value(val) => references(val, &mut envs, &mut values),
+            }
+
+            let values = values.into_iter().filter(|val| holds_references(val));
+            for node in envs.into_iter().map(Node::Env).chain(values.map(Node::Value)) {
+                let j = self.insert(node);
+                self.edges[i].push(j);
+            }
+            i += 1;
+        }
+    }
+
+    /// Nodes referenced from outside of the graph, and the ones reachable from them
+    fn alive(&self) -> Vec<bool> {
+        // the graph holds one reference to each node
+        let mut outer: Vec<isize> = self
+            .nodes
+            .iter()
+            .map(|node| node.strong_count() as isize - 1)
+            .collect();
+        for &j in self.edges.iter().flatten() {
+            outer[j] -= 1;
+        }
+
+        let mut alive = vec![false; self.nodes.len()];
+        let mut stack: Vec<usize> = (0..self.nodes.len()).filter(|&i| outer[i] > 0).collect();
+        while let Some(i) = stack.pop() {
+            if alive[i] {
+                continue;
+            }
+            alive[i] = true;
+            stack.extend(self.edges[i].iter().copied());
+        }
+        alive
+    }
+}
+
+/// Whether the value may reference an environment through a value it owns alone
+fn holds_references(val: &Value) -> bool {
+    matches!(
+        val,
+        Value::Lambda(_)
+            | Value::NativeLambda(_)
+            | Value::SpecialForm(_)
+            | Value::Lazy(_)
+            | Value::Object(_)
+            | Value::Result(_)
+            | Value::Ref(_)
+            | Value::Stream(_)
+    )
+}
+
+/// Environments and values referenced by the value
+fn references(val: &Value, envs: &mut Vec<EnvRef>, values: &mut Vec<ValueRef>) {
+    match val {
+        Value::Lambda(closure) => {
+            envs.push(Rc::clone(&closure.env));
+            values.extend(closure.binded.iter().cloned());
+        }
+        Value::NativeLambda(closure) => {
+            envs.push(Rc::clone(&closure.env));
+            values.extend(closure.binded.iter().cloned());
+        }
+        Value::SpecialForm(closure) => envs.push(Rc::clone(&closure.env)),
+        Value::Lazy(thunk) => {
+            envs.push(Rc::clone(&thunk.env));
+            values.extend(thunk.cached());
+        }
+        Value::Object(obj) => values.extend(obj.values().cloned()),
+        Value::Result(Ok(v) | Err(v)) => values.push(Rc::clone(v)),
+        Value::Ref(cell) => values.push(Rc::clone(&cell.borrow())),
+        Value::Stream(stream) => values.extend(stream.values()),
+        _ => {}
+    }
+}
+
+/// Removes references of an unreachable value, which may be part of a cycle
+fn clear_value(val: &Value) -> Option<ValueRef> {
+    match val {
+        Value::Ref(cell) => Some(cell.replace(Rc::new(Value::Null))),
+        Value::Lazy(thunk) => thunk.cache.take(),
+        _ => None,
+    }
+}
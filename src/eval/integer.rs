@@ -0,0 +1,209 @@
+//! Integers of any size: machine words while they fit, big integers when they grow out of them
+
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, Signed, ToPrimitive};
+use std::cmp::Ordering;
+
+/// Integer value, `Big` holds only values which do not fit into `Small`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Integer {
+    Small(i64),
+    Big(BigInt),
+}
+
+impl From<i64> for Integer {
+    fn from(value: i64) -> Self {
+        Integer::Small(value)
+    }
+}
+
+impl From<u64> for Integer {
+    fn from(value: u64) -> Self {
+        match i64::try_from(value) {
+            Ok(small) => Integer::Small(small),
+            Err(_) => Integer::Big(BigInt::from(value)),
+        }
+    }
+}
+
+impl From<BigInt> for Integer {
+    fn from(value: BigInt) -> Self {
+        match value.to_i64() {
+            Some(small) => Integer::Small(small),
+            None => Integer::Big(value),
+        }
+    }
+}
+
+/// Applies the machine operation, or the big one if the result does not fit into a word
+macro_rules! promoting {
+    ($a:expr, $b:expr, $small:ident, $big:expr) => {
+        match ($a, $b) {
+            (Integer::Small(x), Integer::Small(y)) => match x.$small(*y) {
+                Some(res) => Integer::Small(res),
+                None => Integer::from($big(BigInt::from(*x), BigInt::from(*y))),
+            },
+            (a, b) => Integer::from($big(a.to_big(), b.to_big())),
+        }
+    };
+}
+
+impl Integer {
+    /// Integer part of the float, `None` for infinities and NaN
+    pub fn from_f64(value: f64) -> Option<Integer> {
+        BigInt::from_f64(value.trunc()).map(Integer::from)
+    }
+
+    pub fn to_big(&self) -> BigInt {
+        match self {
+            Integer::Small(x) => BigInt::from(*x),
+            Integer::Big(x) => x.clone(),
+        }
+    }
+
+    /// Number of bits of the absolute value
+    pub fn bits(&self) -> u64 {
+        match self {
+            Integer::Small(x) => u64::from(64 - x.unsigned_abs().leading_zeros()),
+            Integer::Big(x) => x.bits(),
+        }
+    }
+
+    /// Nearest float, precision is lost for large values
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Integer::Small(x) => *x as f64,
+            Integer::Big(x) => x.to_f64().unwrap_or(f64::NAN),
+        }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        match self {
+            Integer::Small(x) => Some(*x),
+            Integer::Big(_) => None,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        matches!(self, Integer::Small(0))
+    }
+
+    pub fn add(&self, other: &Integer) -> Integer {
+        promoting!(self, other, checked_add, |a, b| a + b)
+    }
+
+    pub fn sub(&self, other: &Integer) -> Integer {
+        promoting!(self, other, checked_sub, |a, b| a - b)
+    }
+
+    pub fn mul(&self, other: &Integer) -> Integer {
+        promoting!(self, other, checked_mul, |a, b| a * b)
+    }
+
+    /// Quotient rounded toward zero, `None` if the divisor is zero
+    pub fn div(&self, other: &Integer) -> Option<Integer> {
+        if other.is_zero() {
+            return None;
+        }
+        Some(promoting!(self, other, checked_div, |a: BigInt, b| a / b))
+    }
+
+    /// Remainder with the sign of the dividend, `None` if the divisor is zero
+    pub fn rem(&self, other: &Integer) -> Option<Integer> {
+        if other.is_zero() {
+            return None;
+        }
+        Some(promoting!(self, other, checked_rem, |a: BigInt, b| a % b))
+    }
+
+    pub fn pow(&self, exp: u32) -> Integer {
+        match self {
+            Integer::Small(x) => match x.checked_pow(exp) {
+                Some(res) => Integer::Small(res),
+                None => Integer::from(BigInt::from(*x).pow(exp)),
+            },
+            Integer::Big(x) => Integer::from(x.pow(exp)),
+        }
+    }
+
+    pub fn abs(&self) -> Integer {
+        match self {
+            Integer::Small(x) => match x.checked_abs() {
+                Some(res) => Integer::Small(res),
+                None => Integer::from(BigInt::from(*x).abs()),
+            },
+            Integer::Big(x) => Integer::from(x.abs()),
+        }
+    }
+
+    /// Bitwise operations treat negative values as infinite two's complement
+    pub fn and(&self, other: &Integer) -> Integer {
+        match (self, other) {
+            (Integer::Small(x), Integer::Small(y)) => Integer::Small(x & y),
+            (a, b) => Integer::from(a.to_big() & b.to_big()),
+        }
+    }
+
+    pub fn or(&self, other: &Integer) -> Integer {
+        match (self, other) {
+            (Integer::Small(x), Integer::Small(y)) => Integer::Small(x | y),
+            (a, b) => Integer::from(a.to_big() | b.to_big()),
+        }
+    }
+
+    pub fn xor(&self, other: &Integer) -> Integer {
+        match (self, other) {
+            (Integer::Small(x), Integer::Small(y)) => Integer::Small(x ^ y),
+            (a, b) => Integer::from(a.to_big() ^ b.to_big()),
+        }
+    }
+
+    pub fn not(&self) -> Integer {
+        match self {
+            Integer::Small(x) => Integer::Small(!x),
+            Integer::Big(x) => Integer::from(!x),
+        }
+    }
+
+    pub fn shl(&self, bits: usize) -> Integer {
+        match self {
+            Integer::Small(0) => Integer::Small(0),
+            Integer::Small(x) if x.unsigned_abs().leading_zeros() as usize > bits => {
+                Integer::Small(x << bits)
+            }
+            x => Integer::from(x.to_big() << bits),
+        }
+    }
+
+    /// Shift to the right, rounding toward negative infinity
+    pub fn shr(&self, bits: usize) -> Integer {
+        match self {
+            Integer::Small(x) => Integer::Small(x >> bits.min(63)),
+            Integer::Big(x) => Integer::from(x >> bits),
+        }
+    }
+}
+
+impl Ord for Integer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Integer::Small(x), Integer::Small(y)) => x.cmp(y),
+            (a, b) => a.to_big().cmp(&b.to_big()),
+        }
+    }
+}
+
+impl PartialOrd for Integer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::fmt::Display for Integer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Integer::Small(x) => write!(f, "{}", x),
+            Integer::Big(x) => write!(f, "{}", x),
+        }
+    }
+}
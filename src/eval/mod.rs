@@ -1,8 +1,22 @@
 use crate::eval::{error::RuntimeError, value::ValueRef};
 
+pub mod compiler;
 pub mod error;
 pub mod evaluator;
+pub mod format;
+pub mod gc;
+pub mod integer;
+pub mod module;
+pub mod pattern;
+pub mod resolver;
+pub mod stream;
+pub mod text;
+pub mod types;
 pub mod value;
+pub mod vm;
 pub mod native_functions;
 
 pub type EvalResult = Result<ValueRef, RuntimeError>;
+
+#[cfg(test)]
+mod evaluator_tests;
@@ -0,0 +1,137 @@
+//! Loading of `.shk` files imported by scripts
+
+use crate::{
+    eval::{
+        error::RuntimeError,
+        evaluator::Interpretator,
+        value::{Env, EnvRef},
+    },
+    parser::parse,
+};
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+pub const EXTENSION: &str = "shk";
+
+/// Imported files, each one is evaluated once
+#[derive(Debug, Default)]
+pub struct Modules {
+    // environments of evaluated files by their canonical path
+    loaded: HashMap<PathBuf, EnvRef>,
+    // files being evaluated, the last one is the current file
+    loading: Vec<PathBuf>,
+}
+
+impl Modules {
+    /// Sets the script file, imports are resolved relative to it
+    pub fn set_main(&mut self, file: &Path) {
+        let file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        self.loading.clear();
+        self.loading.push(file);
+    }
+
+    /// File being evaluated, none for the REPL
+    pub fn current_file(&self) -> Option<&Path> {
+        self.loading.last().map(PathBuf::as_path)
+    }
+
+    /// Directory of the current file, or the working directory for the REPL
+    fn current_dir(&self) -> PathBuf {
+        self.current_file()
+            .and_then(|file| file.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    /// Finds the file: `./` and `../` paths are relative to the current file,
+    /// other ones are looked up next to the current file and then in `SHIK_PATH` directories
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, RuntimeError> {
+        let mut file = PathBuf::from(path);
+        if file.extension().is_none() {
+            file.set_extension(EXTENSION);
+        }
+
+        let mut candidates = Vec::new();
+        if file.is_absolute() {
+            candidates.push(file.clone());
+        } else {
+            candidates.push(self.current_dir().join(&file));
+
+            if !path.starts_with("./") && !path.starts_with("../") {
+                if let Some(dirs) = env::var_os("SHIK_PATH") {
+                    candidates.extend(env::split_paths(&dirs).map(|dir| dir.join(&file)));
+                }
+            }
+        }
+
+        candidates
+            .into_iter()
+            .find(|candidate| candidate.is_file())
+            .and_then(|found| found.canonicalize().ok())
+            .ok_or_else(|| RuntimeError::ImportError(format!("cannot find module '{}'", path)))
+    }
+
+    fn enter(&mut self, file: PathBuf) -> Result<(), RuntimeError> {
+        if let Some(start) = self.loading.iter().position(|it| *it == file) {
+            let cycle: Vec<String> = self.loading[start..]
+                .iter()
+                .chain(Some(&file))
+                .map(|it| it.display().to_string())
+                .collect();
+            return Err(RuntimeError::ImportError(format!(
+                "import cycle {}",
+                cycle.join(" -> ")
+            )));
+        }
+
+        self.loading.push(file);
+        Ok(())
+    }
+}
+
+/// Evaluates the file in its own environment, or returns the environment
+/// if the file was imported already
+pub fn load(inter: &Interpretator, path: &str) -> Result<EnvRef, RuntimeError> {
+    let file = inter.modules.borrow().resolve(path)?;
+
+    if let Some(env) = inter.modules.borrow().loaded.get(&file) {
+        return Ok(Rc::clone(env));
+    }
+
+    inter.modules.borrow_mut().enter(file.clone())?;
+    let result = evaluate(inter, &file);
+    inter.modules.borrow_mut().loading.pop();
+
+    let env = result?;
+    inter
+        .modules
+        .borrow_mut()
+        .loaded
+        .insert(file, Rc::clone(&env));
+    Ok(env)
+}
+
+fn evaluate(inter: &Interpretator, file: &Path) -> Result<EnvRef, RuntimeError> {
+    let name = file.display().to_string();
+
+    let source = fs::read_to_string(file)
+        .map_err(|e| RuntimeError::ImportError(format!("cannot read '{}' - {}", name, e)))?;
+    let program = parse(&source)
+        .map_err(|e| RuntimeError::ImportError(format!("cannot parse '{}' - {}", name, e)))?;
+
+    // definitions of the module see only the builtins
+    let env = Rc::new(Env::new(Some(Rc::clone(&inter.builtins))));
+    inter
+        .interpretate_in(&program, &env)
+        .map_err(|error| RuntimeError::Module {
+            file: name,
+            source,
+            error: Box::new(error),
+        })?;
+
+    Ok(env)
+}
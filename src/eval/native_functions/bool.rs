@@ -3,15 +3,16 @@ use crate::{
     eval::{
         error::RuntimeError,
         evaluator::Interpretator,
-        native_functions::{native_result, string::StringEq},
+        native_functions::{native_result, number::compare, string::StringEq},
         value::{EnvRef, NativeClosure, NativeContext, NativeFn, Value, ValueRef},
         EvalResult,
     },
     native_op,
 };
+use std::cmp::Ordering;
 use std::rc::Rc;
 
-native_op!(Bool, "bool", [val], {
+native_op!(Bool, "bool", [val] -> Bool, {
     native_result(match val.as_ref() {
         Value::Number(val) => {
             if *val == 0.0 {
@@ -20,6 +21,7 @@ native_op!(Bool, "bool", [val], {
                 Value::Bool(true)
             }
         }
+        Value::Integer(val) => Value::Bool(!val.is_zero()),
         Value::Null => Value::Bool(false),
         Value::String(val) => {
             if val.is_empty() {
@@ -46,49 +48,45 @@ native_op!(Bool, "bool", [val], {
     })
 });
 
-native_op!(Or, "or", [x, y], {
+native_op!(Or, "or", [x: Bool, y: Bool] -> Bool, {
     let x = x.expect_bool()?;
     let y = y.expect_bool()?;
 
     native_result(Value::Bool(x || y))
 });
 
-native_op!(And, "and", [x, y], {
+native_op!(And, "and", [x: Bool, y: Bool] -> Bool, {
     let x = x.expect_bool()?;
     let y = y.expect_bool()?;
 
     native_result(Value::Bool(x && y))
 });
 
-native_op!(Eq, "=", [x, y], {
+native_op!(Eq, "=", [x, y] -> Bool, {
     native_result(Value::Bool(match (x.as_ref(), y.as_ref()) {
-        (Value::Number(x), Value::Number(y)) => x == y,
+        (Value::Number(_) | Value::Integer(_), Value::Number(_) | Value::Integer(_)) => {
+            compare(x, y)? == Some(Ordering::Equal)
+        }
         (Value::Bool(x), Value::Bool(y)) => x == y,
         (Value::String(_), Value::String(_)) => return StringEq::run(x, y),
         (Value::Null, Value::Null) => true,
         _ => false,
     }))
 });
-native_op!(NotEq, "!=", [x, y], {
+native_op!(NotEq, "!=", [x, y] -> Bool, {
     let res = Eq::run(x, y)?.expect_bool()?;
     native_result(Value::Bool(!res))
 });
-native_op!(Not, "not", [x], {
+native_op!(Not, "not", [x: Bool] -> Bool, {
     let x = x.expect_bool()?;
     native_result(Value::Bool(!x))
 });
 
-native_op!(Gt, ">", [x, y], {
-    let x = x.expect_number()?;
-    let y = y.expect_number()?;
-
-    native_result(Value::Bool(x > y))
+native_op!(Gt, ">", [x: Number, y: Number] -> Bool, {
+    native_result(Value::Bool(compare(x, y)? == Some(Ordering::Greater)))
 });
-native_op!(Lt, "<", [x, y], {
-    let x = x.expect_number()?;
-    let y = y.expect_number()?;
-
-    native_result(Value::Bool(x < y))
+native_op!(Lt, "<", [x: Number, y: Number] -> Bool, {
+    native_result(Value::Bool(compare(x, y)? == Some(Ordering::Less)))
 });
 
 pub fn bind_bool_module(env: &EnvRef, inter: Rc<Interpretator>) {
@@ -1,8 +1,8 @@
 use crate::{
     eval::{
-        error::RuntimeError, evaluator::Interpretator, native_functions::native_result, value::{EnvRef, NativeContext, SpecialClosure, SpecialFn, Value}, EvalResult
+        error::RuntimeError, evaluator::Interpretator, native_functions::native_result, value::{Argument, EnvRef, NativeContext, SpecialClosure, SpecialFn, TailArg, Value}, EvalResult
     },
-    parser::Expression, special_op,
+    special_op,
 };
 use std::{rc::Rc};
 
@@ -30,72 +30,56 @@ if predicate
     3 ; else
 */
 
-special_op!(If, "if", args, ctx, {
-        let args_count = args.len();
-        if args_count < 2 {
-            return Err(RuntimeError::InvalidApplication);
-        }
-
-        let mut args_it = args.iter().peekable();
-        let predicate = args_it.next().ok_or(RuntimeError::InvalidApplication)?;
-        let mut predicate = ctx.inter.eval_expr(predicate, &ctx.env)?.expect_bool()?;
+// Predicates and branches can be lazy values, they are forced on use
+fn eval_forced(arg: &Argument, ctx: &NativeContext) -> EvalResult {
+    ctx.inter.force(ctx.inter.eval_arg(arg, ctx.env)?)
+}
 
-        if args_count == 2 {
-            // Simple if without else
-            if predicate {
-                ctx.inter.eval_expr(&args_it.next().unwrap(), &ctx.env)
-            } else {
-                Ok(Rc::new(Value::Null))
-            }
-        } else if args_count % 2 == 0 {
-            // Without else at the end
-            while !predicate {
-                args_it.next(); // skip body
-                let next = args_it.next();
-                if next == None {
-                    break;
-                }
-                predicate = ctx.inter.eval_expr(next.unwrap(), &ctx.env)?.expect_bool()?;
-            }
+// Index of the body to evaluate, `None` if no predicate holds and there is no `else`
+fn select_branch(args: &[Argument], ctx: &NativeContext) -> Result<Option<usize>, RuntimeError> {
+    let args_count = args.len();
+    if args_count < 2 {
+        return Err(RuntimeError::InvalidApplication);
+    }
 
-            if predicate {
-                let next = args_it.next().ok_or(RuntimeError::InvalidApplication)?;
-                ctx.inter.eval_expr(next, &ctx.env)
-            } else {
-                Ok(Rc::new(Value::Null))
-            }
-        } else {
-            // With else at the end
-            let mut next: Option<&Expression> = args_it.next();
+    // predicate and body pairs
+    let mut i = 0;
+    while i + 1 < args_count {
+        if eval_forced(&args[i], ctx)?.expect_bool()? {
+            return Ok(Some(i + 1));
+        }
+        i += 2;
+    }
 
-            while !predicate {
-                next = args_it.next();
-                // if it is the last - go back, we found final `else`
-                if args_it.peek() == None {
-                    break;
-                }
-                predicate = ctx.inter.eval_expr(next.unwrap(), &ctx.env)?.expect_bool()?;
-                if predicate {
-                    // next body
-                    next = args_it.next();
-                    break
-                } else {
-                    args_it.next();
-                }
-            }
+    // odd count means the final `else`
+    if i < args_count {
+        Ok(Some(i))
+    } else {
+        Ok(None)
+    }
+}
 
-            // the next would be the desired body for sure, either `elseif` block, or `else`
-            let next = next.ok_or(RuntimeError::InvalidApplication)?;
-            ctx.inter.eval_expr(next, &ctx.env)
-        }
+special_op!(If, "if", args, ctx, {
+    match select_branch(args, ctx)? {
+        Some(i) => eval_forced(&args[i], ctx),
+        None => Ok(Rc::new(Value::Null)),
+    }
+}, tail {
+    match select_branch(args, ctx)? {
+        Some(i) => Ok(TailArg::Arg(i)),
+        None => Ok(TailArg::Value(Rc::new(Value::Null))),
+    }
 });
 
 special_op!(While, "while", args, ctx, {
-    let pred_fn = ctx.inter.eval_expr(&args[0], &ctx.env)?;
+    let pred_fn = ctx.inter.eval_arg(&args[0], ctx.env)?;
 
     let void = Rc::new(Value::Null);
     loop {
-        let should_continue = ctx.inter.apply_fn(&pred_fn, &void)?.expect_bool()?;
+        let should_continue = ctx
+            .inter
+            .force(ctx.inter.apply_fn(&pred_fn, &void)?)?
+            .expect_bool()?;
         if !should_continue {
             return native_result(Value::Null);
         }
@@ -103,6 +87,6 @@ special_op!(While, "while", args, ctx, {
 });
 
 pub fn bind_special_module(env: &EnvRef, inter: Rc<Interpretator>) {
-    If::define(&env, Rc::clone(&inter));
+    If::define(env, Rc::clone(&inter));
     While::define(env, inter);
 }
@@ -0,0 +1,140 @@
+use crate::{
+    define_native,
+    eval::{
+        error::RuntimeError,
+        evaluator::Interpretator,
+        native_functions::native_result,
+        value::{Argument, EnvRef, NativeContext, SpecialClosure, SpecialFn, Value, ValueRef},
+        EvalResult,
+    },
+    parser::{parse, Expression},
+    special_op,
+};
+use std::rc::Rc;
+
+// ============================================================================
+// Code values
+// ============================================================================
+
+/// Expression, which puts the value into code
+fn into_code(val: &ValueRef) -> Result<Expression, RuntimeError> {
+    match val.as_ref() {
+        Value::Code(expr) => Ok(expr.as_ref().clone()),
+        Value::Number(x) => Ok(Expression::number(*x)),
+        Value::Integer(x) => Ok(Expression::integer(x.to_big())),
+        Value::String(s) => Ok(Expression::string(s.to_string())),
+        Value::Bool(b) => Ok(Expression::identifier(b.to_string())),
+        Value::Null => Ok(Expression::identifier("null".to_string())),
+        Value::List(items) => Ok(Expression::list(
+            items.iter().map(into_code).collect::<Result<_, _>>()?,
+        )),
+        val => Err(RuntimeError::MacroError(format!(
+            "cannot put {:?} into code",
+            val.get_type()
+        ))),
+    }
+}
+
+// Code of the expression, `~x` inside it is replaced with the value of x
+// Usage: quote (if (not ~cond) ~body)
+special_op!(Quote, "quote", args, ctx, {
+    let [expr] = args else {
+        return Err(RuntimeError::InvalidApplication);
+    };
+    let expr = match expr.expr.as_ref() {
+        Expression::Parenthesized(expr) => expr.as_ref(),
+        expr => expr,
+    };
+
+    let code = expr.fill_unquotes(&mut |unquoted| {
+        let val = ctx.inter.expand(ctx.inter.eval_expr(unquoted, ctx.env)?)?;
+        into_code(&val)
+    })?;
+    native_result(Value::Code(Rc::new(code)))
+});
+
+// ============================================================================
+// User macros
+// ============================================================================
+
+/// Macro defined in shik: the expander gets argument expressions as code values
+/// and returns code, which is evaluated in place of the macro call
+#[derive(Debug)]
+pub struct UserMacro {
+    expander: ValueRef,
+}
+
+impl UserMacro {
+    pub fn new(expander: ValueRef) -> Self {
+        Self { expander }
+    }
+
+    /// Code the macro call turns into
+    pub fn expand(&self, args: &[Argument], ctx: &NativeContext) -> Result<Expression, RuntimeError> {
+        let Value::Lambda(expander) = self.expander.as_ref() else {
+            return Err(RuntimeError::InvalidApplication);
+        };
+
+        let mut call = expander.clone();
+        call.binded = args.iter().map(|arg| Rc::new(Value::Code(Rc::clone(&arg.expr)))).collect();
+        if call.rest().is_none() && call.binded.len() != call.arity() {
+            return Err(RuntimeError::MacroError(format!(
+                "macro takes {} arguments, got {}",
+                call.arity(),
+                call.binded.len()
+            )));
+        }
+
+        let expansion = ctx.inter.force(ctx.inter.call_closure(&call)?)?;
+        match expansion.as_ref() {
+            // source code, as in `macro [name] "let {name} 10"`
+            Value::String(source) => {
+                let program = parse(source).map_err(|e| {
+                    RuntimeError::MacroError(format!("cannot parse expansion - {}", e))
+                })?;
+                let mut exprs: Vec<Expression> =
+                    program.statements.into_iter().map(|stmt| stmt.expression).collect();
+
+                match exprs.len() {
+                    1 => Ok(exprs.remove(0)),
+                    _ => Ok(Expression::block(exprs)),
+                }
+            }
+            Value::Code(expr) => Ok(expr.as_ref().clone()),
+            val => Err(RuntimeError::MacroError(format!(
+                "macro must return code, got {:?}",
+                val.get_type()
+            ))),
+        }
+    }
+}
+
+impl SpecialFn for UserMacro {
+    fn exec(&self, args: &[Argument], ctx: &NativeContext) -> EvalResult {
+        let code = self.expand(args, ctx)?;
+        ctx.inter.eval_expr(&code, ctx.env)
+    }
+
+    // a macro is a value until it is applied
+    fn is_ready(&self, args: &[Argument]) -> bool {
+        !args.is_empty()
+    }
+
+    fn is_macro(&self) -> bool {
+        true
+    }
+}
+
+/// Macro value with the expander function
+pub fn user_macro(expander: ValueRef, inter: Rc<Interpretator>, env: EnvRef) -> ValueRef {
+    let logic = Rc::new(UserMacro::new(expander));
+    Rc::new(Value::SpecialForm(SpecialClosure::new(logic, inter, env)))
+}
+
+// ============================================================================
+// Module Binding
+// ============================================================================
+
+pub fn bind_code_module(env: &EnvRef, inter: Rc<Interpretator>) {
+    define_native!(Quote, env, inter);
+}
@@ -0,0 +1,119 @@
+use crate::{
+    count_args, define_native,
+    eval::{
+        error::{RuntimeError, ShikError},
+        evaluator::Interpretator,
+        value::{
+            Argument, EnvRef, NativeClosure, NativeContext, NativeFn, Object, SpecialClosure,
+            SpecialFn, Value, ValueRef, ValueType,
+        },
+        EvalResult,
+    },
+    native_op,
+    special_op,
+};
+use std::rc::Rc;
+
+// ============================================================================
+// Error objects
+// ============================================================================
+
+// Caught errors are passed to handlers as objects { :title :message :data }
+pub fn error_object(err: RuntimeError) -> ValueRef {
+    let (title, message, data) = match err.into_root() {
+        RuntimeError::Custom(err) => (err.title, err.msg, err.data),
+        err => {
+            let text = err.to_string();
+            let (title, message) = text.split_once(": ").unwrap_or(("RuntimeError", &text));
+            (title.to_string(), message.to_string(), Rc::new(Value::Null))
+        }
+    };
+
+    let mut obj = Object::new();
+    obj.insert("title".to_string(), Rc::new(Value::String(title.into())));
+    obj.insert("message".to_string(), Rc::new(Value::String(message.into())));
+    obj.insert("data".to_string(), data);
+    Rc::new(Value::Object(obj))
+}
+
+fn field(obj: &Object, key: &str) -> Result<Option<String>, RuntimeError> {
+    match obj.get(key) {
+        Some(val) => Ok(Some(val.expect_string()?.to_string())),
+        None => Ok(None),
+    }
+}
+
+// Error made from a message or an error object, the opposite of `error_object`
+pub fn raise(err: &ValueRef) -> RuntimeError {
+    match err.as_ref() {
+        Value::String(msg) => {
+            let data = Rc::new(Value::Null);
+            ShikError::raise("Error".to_string(), msg.to_string(), data)
+        }
+        Value::Object(obj) => {
+            let title = match field(obj, "title") {
+                Ok(title) => title.unwrap_or_else(|| "Error".to_string()),
+                Err(e) => return e,
+            };
+            let message = match field(obj, "message") {
+                Ok(message) => message.unwrap_or_default(),
+                Err(e) => return e,
+            };
+            let data = obj.get("data").cloned().unwrap_or_else(|| Rc::new(Value::Null));
+            ShikError::raise(title, message, data)
+        }
+        _ => RuntimeError::MissmatchedTypes {
+            got: err.get_type(),
+            expected: ValueType::Object,
+        },
+    }
+}
+
+// ============================================================================
+// Raising and handling
+// ============================================================================
+
+// Raise an error with a message, or with an object { :title :message :data }
+// Usage: error "config is missing"
+// Usage: error { :title :NotFound :message "no such file" :data path }
+native_op!(Error, "error", [err], {
+    Err(raise(err))
+});
+
+// Evaluate the body, on error pass the error object to the handler.
+// The optional third argument is evaluated at the end in any case
+// Usage: try (file.read :config.json) (fn [e] print e)
+// Usage: try #(shell "make") (fn [e] error e) #(file.remove :tmp)
+special_op!(Try, "try", args, ctx, {
+    let (body, handler, cleanup) = match args {
+        [body, handler] => (body, handler, None),
+        [body, handler, cleanup] => (body, handler, Some(cleanup)),
+        _ => return Err(RuntimeError::InvalidApplication),
+    };
+
+    let eval_forced = |arg: &Argument| {
+        ctx.inter.force(ctx.inter.expand(ctx.inter.eval_arg(arg, ctx.env)?)?)
+    };
+
+    let result = match eval_forced(body) {
+        Ok(val) => Ok(val),
+        Err(err) => ctx
+            .inter
+            .eval_arg(handler, ctx.env)
+            .and_then(|handler| ctx.apply(&handler, &error_object(err))),
+    };
+
+    if let Some(cleanup) = cleanup {
+        eval_forced(cleanup)?;
+    }
+    result
+});
+
+// ============================================================================
+// Module Binding
+// ============================================================================
+
+pub fn bind_error_module(env: &EnvRef, inter: Rc<Interpretator>) {
+    define_native!(Error, env, inter);
+    define_native!(Try, env, inter);
+}
@@ -3,16 +3,17 @@ use crate::{
     eval::{
         error::{RuntimeError, ShikError},
         evaluator::Interpretator,
-        native_functions::native_result,
-        value::{EnvRef, NativeClosure, NativeContext, NativeFn, Value, ValueRef},
+        integer::Integer,
+        native_functions::{native_result, result::define_fallible},
+        stream::{Lines, Stream},
+        value::{EnvRef, List, NativeClosure, NativeContext, NativeFn, Object, Value, ValueRef},
         EvalResult,
     },
     native_op,
 };
 use glob::glob;
-use std::collections::HashMap;
 use std::fs;
-use std::io;
+use std::io::{self, BufReader};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
@@ -22,37 +23,26 @@ use std::rc::Rc;
 
 // Read file contents as string
 // Usage: file.read "path/to/file.txt"
-native_op!(FileRead, "file.read", [path], {
+native_op!(FileRead, "file.read", [path: String] -> String, {
     let path = path.expect_string()?;
 
     let content = fs::read_to_string(path)
         .map_err(|e| ShikError::default_error(format!("cannot open file - {}", e)))?;
 
-    native_result(Value::String(content))
-});
-
-// Try to read file, return null on failure
-// Usage: file.read? "path/to/file.txt"
-native_op!(FileTryRead, "file.read?", [path], {
-    let path = path.expect_string()?;
-
-    match fs::read_to_string(path) {
-        Ok(content) => native_result(Value::String(content)),
-        Err(_) => native_result(Value::Null),
-    }
+    native_result(Value::String(content.into()))
 });
 
 // Read file as binary (returns list of numbers 0-255)
 // Usage: file.read-bytes "path/to/file.bin"
-native_op!(FileReadBytes, "file.read-bytes", [path], {
+native_op!(FileReadBytes, "file.read-bytes", [path: String] -> List, {
     let path = path.expect_string()?;
 
     let bytes = fs::read(path)
         .map_err(|e| ShikError::default_error(format!("cannot read file - {}", e)))?;
 
-    let result: Vec<ValueRef> = bytes
+    let result: List = bytes
         .into_iter()
-        .map(|b| Rc::new(Value::Number(b as f64)))
+        .map(|b| Rc::new(Value::Integer(Integer::from(i64::from(b)))))
         .collect();
 
     native_result(Value::List(result))
@@ -60,27 +50,38 @@ native_op!(FileReadBytes, "file.read-bytes", [path], {
 
 // Read file lines as a list
 // Usage: file.lines "path/to/file.txt"
-native_op!(FileLines, "file.read-lines", [path], {
+native_op!(FileLines, "file.read-lines", [path: String] -> List, {
     let path = path.expect_string()?;
 
     let content = fs::read_to_string(path)
         .map_err(|e| ShikError::default_error(format!("cannot read file - {}", e)))?;
 
-    let lines: Vec<ValueRef> = content
+    let lines: List = content
         .lines()
-        .map(|line| Rc::new(Value::String(line.to_string())))
+        .map(|line| Rc::new(Value::String(line.into())))
         .collect();
 
     native_result(Value::List(lines))
 });
 
+// Lines of the file read one by one, for files too large to be read at once
+// Usage: file.stream-lines "access.log" $> stream.filter (string.has "ERROR")
+native_op!(FileStreamLines, "file.stream-lines", [path: String] -> Stream, {
+    let path = path.expect_string()?;
+
+    let file = fs::File::open(path)
+        .map_err(|e| ShikError::default_error(format!("cannot open file - {}", e)))?;
+
+    native_result(Value::Stream(Stream::new(Lines(BufReader::new(file)))))
+});
+
 // ============================================================================
 // File Writing Functions
 // ============================================================================
 
 // Write string to file (overwrites existing)
 // Usage: file.write "path/to/file.txt" "content"
-native_op!(FileWrite, "file.write", [path, content], {
+native_op!(FileWrite, "file.write", [path: String, content: String] -> Null, {
     let path = path.expect_string()?;
     let content = content.expect_string()?;
 
@@ -92,7 +93,7 @@ native_op!(FileWrite, "file.write", [path, content], {
 
 // Append string to file
 // Usage: file.append "path/to/file.txt" "content"
-native_op!(FileAppend, "file.append", [path, content], {
+native_op!(FileAppend, "file.append", [path: String, content: String] -> Null, {
     let path = path.expect_string()?;
     let content = content.expect_string()?;
 
@@ -113,14 +114,14 @@ native_op!(FileAppend, "file.append", [path, content], {
 
 // Write bytes to file (takes list of numbers 0-255)
 // Usage: file.write-bytes "path/to/file.bin" [72 101 108 108 111]
-native_op!(FileWriteBytes, "file.write-bytes", [path, bytes], {
+native_op!(FileWriteBytes, "file.write-bytes", [path: String, bytes: List] -> Null, {
     let path = path.expect_string()?;
     let bytes_list = bytes.expect_list()?;
 
     let mut bytes_vec: Vec<u8> = Vec::with_capacity(bytes_list.len());
     for b in bytes_list.iter() {
         let num = b.expect_number()?;
-        if num < 0.0 || num > 255.0 {
+        if !(0.0..=255.0).contains(&num) {
             return Err(ShikError::default_error(format!(
                 "byte value out of range: {}",
                 num
@@ -141,7 +142,7 @@ native_op!(FileWriteBytes, "file.write-bytes", [path, bytes], {
 
 // Copy file or directory
 // Usage: file.copy "source" "destination"
-native_op!(FileCopy, ["file.copy", "file.cp"], [dst, src], {
+native_op!(FileCopy, ["file.copy", "file.cp"], [dst: String, src: String] -> Null, {
     let src = src.expect_string()?;
     let dst = dst.expect_string()?;
 
@@ -158,7 +159,7 @@ native_op!(FileCopy, ["file.copy", "file.cp"], [dst, src], {
 
 // Move/rename file or directory
 // Usage: file.move "source" "destination"
-native_op!(FileMove, ["file.move", "file.mv"], [dst, src], {
+native_op!(FileMove, ["file.move", "file.mv"], [dst: String, src: String] -> Null, {
     let src = src.expect_string()?;
     let dst = dst.expect_string()?;
 
@@ -170,7 +171,7 @@ native_op!(FileMove, ["file.move", "file.mv"], [dst, src], {
 
 // Delete any file or directory(recursively)
 // Usage: file.remove "path/to/file.txt"
-native_op!(FileRm, ["file.remove", "file.rm"], [path], {
+native_op!(FileRm, ["file.remove", "file.rm"], [path: String] -> Null, {
     let path = path.expect_string()?;
 
     if Path::new(path).is_dir() {
@@ -186,7 +187,7 @@ native_op!(FileRm, ["file.remove", "file.rm"], [path], {
 
 // Delete directory (must be empty)
 // Usage: file.rmdir "path/to/dir"
-native_op!(FileRmdir, "file.rmdir", [path], {
+native_op!(FileRmdir, "file.rmdir", [path: String] -> Null, {
     let path = path.expect_string()?;
 
     fs::remove_dir(path)
@@ -197,7 +198,7 @@ native_op!(FileRmdir, "file.rmdir", [path], {
 
 // Delete directory recursively
 // Usage: file.rmdir-all "path/to/dir"
-native_op!(FileRmdirAll, "file.rmdir!", [path], {
+native_op!(FileRmdirAll, "file.rmdir!", [path: String] -> Null, {
     let path = path.expect_string()?;
 
     fs::remove_dir_all(path)
@@ -208,7 +209,7 @@ native_op!(FileRmdirAll, "file.rmdir!", [path], {
 
 // Create directory
 // Usage: file.mkdir "path/to/dir"
-native_op!(FileMkdir, "file.mkdir", [path], {
+native_op!(FileMkdir, "file.mkdir", [path: String] -> Null, {
     let path = path.expect_string()?;
 
     fs::create_dir(path)
@@ -219,7 +220,7 @@ native_op!(FileMkdir, "file.mkdir", [path], {
 
 // Create directory and all parent directories
 // Usage: file.mkdir! "path/to/nested/dir"
-native_op!(FileMkdirAll, "file.mkdir!", [path], {
+native_op!(FileMkdirAll, "file.mkdir!", [path: String] -> Null, {
     let path = path.expect_string()?;
 
     fs::create_dir_all(path)
@@ -234,41 +235,41 @@ native_op!(FileMkdirAll, "file.mkdir!", [path], {
 
 // Check if path exists
 // Usage: file.exists "path"
-native_op!(FileExists, "file.exists", [path], {
+native_op!(FileExists, "file.exists", [path: String] -> Bool, {
     let path = path.expect_string()?;
     native_result(Value::Bool(Path::new(path).exists()))
 });
 
 // Check if path is a directory
 // Usage: file.is-dir "path"
-native_op!(FileIsDir, "file.is-dir", [path], {
+native_op!(FileIsDir, "file.is-dir", [path: String] -> Bool, {
     let path = path.expect_string()?;
     native_result(Value::Bool(Path::new(path).is_dir()))
 });
 
 // Check if path is a file
 // Usage: file.is-file "path"
-native_op!(FileIsFile, "file.is-file", [path], {
+native_op!(FileIsFile, "file.is-file", [path: String] -> Bool, {
     let path = path.expect_string()?;
     native_result(Value::Bool(Path::new(path).is_file()))
 });
 
 // Check if path is a symlink
 // Usage: file.is-symlink "path"
-native_op!(FileIsSymlink, "file.is-symlink", [path], {
+native_op!(FileIsSymlink, "file.is-symlink", [path: String] -> Bool, {
     let path = path.expect_string()?;
     native_result(Value::Bool(Path::new(path).is_symlink()))
 });
 
 // Get file size in bytes
 // Usage: file.size "path/to/file.txt"
-native_op!(FileSize, "file.size", [path], {
+native_op!(FileSize, "file.size", [path: String] -> Integer, {
     let path = path.expect_string()?;
 
     let metadata = fs::metadata(path)
         .map_err(|e| ShikError::default_error(format!("cannot get file metadata: {}", e)))?;
 
-    native_result(Value::Number(metadata.len() as f64))
+    native_result(Value::Integer(Integer::from(metadata.len())))
 });
 
 /// Compute the size of a directory recursively (in bytes).
@@ -301,7 +302,7 @@ fn dir_size(root: &Path) -> io::Result<u64> {
     Ok(total)
 }
 
-native_op!(FileSizeDeep, "file.size.deep", [path], {
+native_op!(FileSizeDeep, "file.size.deep", [path: String] -> Integer, {
     use std::path::Path;
 
     let path = path.expect_string()?;
@@ -321,21 +322,21 @@ native_op!(FileSizeDeep, "file.size.deep", [path], {
         0
     };
 
-    native_result(Value::Number(size as f64))
+    native_result(Value::Integer(Integer::from(size)))
 });
 
 // Get file metadata as object
 // Usage: file.stat "path/to/file.txt"
-native_op!(FileStat, "file.stat", [path], {
+native_op!(FileStat, "file.stat", [path: String] -> Object, {
     let path = path.expect_string()?;
 
     let metadata = fs::metadata(path)
         .map_err(|e| ShikError::default_error(format!("cannot get file metadata: {}", e)))?;
 
-    let mut result: HashMap<String, ValueRef> = HashMap::new();
+    let mut result = Object::new();
     result.insert(
         "size".to_string(),
-        Rc::new(Value::Number(metadata.len() as f64)),
+        Rc::new(Value::Integer(Integer::from(metadata.len()))),
     );
     result.insert(
         "is_file".to_string(),
@@ -363,18 +364,18 @@ native_op!(FileStat, "file.stat", [path], {
 
 // List directory contents
 // Usage: file.list "path/to/dir"
-native_op!(FileList, "file.list", [path], {
+native_op!(FileList, "file.list", [path: String] -> List, {
     let path = path.expect_string()?;
 
     let entries = fs::read_dir(path)
         .map_err(|e| ShikError::default_error(format!("cannot read directory: {}", e)))?;
 
-    let mut result: Vec<ValueRef> = Vec::new();
+    let mut result = List::new();
     for entry in entries {
         let entry =
             entry.map_err(|e| ShikError::default_error(format!("cannot read entry: {}", e)))?;
         let name = entry.file_name().to_string_lossy().to_string();
-        result.push(Rc::new(Value::String(name)));
+        result.push_back(Rc::new(Value::String(name.into())));
     }
 
     native_result(Value::List(result))
@@ -382,18 +383,18 @@ native_op!(FileList, "file.list", [path], {
 
 // List directory contents with full paths
 // Usage: file.list! "path/to/dir"
-native_op!(FileListPaths, "file.list!", [path], {
+native_op!(FileListPaths, "file.list!", [path: String] -> List, {
     let path = path.expect_string()?;
 
     let entries = fs::read_dir(path)
         .map_err(|e| ShikError::default_error(format!("cannot read directory: {}", e)))?;
 
-    let mut result: Vec<ValueRef> = Vec::new();
+    let mut result = List::new();
     for entry in entries {
         let entry =
             entry.map_err(|e| ShikError::default_error(format!("cannot read entry: {}", e)))?;
         let path_str = entry.path().to_string_lossy().to_string();
-        result.push(Rc::new(Value::String(path_str)));
+        result.push_back(Rc::new(Value::String(path_str.into())));
     }
 
     native_result(Value::List(result))
@@ -401,18 +402,18 @@ native_op!(FileListPaths, "file.list!", [path], {
 
 // Glob pattern matching
 // Usage: file.glob "*.txt"
-native_op!(FileGlob, "file.glob", [pattern], {
+native_op!(FileGlob, "file.glob", [pattern: String] -> List, {
     let pattern = pattern.expect_string()?;
 
     let paths = glob(pattern)
         .map_err(|e| ShikError::default_error(format!("invalid glob pattern: {}", e)))?;
 
-    let mut result: Vec<ValueRef> = Vec::new();
+    let mut result = List::new();
     for entry in paths {
         match entry {
             Ok(path) => {
                 let path_str = path.to_string_lossy().to_string();
-                result.push(Rc::new(Value::String(path_str)));
+                result.push_back(Rc::new(Value::String(path_str.into())));
             }
             Err(e) => {
                 return Err(ShikError::default_error(format!("glob error: {}", e)));
@@ -429,71 +430,71 @@ native_op!(FileGlob, "file.glob", [pattern], {
 
 // Get file name from path
 // Usage: file.name "/path/to/file.txt" -> "file.txt"
-native_op!(FileName, "path.name", [path], {
+native_op!(FileName, "path.name", [path: String], {
     let path = path.expect_string()?;
     let p = Path::new(path);
 
     match p.file_name() {
-        Some(name) => native_result(Value::String(name.to_string_lossy().to_string())),
+        Some(name) => native_result(Value::String(name.to_string_lossy().to_string().into())),
         None => native_result(Value::Null),
     }
 });
 
 // Get file stem (name without extension)
 // Usage: file.stem "/path/to/file.txt" -> "file"
-native_op!(FileStem, "path.stem", [path], {
+native_op!(FileStem, "path.stem", [path: String], {
     let path = path.expect_string()?;
     let p = Path::new(path);
 
     match p.file_stem() {
-        Some(stem) => native_result(Value::String(stem.to_string_lossy().to_string())),
+        Some(stem) => native_result(Value::String(stem.to_string_lossy().to_string().into())),
         None => native_result(Value::Null),
     }
 });
 
 // Get file extension
 // Usage: file.ext "/path/to/file.txt" -> "txt"
-native_op!(FileExt, "path.ext", [path], {
+native_op!(FileExt, "path.ext", [path: String], {
     let path = path.expect_string()?;
     let p = Path::new(path);
 
     match p.extension() {
-        Some(ext) => native_result(Value::String(ext.to_string_lossy().to_string())),
+        Some(ext) => native_result(Value::String(ext.to_string_lossy().to_string().into())),
         None => native_result(Value::Null),
     }
 });
 
 // Get parent directory
 // Usage: file.parent "/path/to/file.txt" -> "/path/to"
-native_op!(FileParent, "path.parent", [path], {
+native_op!(FileParent, "path.parent", [path: String], {
     let path = path.expect_string()?;
     let p = Path::new(path);
 
     match p.parent() {
-        Some(parent) => native_result(Value::String(parent.to_string_lossy().to_string())),
+        Some(parent) => native_result(Value::String(parent.to_string_lossy().to_string().into())),
         None => native_result(Value::Null),
     }
 });
 
 // Join path components
 // Usage: file.join "/path/to" "file.txt" -> "/path/to/file.txt"
-native_op!(FileJoin, "path.join", [base, component], {
+native_op!(FileJoin, "path.join", [base: String, component: String] -> String, {
     let base = base.expect_string()?;
     let component = component.expect_string()?;
 
     let result = Path::new(base).join(component);
-    native_result(Value::String(result.to_string_lossy().to_string()))
+    native_result(Value::String(result.to_string_lossy().to_string().into()))
 });
 
 // Get absolute path
 // Usage: file.absolute "./relative/path"
-native_op!(FileAbsolute, "path.absolute", [path], {
+native_op!(FileAbsolute, "path.absolute", [path: String] -> String, {
     let path = path.expect_string()?;
 
     let abs_path = fs::canonicalize(path)
         .map_err(|e| ShikError::default_error(format!("cannot resolve path: {}", e)))?;
 
-    native_result(Value::String(abs_path.to_string_lossy().to_string()))
+    native_result(Value::String(abs_path.to_string_lossy().to_string().into()))
 });
 
 // ============================================================================
@@ -502,7 +503,7 @@ native_op!(FileAbsolute, "path.absolute", [path], {
 
 // Create symbolic link
 // Usage: file.symlink "target" "link_path"
-native_op!(FileSymlink, "file.symlink", [link_path, target], {
+native_op!(FileSymlink, "file.symlink", [link_path: String, target: String] -> Null, {
     let target = target.expect_string()?;
     let link_path = link_path.expect_string()?;
 
@@ -529,13 +530,13 @@ native_op!(FileSymlink, "file.symlink", [link_path, target], {
 
 // Read symlink target
 // Usage: file.read-link "path/to/symlink"
-native_op!(FileReadLink, "file.read-link", [path], {
+native_op!(FileReadLink, "file.read-link", [path: String] -> String, {
     let path = path.expect_string()?;
 
     let target = fs::read_link(path)
         .map_err(|e| ShikError::default_error(format!("cannot read symlink: {}", e)))?;
 
-    native_result(Value::String(target.to_string_lossy().to_string()))
+    native_result(Value::String(target.to_string_lossy().to_string().into()))
 });
 
 // ============================================================================
@@ -544,9 +545,9 @@ native_op!(FileReadLink, "file.read-link", [path], {
 
 // Get system temp directory
 // Usage: file.temp-dir
-native_op!(FileTempDir, "file.temp-dir", [], {
+native_op!(FileTempDir, "file.temp-dir", [] -> String, {
     let temp_dir = std::env::temp_dir();
-    native_result(Value::String(temp_dir.to_string_lossy().to_string()))
+    native_result(Value::String(temp_dir.to_string_lossy().to_string().into()))
 });
 
 // ============================================================================
@@ -583,9 +584,9 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), RuntimeError> {
 pub fn bind_file_module(env: &EnvRef, inter: Rc<Interpretator>) {
     // Reading
     define_native!(FileRead, env, inter);
-    define_native!(FileTryRead, env, inter);
     define_native!(FileReadBytes, env, inter);
     define_native!(FileLines, env, inter);
+    define_native!(FileStreamLines, env, inter);
 
     // Writing
     define_native!(FileWrite, env, inter);
@@ -629,4 +630,33 @@ pub fn bind_file_module(env: &EnvRef, inter: Rc<Interpretator>) {
 
     // Temp
     define_native!(FileTempDir, env, inter);
+
+    // Fallible variants returning results, e.g. file.read? "config.json"
+    define_fallible(
+        env,
+        inter,
+        &[
+            "file.read",
+            "file.read-bytes",
+            "file.read-lines",
+            "file.stream-lines",
+            "file.write",
+            "file.append",
+            "file.write-bytes",
+            "file.copy",
+            "file.move",
+            "file.remove",
+            "file.rmdir",
+            "file.rmdir!",
+            "file.mkdir",
+            "file.mkdir!",
+            "file.size",
+            "file.stat",
+            "file.list",
+            "file.list!",
+            "file.glob",
+            "file.symlink",
+            "file.read-link",
+        ],
+    );
 }
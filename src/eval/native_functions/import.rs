@@ -0,0 +1,61 @@
+use crate::{
+    define_native,
+    eval::{
+        error::RuntimeError,
+        evaluator::Interpretator,
+        module::load,
+        value::{Argument, EnvRef, NativeContext, Object, SpecialClosure, SpecialFn, Value},
+        EvalResult,
+    },
+    special_op,
+};
+use std::path::Path;
+use std::rc::Rc;
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+// Evaluate the file once and define its names with the file name as prefix,
+// returns an object with the definitions
+// Usage: import "lib/git.shk"           ;; git.branch, git.commit, ...
+//        import "lib/git.shk" :g        ;; g.branch, g.commit, ...
+special_op!(Import, "import", args, ctx, {
+    let (path, prefix) = match args {
+        [path] => (path, None),
+        [path, prefix] => (path, Some(prefix)),
+        _ => return Err(RuntimeError::InvalidApplication),
+    };
+
+    let eval_string = |arg: &Argument| -> Result<String, RuntimeError> {
+        let val = ctx.inter.expand(ctx.inter.eval_arg(arg, ctx.env)?)?;
+        Ok(val.expect_string()?.to_string())
+    };
+
+    let path = eval_string(path)?;
+    let prefix = match prefix {
+        Some(prefix) => eval_string(prefix)?,
+        None => Path::new(&path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.clone()),
+    };
+
+    let module = load(ctx.inter, &path)?;
+    // environment keeps no order, names are sorted to be stable
+    let mut exports: Object = module.vars.borrow().clone().into_iter().collect();
+    exports.sort_keys();
+
+    for (name, val) in exports.iter() {
+        ctx.env.define(format!("{}.{}", prefix, name), Rc::clone(val));
+    }
+    Ok(Rc::new(Value::Object(exports)))
+});
+
+// ============================================================================
+// Module Binding
+// ============================================================================
+
+pub fn bind_import_module(env: &EnvRef, inter: Rc<Interpretator>) {
+    define_native!(Import, env, inter);
+}
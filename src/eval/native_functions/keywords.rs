@@ -4,7 +4,7 @@ use crate::{
         evaluator::Interpretator,
         value::{EnvRef, SpecialClosure, SpecialFn, Value, NativeContext},
         EvalResult,
-    }, parser::Expression,
+    },
     special_op,
 };
 use std::{rc::Rc};
@@ -12,7 +12,7 @@ use std::{rc::Rc};
 special_op!(Call, "call", args, ctx, {
         let mut args_it = args.iter().peekable();
         let fun = args_it.next().ok_or(RuntimeError::InvalidApplication)?;
-        let mut fun = ctx.inter.eval_expr(fun, &ctx.env)?;
+        let mut fun = ctx.inter.eval_arg(fun, ctx.env)?;
 
         if args.len() == 1 {
             // If called without arguments - pass null
@@ -20,8 +20,8 @@ special_op!(Call, "call", args, ctx, {
         }
 
         let mut arg = args_it.next();
-        while arg != None {
-            let arg_val = ctx.inter.eval_expr(arg.unwrap(), &ctx.env)?;
+        while arg.is_some() {
+            let arg_val = ctx.inter.eval_arg(arg.unwrap(), ctx.env)?;
             fun = ctx.apply(&fun, &arg_val)?;
             arg = args_it.next();
         }
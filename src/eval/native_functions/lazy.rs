@@ -0,0 +1,48 @@
+use crate::{
+    count_args, define_native,
+    eval::{
+        error::RuntimeError,
+        evaluator::Interpretator,
+        native_functions::native_result,
+        value::{EnvRef, NativeClosure, NativeContext, NativeFn, Value, ValueRef},
+        EvalResult,
+    },
+    native_op,
+};
+use std::rc::Rc;
+
+// ============================================================================
+// Lazy values, made with #( ... )
+// ============================================================================
+
+// Evaluate lazy value. Strict values are returned as is
+// Usage: force #(shell "ls")
+native_op!(LazyForce, ["force", "lazy.force"], [val], ctx, {
+    ctx.inter.force(Rc::clone(val))
+});
+
+// Make lazy value evaluated only once, next forces reuse the result
+// Usage: let config (lazy.memo #(file.read :config.json))
+native_op!(LazyMemo, "lazy.memo", [val], {
+    match val.as_ref() {
+        Value::Lazy(thunk) if thunk.memoized => Ok(Rc::clone(val)),
+        Value::Lazy(thunk) => native_result(Value::Lazy(thunk.memoize())),
+        _ => Ok(Rc::clone(val)),
+    }
+});
+
+// Check if value is lazy
+// Usage: lazy? #(1)
+native_op!(LazyIs, "lazy?", [val] -> Bool, {
+    native_result(Value::Bool(matches!(val.as_ref(), Value::Lazy(_))))
+});
+
+// ============================================================================
+// Module Binding
+// ============================================================================
+
+pub fn bind_lazy_module(env: &EnvRef, inter: Rc<Interpretator>) {
+    define_native!(LazyForce, env, inter);
+    define_native!(LazyMemo, env, inter);
+    define_native!(LazyIs, env, inter);
+}
@@ -3,82 +3,83 @@ use crate::{
     eval::{
         error::RuntimeError,
         evaluator::Interpretator,
-        native_functions::native_result,
+        integer::Integer,
+        native_functions::{native_result, number::Plus},
         value::{
-            EnvRef, NativeClosure, NativeContext, NativeFn, SpecialClosure, SpecialFn, Value,
+            EnvRef, List, NativeClosure, NativeContext, NativeFn, SpecialClosure, SpecialFn, Value,
             ValueRef, ValueType,
         },
         EvalResult,
     },
     native_op,
-    parser::Expression,
     special_op,
 };
 use std::rc::Rc;
 
-native_op!(ListLen, "list.len", [lst], {
+native_op!(ListLen, "list.len", [lst: List] -> Integer, {
     let lst = lst.expect_list()?;
-    native_result(Value::Number(lst.len() as f64))
+    native_result(Value::Integer(Integer::from(lst.len() as i64)))
 });
 
-native_op!(ListSum, "list.sum", [lst], {
+// Sum of integers is an integer, a float makes it a float
+native_op!(ListSum, "list.sum", [lst: List] -> Number, {
     let lst = lst.expect_list()?;
-    let mut sum = 0.0;
+    let mut sum = Rc::new(Value::Integer(Integer::Small(0)));
     for item in lst.iter() {
-        sum += item.expect_number()?;
+        sum = Plus::run(&sum, item)?;
     }
-    native_result(Value::Number(sum))
+    Ok(sum)
 });
 
-native_op!(ListHead, "list.head", [lst], {
+native_op!(ListHead, "list.head", [lst: List], {
     let lst = lst.expect_list()?;
-    match lst.first() {
+    match lst.front() {
         Some(v) => Ok(Rc::clone(v)),
         None => native_result(Value::Null),
     }
 });
 
-native_op!(ListTail, "list.tail", [lst], {
+native_op!(ListTail, "list.tail", [lst: List] -> List, {
     let lst = lst.expect_list()?;
     if lst.is_empty() {
-        native_result(Value::List(vec![]))
+        native_result(Value::List(List::new()))
     } else {
-        native_result(Value::List(lst[1..].to_vec()))
+        native_result(Value::List(lst.skip(1)))
     }
 });
 
-native_op!(ListLast, "list.last", [lst], {
+native_op!(ListLast, "list.last", [lst: List], {
     let lst = lst.expect_list()?;
-    match lst.last() {
+    match lst.back() {
         Some(v) => Ok(Rc::clone(v)),
         None => native_result(Value::Null),
     }
 });
 
-native_op!(ListInit, "list.init", [lst], {
+native_op!(ListInit, "list.init", [lst: List] -> List, {
     let lst = lst.expect_list()?;
     if lst.is_empty() {
-        native_result(Value::List(vec![]))
+        native_result(Value::List(List::new()))
     } else {
-        native_result(Value::List(lst[..lst.len() - 1].to_vec()))
+        native_result(Value::List(lst.take(lst.len() - 1)))
     }
 });
 
-native_op!(ListReverse, "list.reverse", [lst], {
+native_op!(ListReverse, "list.reverse", [lst: List] -> List, {
     let lst = lst.expect_list()?;
-    let reversed: Vec<ValueRef> = lst.iter().rev().cloned().collect();
+    let reversed: List = lst.iter().rev().cloned().collect();
     native_result(Value::List(reversed))
 });
 
-native_op!(ListConcat, "list.concat", [a, b], {
+native_op!(ListConcat, "list.concat", [a: List, b: List] -> List, {
     let a = a.expect_list()?;
     let b = b.expect_list()?;
     let mut result = a.clone();
-    result.extend(b.iter().cloned());
+    result.append(b.clone());
     native_result(Value::List(result))
 });
 
-native_op!(ListAt, "list.at", [idx, lst], {
+native_op!(ListAt, "list.at", [idx: Number, lst: List], {
     let lst = lst.expect_list()?;
     let idx = idx.expect_number()? as usize;
     match lst.get(idx) {
@@ -87,66 +88,66 @@ native_op!(ListAt, "list.at", [idx, lst], {
     }
 });
 
-native_op!(ListIsEmpty, "list.empty?", [lst], {
+native_op!(ListIsEmpty, "list.empty?", [lst: List] -> Bool, {
     let lst = lst.expect_list()?;
     native_result(Value::Bool(lst.is_empty()))
 });
 
 special_op!(ListRange, "list.range", args, ctx, {
     let mut start = 0;
-    let mut end = 0;
+    let end;
     let mut step = 1;
 
     match args.len() {
         1 => {
-            end = ctx.inter.eval_expr(&args[0], &ctx.env)?.expect_number()? as i64;
+            end = ctx.inter.eval_arg(&args[0], ctx.env)?.expect_number()? as i64;
         }
         2 => {
-            start = ctx.inter.eval_expr(&args[0], &ctx.env)?.expect_number()? as i64;
-            end = ctx.inter.eval_expr(&args[1], &ctx.env)?.expect_number()? as i64;
+            start = ctx.inter.eval_arg(&args[0], ctx.env)?.expect_number()? as i64;
+            end = ctx.inter.eval_arg(&args[1], ctx.env)?.expect_number()? as i64;
         }
         3 => {
-            start = ctx.inter.eval_expr(&args[0], &ctx.env)?.expect_number()? as i64;
-            end = ctx.inter.eval_expr(&args[1], &ctx.env)?.expect_number()? as i64;
-            step = ctx.inter.eval_expr(&args[2], &ctx.env)?.expect_number()? as usize;
+            start = ctx.inter.eval_arg(&args[0], ctx.env)?.expect_number()? as i64;
+            end = ctx.inter.eval_arg(&args[1], ctx.env)?.expect_number()? as i64;
+            step = ctx.inter.eval_arg(&args[2], ctx.env)?.expect_number()? as usize;
         }
         _ => return Err(RuntimeError::InvalidApplication),
     }
 
-    let result: Vec<ValueRef> = (start..end)
+    let result: List = (start..end)
         .step_by(step)
-        .map(|n| Rc::new(Value::Number(n as f64)))
+        .map(|n| Rc::new(Value::Integer(Integer::from(n))))
         .collect();
     native_result(Value::List(result))
 });
 
-native_op!(ListTake, "list.take", [n, lst], {
+native_op!(ListTake, "list.take", [n: Number, lst: List] -> List, {
     let lst = lst.expect_list()?;
     let n = n.expect_number()? as usize;
-    let result: Vec<ValueRef> = lst.iter().take(n).cloned().collect();
+    let result = lst.take(n.min(lst.len()));
     native_result(Value::List(result))
 });
 
-native_op!(ListDrop, "list.drop", [n, lst], {
+native_op!(ListDrop, "list.drop", [n: Number, lst: List] -> List, {
     let lst = lst.expect_list()?;
     let n = n.expect_number()? as usize;
-    let result: Vec<ValueRef> = lst.iter().skip(n).cloned().collect();
+    let result = lst.skip(n.min(lst.len()));
     native_result(Value::List(result))
 });
 
 // Higher-order functions using NativeContext to call lambdas
 
-native_op!(ListMap, "list.map", [func, lst], ctx, {
+native_op!(ListMap, "list.map", [func, lst: List] -> List, ctx, {
     let lst = lst.expect_list()?;
-    let mut result: Vec<ValueRef> = Vec::new();
+    let mut result = List::new();
     for item in lst.iter() {
         let mapped = ctx.apply(func, item)?;
-        result.push(mapped);
+        result.push_back(mapped);
     }
     native_result(Value::List(result))
 });
 
-native_op!(ListIterate, "list.iterate", [func, lst], ctx, {
+native_op!(ListIterate, "list.iterate", [func, lst: List] -> Null, ctx, {
     let lst = lst.expect_list()?;
     for item in lst.iter() {
         ctx.apply(func, item)?;
@@ -157,7 +158,7 @@ native_op!(ListIterate, "list.iterate", [func, lst], ctx, {
 native_op!(
     ListIterateBackward,
     ["list.iterate-backward", "list.<iterate"],
-    [func, lst],
+    [func, lst: List] -> Null,
     ctx,
     {
         let lst = lst.expect_list()?;
@@ -168,19 +169,19 @@ native_op!(
     }
 );
 
-native_op!(ListFilter, "list.filter", [func, lst], ctx, {
+native_op!(ListFilter, "list.filter", [func, lst: List] -> List, ctx, {
     let lst = lst.expect_list()?;
-    let mut result: Vec<ValueRef> = Vec::new();
+    let mut result = List::new();
     for item in lst.iter() {
         let predicate_result = ctx.apply(func, item)?;
         if predicate_result.expect_bool()? {
-            result.push(Rc::clone(item));
+            result.push_back(Rc::clone(item));
         }
     }
     native_result(Value::List(result))
 });
 
-native_op!(ListFold, "list.fold", [init, func, lst], ctx, {
+native_op!(ListFold, "list.fold", [init, func, lst: List], ctx, {
     let lst = lst.expect_list()?;
     let mut acc = Rc::clone(init);
     for item in lst.iter() {
@@ -191,7 +192,7 @@ native_op!(ListFold, "list.fold", [init, func, lst], ctx, {
     Ok(acc)
 });
 
-native_op!(ListAny, "list.any", [func, lst], ctx, {
+native_op!(ListAny, "list.any", [func, lst: List] -> Bool, ctx, {
     let lst = lst.expect_list()?;
     for item in lst.iter() {
         let result = ctx.apply(func, item)?;
@@ -202,7 +203,7 @@ native_op!(ListAny, "list.any", [func, lst], ctx, {
     native_result(Value::Bool(false))
 });
 
-native_op!(ListAll, "list.all", [func, lst], ctx, {
+native_op!(ListAll, "list.all", [func, lst: List] -> Bool, ctx, {
     let lst = lst.expect_list()?;
     for item in lst.iter() {
         let result = ctx.apply(func, item)?;
@@ -213,7 +214,7 @@ native_op!(ListAll, "list.all", [func, lst], ctx, {
     native_result(Value::Bool(true))
 });
 
-native_op!(ListFind, "list.find", [func, lst], ctx, {
+native_op!(ListFind, "list.find", [func, lst: List], ctx, {
     let lst = lst.expect_list()?;
     for item in lst.iter() {
         let result = ctx.apply(func, item)?;
@@ -224,18 +225,18 @@ native_op!(ListFind, "list.find", [func, lst], ctx, {
     native_result(Value::Null)
 });
 
-native_op!(ListFindIndex, "list.find-index", [func, lst], ctx, {
+native_op!(ListFindIndex, "list.find-index", [func, lst: List] -> Integer, ctx, {
     let lst = lst.expect_list()?;
     for (inx, item) in lst.iter().enumerate() {
         let result = ctx.apply(func, item)?;
         if result.expect_bool()? {
-            return native_result(Value::Number(inx as f64));
+            return native_result(Value::Integer(Integer::from(inx as i64)));
         }
     }
-    native_result(Value::Number(-1.0))
+    native_result(Value::Integer(Integer::Small(-1)))
 });
 
-native_op!(ListSet, "list.set", [inx, lst, content], {
+native_op!(ListSet, "list.set", [inx: Number, lst: List, content], {
     let inx = inx.expect_number()?;
     let inx = inx as usize;
 
@@ -244,7 +245,7 @@ native_op!(ListSet, "list.set", [inx, lst, content], {
     unsafe {
         match &mut *lst_ptr {
             Value::List(lst) => {
-                lst[inx] = Rc::clone(&content);
+                lst[inx] = Rc::clone(content);
                 return Ok(Rc::clone(content));
             }
             _ => {
@@ -260,14 +261,14 @@ native_op!(ListSet, "list.set", [inx, lst, content], {
 native_op!(
     ListPush,
     ["list.push", "list.push>", "list.push-right"],
-    [lst, content],
+    [lst: List, content],
     {
         let lst_ptr = Rc::as_ptr(lst) as *mut Value;
 
         unsafe {
             match &mut *lst_ptr {
                 Value::List(lst) => {
-                    lst.push(Rc::clone(&content));
+                    lst.push_back(Rc::clone(content));
                     return Ok(Rc::clone(content));
                 }
                 _ => {
@@ -284,14 +285,14 @@ native_op!(
 native_op!(
     ListPushLeft,
     ["list.<push", "list.push-left"],
-    [lst, content],
+    [lst: List, content],
     {
         let lst_ptr = Rc::as_ptr(lst) as *mut Value;
 
         unsafe {
             match &mut *lst_ptr {
                 Value::List(lst) => {
-                    lst.insert(0, Rc::clone(&content));
+                    lst.push_front(Rc::clone(content));
                     return Ok(Rc::clone(content));
                 }
                 _ => {
@@ -1,12 +1,24 @@
 #[macro_export]
 macro_rules! native_op {
-    ($name:ident, $fn_title:tt, [$($arg:ident),* $(,)?] $(, $ctx:ident)? , $body:block) => {
+    // arguments and the result may be annotated with types for `shik check`:
+    // [x: Number, y: Number] -> Number
+    (
+        $name:ident, $fn_title:tt, [$($arg:ident $(: $ty:ident)?),* $(,)?] $(-> $ret:ident)?
+        $(, $ctx:ident)? , $body:block
+    ) => {
         #[derive(Debug)]
         pub struct $name;
 
         impl NativeFn for $name {
+            fn signature(&self) -> Option<$crate::eval::types::Signature> {
+                Some($crate::eval::types::Signature {
+                    params: vec![$($crate::native_op!(@type $($ty)?)),*],
+                    ret: $crate::native_op!(@type $($ret)?),
+                })
+            }
+
             #[allow(unused_variables)]
-            fn exec(&self, args: &Vec<ValueRef>, __native_ctx: &NativeContext) -> EvalResult {
+            fn exec(&self, args: &[ValueRef], __native_ctx: &NativeContext) -> EvalResult {
                 if args.len() != $crate::count_args!($($arg),*) {
                     return Err(RuntimeError::InvalidApplication);
                 }
@@ -46,6 +58,9 @@ macro_rules! native_op {
         }
     };
 
+    (@type) => { $crate::eval::types::Type::Any };
+    (@type $ty:ident) => { $crate::eval::types::Type::$ty };
+
     (@bind_ctx $native_ctx:ident, $ctx:ident) => { let $ctx = $native_ctx; };
     (@bind_ctx $native_ctx:ident) => {};
 
@@ -63,16 +78,42 @@ macro_rules! native_op {
 
 #[macro_export]
 macro_rules! special_op {
+    // `tail` block picks the argument to evaluate in tail position, see `SpecialFn::exec_tail`
+    ($name:ident, $fn_title:expr, $args:ident, $ctx:ident, $body:block, tail $tail:block) => {
+        #[derive(Debug)]
+        pub struct $name;
+
+        impl SpecialFn for $name {
+            fn exec(&self, $args: &[$crate::eval::value::Argument], $ctx: &NativeContext) -> EvalResult {
+                $body
+            }
+
+            fn exec_tail(
+                &self,
+                $args: &[$crate::eval::value::Argument],
+                $ctx: &NativeContext,
+            ) -> Result<$crate::eval::value::TailArg, RuntimeError> {
+                $tail
+            }
+        }
+
+        $crate::special_op!(@define $name, $fn_title);
+    };
+
     ($name:ident, $fn_title:expr, $args:ident, $ctx:ident, $body:block) => {
         #[derive(Debug)]
         pub struct $name;
 
         impl SpecialFn for $name {
-            fn exec(&self, $args: &Vec<Expression>, $ctx: &NativeContext) -> EvalResult {
+            fn exec(&self, $args: &[$crate::eval::value::Argument], $ctx: &NativeContext) -> EvalResult {
                 $body
             }
         }
 
+        $crate::special_op!(@define $name, $fn_title);
+    };
+
+    (@define $name:ident, $fn_title:expr) => {
         impl $name {
             pub fn define(env: &EnvRef, inter: Rc<Interpretator>) {
                 env.define(
@@ -5,7 +5,6 @@ use crate::{
         value::{EnvRef, NativeContext, SpecialClosure, SpecialFn, Value},
         EvalResult,
     },
-    parser::Expression,
     special_op,
     define_native,
 };
@@ -18,14 +17,14 @@ use std::rc::Rc;
 // If got null, convert to the value on the right side
 // Usage: null $> or? 10
 special_op!(IfNull, "or?", args, ctx, {
-    let mut args_it = args.into_iter();
+    let mut args_it = args.iter();
 
     let on_null = args_it.next().ok_or(RuntimeError::InvalidApplication)?;
     let val = args_it.next().ok_or(RuntimeError::InvalidApplication)?;
-    let val = ctx.inter.eval_expr(val, &ctx.env)?;
+    let val = ctx.inter.force(ctx.inter.eval_arg(val, ctx.env)?)?;
 
     Ok(match val.as_ref() {
-        Value::Null => Rc::clone(&ctx.inter.eval_expr(on_null, ctx.env)?),
+        Value::Null => ctx.inter.force(ctx.inter.eval_arg(on_null, ctx.env)?)?,
         _ => Rc::clone(&val),
     })
 });
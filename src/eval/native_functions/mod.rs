@@ -8,9 +8,17 @@ pub mod macros;
 pub mod file;
 pub mod string;
 pub mod list;
+pub mod object;
 pub mod variables;
 pub mod shell;
 pub mod misc;
+pub mod lazy;
+pub mod error;
+pub mod import;
+pub mod code;
+pub mod result;
+pub mod reference;
+pub mod stream;
 
 use crate::eval::{value::Value, EvalResult};
 use std::rc::Rc;
@@ -3,112 +3,252 @@ use crate::{
     eval::{
         error::RuntimeError,
         evaluator::Interpretator,
+        integer::Integer,
         native_functions::native_result,
         value::{EnvRef, NativeClosure, NativeContext, NativeFn, Value, ValueRef},
         EvalResult,
     },
     native_op,
 };
+use std::cmp::Ordering;
 use std::rc::Rc;
 
-native_op!(Plus, "number.+", [x, y], {
-    let x = x.expect_number()?;
-    let y = y.expect_number()?;
+/// Operands of arithmetic: two integers stay integers, otherwise both are floats
+enum Operands<'a> {
+    Integers(&'a Integer, &'a Integer),
+    Floats(f64, f64),
+}
 
-    native_result(Value::Number(x + y))
-});
+fn operands<'a>(x: &'a Value, y: &'a Value) -> Result<Operands<'a>, RuntimeError> {
+    match (x, y) {
+        (Value::Integer(x), Value::Integer(y)) => Ok(Operands::Integers(x, y)),
+        _ => Ok(Operands::Floats(x.expect_number()?, y.expect_number()?)),
+    }
+}
 
-native_op!(Minus, ["-", "number.-"], [y, x], {
-    let x = x.expect_number()?;
-    let y = y.expect_number()?;
+/// Order of two numbers, `None` if one of them is NaN
+pub fn compare(x: &Value, y: &Value) -> Result<Option<Ordering>, RuntimeError> {
+    Ok(match operands(x, y)? {
+        Operands::Integers(x, y) => Some(x.cmp(y)),
+        Operands::Floats(x, y) => x.partial_cmp(&y),
+    })
+}
+
+/// Integer of a whole float, infinities and NaN stay floats
+fn whole(x: f64) -> Value {
+    match Integer::from_f64(x) {
+        Some(x) => Value::Integer(x),
+        None => Value::Number(x),
+    }
+}
 
-    native_result(Value::Number(x - y))
+fn division_by_zero() -> RuntimeError {
+    RuntimeError::Arithmetic("division by zero".to_string())
+}
+
+native_op!(Plus, "number.+", [x: Number, y: Number] -> Number, {
+    native_result(match operands(x, y)? {
+        Operands::Integers(x, y) => Value::Integer(x.add(y)),
+        Operands::Floats(x, y) => Value::Number(x + y),
+    })
 });
 
-native_op!(Multiply, ["*", "number.*"], [x, y], {
-    let x = x.expect_number()?;
-    let y = y.expect_number()?;
+native_op!(Minus, ["-", "number.-"], [y: Number, x: Number] -> Number, {
+    native_result(match operands(x, y)? {
+        Operands::Integers(x, y) => Value::Integer(x.sub(y)),
+        Operands::Floats(x, y) => Value::Number(x - y),
+    })
+});
 
-    native_result(Value::Number(x * y))
+native_op!(Multiply, ["*", "number.*"], [x: Number, y: Number] -> Number, {
+    native_result(match operands(x, y)? {
+        Operands::Integers(x, y) => Value::Integer(x.mul(y)),
+        Operands::Floats(x, y) => Value::Number(x * y),
+    })
 });
 
-native_op!(Divide, ["/", "number./"], [y, x], {
+// Division is always of floats, `number.div` divides integers
+native_op!(Divide, ["/", "number./"], [y: Number, x: Number] -> Number, {
     let x = x.expect_number()?;
     let y = y.expect_number()?;
 
     native_result(Value::Number(x / y))
 });
 
-native_op!(Mod, ["%", "number.%"], [y, x], {
-    let x = x.expect_number()?;
-    let y = y.expect_number()?;
-    native_result(Value::Number(x % y))
+// Quotient of integers, rounded toward zero
+// Usage: number.div 3 10      ;; 3
+native_op!(Div, "number.div", [y: Integer, x: Integer] -> Integer, {
+    let x = x.expect_integer()?;
+    let y = y.expect_integer()?;
+
+    native_result(Value::Integer(x.div(y).ok_or_else(division_by_zero)?))
 });
 
-native_op!(Abs, "number.abs", [x], {
-    let x = x.expect_number()?;
-    native_result(Value::Number(x.abs()))
+// Remainder with the sign of the dividend
+native_op!(Mod, ["%", "number.%"], [y: Number, x: Number] -> Number, {
+    native_result(match operands(x, y)? {
+        Operands::Integers(x, y) => Value::Integer(x.rem(y).ok_or_else(division_by_zero)?),
+        Operands::Floats(x, y) => Value::Number(x % y),
+    })
 });
 
-native_op!(Floor, "number.floor", [x], {
-    let x = x.expect_number()?;
-    native_result(Value::Number(x.floor()))
+native_op!(Abs, "number.abs", [x: Number] -> Number, {
+    match x.as_ref() {
+        Value::Integer(x) => native_result(Value::Integer(x.abs())),
+        _ => native_result(Value::Number(x.expect_number()?.abs())),
+    }
 });
 
-native_op!(Ceil, "number.ceil", [x], {
-    let x = x.expect_number()?;
-    native_result(Value::Number(x.ceil()))
+native_op!(Floor, "number.floor", [x: Number] -> Integer, {
+    match x.as_ref() {
+        Value::Integer(_) => Ok(Rc::clone(x)),
+        _ => native_result(whole(x.expect_number()?.floor())),
+    }
 });
 
-native_op!(Round, "number.round", [x], {
-    let x = x.expect_number()?;
-    native_result(Value::Number(x.round()))
+native_op!(Ceil, "number.ceil", [x: Number] -> Integer, {
+    match x.as_ref() {
+        Value::Integer(_) => Ok(Rc::clone(x)),
+        _ => native_result(whole(x.expect_number()?.ceil())),
+    }
 });
 
-native_op!(Min, "number.min", [x, y], {
-    let x = x.expect_number()?;
-    let y = y.expect_number()?;
-    native_result(Value::Number(x.min(y)))
+native_op!(Round, "number.round", [x: Number] -> Integer, {
+    match x.as_ref() {
+        Value::Integer(_) => Ok(Rc::clone(x)),
+        _ => native_result(whole(x.expect_number()?.round())),
+    }
 });
 
-native_op!(Max, "number.max", [x, y], {
-    let x = x.expect_number()?;
-    let y = y.expect_number()?;
-    native_result(Value::Number(x.max(y)))
+native_op!(Min, "number.min", [x: Number, y: Number] -> Number, {
+    match compare(x, y)? {
+        Some(Ordering::Greater) => Ok(Rc::clone(y)),
+        _ => Ok(Rc::clone(x)),
+    }
+});
+
+native_op!(Max, "number.max", [x: Number, y: Number] -> Number, {
+    match compare(x, y)? {
+        Some(Ordering::Less) => Ok(Rc::clone(y)),
+        _ => Ok(Rc::clone(x)),
+    }
 });
 
-native_op!(Pow, ["^", "number.pow"], [exp, base], {
+// Integer raised to a non-negative integer stays an integer
+// powers and left shifts make integers longer, a million bits is far more than is ever needed
+const MAX_BITS: u64 = 1 << 20;
+
+native_op!(Pow, ["^", "number.pow"], [exp: Number, base: Number] -> Number, {
+    if let (Value::Integer(base), Value::Integer(exp)) = (base.as_ref(), exp.as_ref()) {
+        if let Some(exp) = exp.to_i64().and_then(|exp| u32::try_from(exp).ok()) {
+            // the power has at least this many bits
+            if base.bits().saturating_sub(1).saturating_mul(u64::from(exp)) > MAX_BITS {
+                return Err(RuntimeError::Arithmetic(format!(
+                    "power would be longer than {} bits",
+                    MAX_BITS
+                )));
+            }
+            return native_result(Value::Integer(base.pow(exp)));
+        }
+    }
+
     let base = base.expect_number()?;
     let exp = exp.expect_number()?;
     native_result(Value::Number(base.powf(exp)))
 });
 
-native_op!(Sqrt, "number.sqrt", [x], {
+// Integer part of the number
+// Usage: number.int 2.7       ;; 2
+native_op!(Int, "number.int", [x: Number] -> Integer, {
+    match x.as_ref() {
+        Value::Integer(_) => Ok(Rc::clone(x)),
+        _ => {
+            let x = x.expect_number()?;
+            let int = Integer::from_f64(x)
+                .ok_or_else(|| RuntimeError::Arithmetic(format!("{} has no integer part", x)))?;
+            native_result(Value::Integer(int))
+        }
+    }
+});
+
+// Nearest float, large integers lose precision
+// Usage: number.float 2       ;; 2.0
+native_op!(Float, "number.float", [x: Number] -> Number, {
+    native_result(Value::Number(x.expect_number()?))
+});
+
+// ============================================================================
+// Bitwise operations, negative integers are in two's complement
+// ============================================================================
+
+native_op!(BitAnd, "number.and", [y: Integer, x: Integer] -> Integer, {
+    native_result(Value::Integer(x.expect_integer()?.and(y.expect_integer()?)))
+});
+
+native_op!(BitOr, "number.or", [y: Integer, x: Integer] -> Integer, {
+    native_result(Value::Integer(x.expect_integer()?.or(y.expect_integer()?)))
+});
+
+native_op!(BitXor, "number.xor", [y: Integer, x: Integer] -> Integer, {
+    native_result(Value::Integer(x.expect_integer()?.xor(y.expect_integer()?)))
+});
+
+native_op!(BitNot, "number.not", [x: Integer] -> Integer, {
+    native_result(Value::Integer(x.expect_integer()?.not()))
+});
+
+/// Number of bits to shift by
+fn shift(bits: &Value) -> Result<usize, RuntimeError> {
+    bits.expect_integer()?
+        .to_i64()
+        .and_then(|bits| usize::try_from(bits).ok())
+        .ok_or_else(|| RuntimeError::Arithmetic(format!("cannot shift by {} bits", bits)))
+}
+
+// Usage: number.shl 4 1       ;; 16
+native_op!(ShiftLeft, "number.shl", [bits: Integer, x: Integer] -> Integer, {
+    let bits = shift(bits)?;
+    if bits > MAX_BITS as usize {
+        return Err(RuntimeError::Arithmetic(format!(
+            "cannot shift left by more than {} bits",
+            MAX_BITS
+        )));
+    }
+    native_result(Value::Integer(x.expect_integer()?.shl(bits)))
+});
+
+// Usage: number.shr 4 256     ;; 16
+native_op!(ShiftRight, "number.shr", [bits: Integer, x: Integer] -> Integer, {
+    let bits = shift(bits)?;
+    native_result(Value::Integer(x.expect_integer()?.shr(bits)))
+});
+
+native_op!(Sqrt, "number.sqrt", [x: Number] -> Number, {
     let x = x.expect_number()?;
     native_result(Value::Number(x.sqrt()))
 });
 
-native_op!(Sin, "number.sin", [x], {
+native_op!(Sin, "number.sin", [x: Number] -> Number, {
     let x = x.expect_number()?;
     native_result(Value::Number(x.sin()))
 });
 
-native_op!(Cos, "number.cos", [x], {
+native_op!(Cos, "number.cos", [x: Number] -> Number, {
     let x = x.expect_number()?;
     native_result(Value::Number(x.cos()))
 });
 
-native_op!(Tan, "number.tan", [x], {
+native_op!(Tan, "number.tan", [x: Number] -> Number, {
     let x = x.expect_number()?;
     native_result(Value::Number(x.tan()))
 });
 
-native_op!(Log, "number.log", [x], {
+native_op!(Log, "number.log", [x: Number] -> Number, {
     let x = x.expect_number()?;
     native_result(Value::Number(x.ln()))
 });
 
-native_op!(Log10, "number.log10", [x], {
+native_op!(Log10, "number.log10", [x: Number] -> Number, {
     let x = x.expect_number()?;
     native_result(Value::Number(x.log10()))
 });
@@ -125,6 +265,15 @@ pub fn bind_number_module(env: &EnvRef, inter: Rc<Interpretator>) {
     define_native!(Min, env, inter);
     define_native!(Max, env, inter);
     define_native!(Mod, env, inter);
+    define_native!(Div, env, inter);
+    define_native!(Int, env, inter);
+    define_native!(Float, env, inter);
+    define_native!(BitAnd, env, inter);
+    define_native!(BitOr, env, inter);
+    define_native!(BitXor, env, inter);
+    define_native!(BitNot, env, inter);
+    define_native!(ShiftLeft, env, inter);
+    define_native!(ShiftRight, env, inter);
     define_native!(Pow, env, inter);
     define_native!(Sqrt, env, inter);
     define_native!(Sin, env, inter);
@@ -0,0 +1,270 @@
+use crate::{
+    count_args, define_native,
+    eval::{
+        error::{RuntimeError, ShikError},
+        evaluator::Interpretator,
+        native_functions::native_result,
+        value::{
+            EnvRef, List, NativeClosure, NativeContext, NativeFn, Object, Value, ValueRef, ValueType,
+        },
+        EvalResult,
+    },
+    native_op,
+};
+use std::rc::Rc;
+
+fn pair(key: &str, val: &ValueRef) -> ValueRef {
+    let key = Rc::new(Value::String(key.to_string().into()));
+    Rc::new(Value::List(List::from(vec![key, Rc::clone(val)])))
+}
+
+// ============================================================================
+// Reading
+// ============================================================================
+
+// Value by the key, null if there is no such key
+// Usage: obj.get :name user
+native_op!(ObjGet, "obj.get", [key: String, obj: Object], {
+    let key = key.expect_string()?;
+    let obj = obj.expect_obj()?;
+
+    match obj.get(key.as_str()) {
+        Some(val) => Ok(Rc::clone(val)),
+        None => native_result(Value::Null),
+    }
+});
+
+// Usage: obj.has? :name user
+native_op!(ObjHas, "obj.has?", [key: String, obj: Object] -> Bool, {
+    let key = key.expect_string()?;
+    let obj = obj.expect_obj()?;
+
+    native_result(Value::Bool(obj.contains_key(key.as_str())))
+});
+
+// Usage: obj.keys user ;; [ name age ]
+native_op!(ObjKeys, "obj.keys", [obj: Object] -> List, {
+    let obj = obj.expect_obj()?;
+    let keys = obj
+        .keys()
+        .map(|key| Rc::new(Value::String(key.clone().into())))
+        .collect();
+
+    native_result(Value::List(keys))
+});
+
+// Usage: obj.values user ;; [ bob 42 ]
+native_op!(ObjValues, "obj.values", [obj: Object] -> List, {
+    let obj = obj.expect_obj()?;
+    native_result(Value::List(obj.values().cloned().collect()))
+});
+
+// Key-value pairs of the object
+// Usage: obj.entries user ;; [ [ name bob ] [ age 42 ] ]
+native_op!(ObjEntries, "obj.entries", [obj: Object] -> List, {
+    let obj = obj.expect_obj()?;
+    let entries = obj.iter().map(|(key, val)| pair(key, val)).collect();
+
+    native_result(Value::List(entries))
+});
+
+// Same object with the keys in alphabetical order
+// Usage: shell.env.all $> obj.sorted $> obj.keys
+native_op!(ObjSorted, "obj.sorted", [obj: Object] -> Object, {
+    let mut obj = obj.expect_obj()?.clone();
+
+    obj.sort_keys();
+    native_result(Value::Object(obj))
+});
+
+/// List index of the path, it must be a whole number which is not negative
+fn path_index(key: &Value) -> Result<usize, RuntimeError> {
+    let inx = match key {
+        Value::Integer(x) => x.to_i64(),
+        Value::Number(x) if x.fract() == 0.0 => Some(*x as i64),
+        _ => None,
+    };
+    inx.and_then(|inx| usize::try_from(inx).ok()).ok_or_else(|| {
+        ShikError::default_error(format!("path index must be a whole number from 0, got {}", key))
+    })
+}
+
+// Value by the path of keys (or list indexes), null if some part of the path is missing
+// Usage: obj.get-in [ :server :ports 0 ] config
+native_op!(ObjGetIn, "obj.get-in", [path: List, obj], {
+    let path = path.expect_list()?;
+    let mut current = Rc::clone(obj);
+
+    for key in path.iter() {
+        let next = match (current.as_ref(), key.as_ref()) {
+            (Value::Object(obj), Value::String(key)) => obj.get(key.as_str()).cloned(),
+            (Value::List(lst), Value::Number(_) | Value::Integer(_)) => {
+                lst.get(path_index(key)?).cloned()
+            }
+            _ => None,
+        };
+
+        match next {
+            Some(val) => current = val,
+            None => return native_result(Value::Null),
+        }
+    }
+    Ok(current)
+});
+
+// ============================================================================
+// Making new objects
+// ============================================================================
+
+// New object with the key set to the value
+// Usage: obj.set :name user :alice
+native_op!(ObjSet, "obj.set", [key: String, obj: Object, val] -> Object, {
+    let key = key.expect_string()?;
+    let mut obj = obj.expect_obj()?.clone();
+
+    obj.insert(key.to_string(), Rc::clone(val));
+    native_result(Value::Object(obj))
+});
+
+// New object without the key
+// Usage: obj.remove :password user
+native_op!(ObjRemove, "obj.remove", [key: String, obj: Object] -> Object, {
+    let key = key.expect_string()?;
+    let mut obj = obj.expect_obj()?.clone();
+
+    obj.shift_remove(key.as_str());
+    native_result(Value::Object(obj))
+});
+
+// Put keys of the second object into the first one, the second object wins on conflicts
+// Usage: obj.merge defaults options
+native_op!(ObjMerge, "obj.merge", [dst: Object, src: Object] -> Object, {
+    let mut dst = dst.expect_obj()?.clone();
+    let src = src.expect_obj()?;
+
+    for (key, val) in src.iter() {
+        dst.insert(key.clone(), Rc::clone(val));
+    }
+    native_result(Value::Object(dst))
+});
+
+// New object with the value set by the path of keys (or list indexes), missing objects
+// on the way are created, indexes must be inside of their lists
+// Usage: obj.set-in [ :server :port ] config 8080
+native_op!(ObjSetIn, "obj.set-in", [path: List, obj: Object, val] -> Object, {
+    let path: Vec<ValueRef> = path.expect_list()?.iter().cloned().collect();
+
+    fn set_in(path: &[ValueRef], current: &Value, val: &ValueRef) -> EvalResult {
+        let Some((key, rest)) = path.split_first() else {
+            return Ok(Rc::clone(val));
+        };
+
+        match (current, key.as_ref()) {
+            (Value::Object(obj), Value::String(key)) => {
+                let mut obj = obj.clone();
+                let inner = match obj.get(key.as_str()) {
+                    Some(inner) => set_in(rest, inner, val)?,
+                    None => set_in(rest, &Value::Object(Object::new()), val)?,
+                };
+                obj.insert(key.to_string(), inner);
+                native_result(Value::Object(obj))
+            }
+            (Value::List(lst), Value::Number(_) | Value::Integer(_)) => {
+                let inx = path_index(key)?;
+                let Some(inner) = lst.get(inx) else {
+                    return Err(RuntimeError::IndexOutOfBounds { index: inx });
+                };
+                let inner = set_in(rest, inner, val)?;
+                native_result(Value::List(lst.update(inx, inner)))
+            }
+            (_, Value::String(_)) => Err(RuntimeError::MissmatchedTypes {
+                got: current.get_type(),
+                expected: ValueType::Object,
+            }),
+            (_, Value::Number(_) | Value::Integer(_)) => Err(RuntimeError::MissmatchedTypes {
+                got: current.get_type(),
+                expected: ValueType::List,
+            }),
+            (_, key) => Err(RuntimeError::MissmatchedTypes {
+                got: key.get_type(),
+                expected: ValueType::String,
+            }),
+        }
+    }
+
+    obj.expect_obj()?;
+    set_in(&path, obj, val)
+});
+
+// Object from the list of key-value pairs
+// Usage: obj.from-entries [ [ :name :bob ] [ :age 42 ] ]
+native_op!(ObjFromEntries, "obj.from-entries", [entries: List] -> Object, {
+    let mut obj = Object::new();
+
+    for entry in entries.expect_list()?.iter() {
+        let entry = entry.expect_list()?;
+        if entry.len() != 2 {
+            return Err(RuntimeError::InvalidApplication);
+        }
+        obj.insert(entry[0].expect_string()?.to_string(), Rc::clone(&entry[1]));
+    }
+    native_result(Value::Object(obj))
+});
+
+// ============================================================================
+// Higher-order functions
+// ============================================================================
+
+// Apply the function to each value
+// Usage: obj.map-values (+ 1) counters
+native_op!(ObjMapValues, "obj.map-values", [func, obj: Object] -> Object, ctx, {
+    let obj = obj.expect_obj()?;
+    let mut result = Object::new();
+
+    for (key, val) in obj.iter() {
+        result.insert(key.clone(), ctx.apply(func, val)?);
+    }
+    native_result(Value::Object(result))
+});
+
+// Keep the entries the predicate returns true for, the predicate gets the key and the value
+// Usage: obj.filter (fn [key value] < 0 value) counters
+native_op!(ObjFilter, "obj.filter", [func, obj: Object] -> Object, ctx, {
+    let obj = obj.expect_obj()?;
+    let mut result = Object::new();
+
+    for (key, val) in obj.iter() {
+        let key_val = Rc::new(Value::String(key.clone().into()));
+        let partial = ctx.apply(func, &key_val)?;
+        if ctx.apply(&partial, val)?.expect_bool()? {
+            result.insert(key.clone(), Rc::clone(val));
+        }
+    }
+    native_result(Value::Object(result))
+});
+
+// ============================================================================
+// Module Binding
+// ============================================================================
+
+pub fn bind_object_module(env: &EnvRef, inter: Rc<Interpretator>) {
+    // Reading
+    define_native!(ObjGet, env, inter);
+    define_native!(ObjHas, env, inter);
+    define_native!(ObjKeys, env, inter);
+    define_native!(ObjValues, env, inter);
+    define_native!(ObjEntries, env, inter);
+    define_native!(ObjSorted, env, inter);
+    define_native!(ObjGetIn, env, inter);
+
+    // Making new objects
+    define_native!(ObjSet, env, inter);
+    define_native!(ObjRemove, env, inter);
+    define_native!(ObjMerge, env, inter);
+    define_native!(ObjSetIn, env, inter);
+    define_native!(ObjFromEntries, env, inter);
+
+    // Higher-order functions
+    define_native!(ObjMapValues, env, inter);
+    define_native!(ObjFilter, env, inter);
+}
@@ -3,7 +3,7 @@ use crate::{
     eval::{
         error::RuntimeError,
         evaluator::Interpretator,
-        native_functions::{list::{ListAt, ListIterate, ListIterateBackward}, native_result, number::Plus, string::{StringCharAt, StringConcat, StringIterate, StringIterateBackward}},
+        native_functions::{list::{ListAt, ListIterate, ListIterateBackward}, number::Plus, string::{StringCharAt, StringConcat, StringIterate, StringIterateBackward}},
         value::{EnvRef, NativeClosure, NativeContext, NativeFn, Value, ValueRef},
         EvalResult,
     },
@@ -14,7 +14,9 @@ use std::rc::Rc;
 native_op!(PPlus, "+", [x, y], {
     match (x.as_ref(), y.as_ref()) {
         (Value::String(_), Value::String(_)) => StringConcat::run(x, y),
-        (Value::Number(_), Value::Number(_)) => Plus::run(x, y),
+        (Value::Number(_) | Value::Integer(_), Value::Number(_) | Value::Integer(_)) => {
+            Plus::run(x, y)
+        }
 
         (Value::String(_), other) => {
             StringConcat::run(x, &other.into_string())
@@ -11,8 +11,8 @@ use crate::{
 };
 use std::rc::Rc;
 
-native_op!(Print, "print", [arg], {
-    println!("{}", arg.to_string());
+native_op!(Print, "print", [arg] -> Null, {
+    println!("{}", arg);
 
     native_result(Value::Null)
 });
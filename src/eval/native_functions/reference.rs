@@ -0,0 +1,69 @@
+use crate::{
+    count_args, define_native,
+    eval::{
+        error::RuntimeError,
+        evaluator::Interpretator,
+        native_functions::native_result,
+        value::{EnvRef, NativeClosure, NativeContext, NativeFn, Value, ValueRef},
+        EvalResult,
+    },
+    native_op,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// ============================================================================
+// Reference cells
+// ============================================================================
+
+// New cell holding the value, closures sharing the cell see the changes of each other
+// Usage: let counter (ref 0)
+native_op!(RefNew, "ref", [val] -> Ref, {
+    native_result(Value::Ref(RefCell::new(Rc::clone(val))))
+});
+
+// Current value of the cell
+// Usage: ref.get counter
+native_op!(RefGet, "ref.get", [cell], {
+    let cell = cell.expect_ref()?;
+    let val = Rc::clone(&cell.borrow());
+    Ok(val)
+});
+
+// Put the value into the cell, returns the value
+// Usage: ref.set counter 10
+native_op!(RefSet, "ref.set", [cell, val], ctx, {
+    cell.expect_ref()?.replace(Rc::clone(val));
+    ctx.inter.heap.track_cell(cell, val);
+    Ok(Rc::clone(val))
+});
+
+// Replace the value of the cell with the result of the function, returns the new value
+// Usage: ref.update counter (+ 1)
+native_op!(RefUpdate, "ref.update", [cell, func], ctx, {
+    // the cell is not borrowed while the function runs, so it may read the cell too
+    let current = Rc::clone(&cell.expect_ref()?.borrow());
+    let val = ctx.apply(func, &current)?;
+
+    cell.expect_ref()?.replace(Rc::clone(&val));
+    ctx.inter.heap.track_cell(cell, &val);
+    Ok(val)
+});
+
+// Check if value is a reference cell
+// Usage: ref? counter
+native_op!(RefIs, "ref?", [val] -> Bool, {
+    native_result(Value::Bool(matches!(val.as_ref(), Value::Ref(_))))
+});
+
+// ============================================================================
+// Module Binding
+// ============================================================================
+
+pub fn bind_ref_module(env: &EnvRef, inter: Rc<Interpretator>) {
+    define_native!(RefNew, env, inter);
+    define_native!(RefGet, env, inter);
+    define_native!(RefSet, env, inter);
+    define_native!(RefUpdate, env, inter);
+    define_native!(RefIs, env, inter);
+}
@@ -0,0 +1,173 @@
+use crate::{
+    count_args, define_native,
+    eval::{
+        error::RuntimeError,
+        evaluator::Interpretator,
+        native_functions::{
+            error::{error_object, raise},
+            native_result,
+        },
+        types::{Signature, Type},
+        value::{EnvRef, NativeClosure, NativeContext, NativeFn, Value, ValueRef},
+        EvalResult,
+    },
+    native_op,
+};
+use std::rc::Rc;
+
+// ============================================================================
+// Fallible variants of native functions
+// ============================================================================
+
+// Runs the native function, returning its error as `err` result instead of raising it
+#[derive(Debug)]
+pub struct Fallible(Rc<dyn NativeFn>);
+
+impl NativeFn for Fallible {
+    fn exec(&self, args: &[ValueRef], ctx: &NativeContext) -> EvalResult {
+        match self.0.exec(args, ctx) {
+            Ok(val) => native_result(Value::Result(Ok(val))),
+            Err(err) => native_result(Value::Result(Err(error_object(err)))),
+        }
+    }
+
+    fn signature(&self) -> Option<Signature> {
+        let signature = self.0.signature()?;
+        Some(Signature {
+            params: signature.params,
+            ret: Type::Result,
+        })
+    }
+}
+
+// Defines `name?` for each of the native functions, returning a result
+pub fn define_fallible(env: &EnvRef, inter: Rc<Interpretator>, titles: &[&str]) {
+    for title in titles {
+        let val = env.lookup(title);
+        // a wrong name is a mistake in the interpreter, release builds only skip it
+        debug_assert!(
+            matches!(val.as_deref(), Some(Value::NativeLambda(_))),
+            "'{}' is not a native function",
+            title
+        );
+        let Some(Value::NativeLambda(closure)) = val.as_deref() else {
+            continue;
+        };
+
+        let fallible = Rc::new(Value::NativeLambda(NativeClosure::new(
+            closure.params_count,
+            Rc::new(Fallible(Rc::clone(&closure.logic))),
+            Rc::clone(&inter),
+            Rc::clone(env),
+        )));
+        env.define(format!("{}?", title), fallible);
+    }
+}
+
+// ============================================================================
+// Making results
+// ============================================================================
+
+// Successful result
+// Usage: result.ok 10
+native_op!(ResultOk, "result.ok", [val] -> Result, {
+    native_result(Value::Result(Ok(Rc::clone(val))))
+});
+
+// Failed result, the payload is usually a message or an error object
+// Usage: result.err "not found"
+native_op!(ResultErr, "result.err", [err] -> Result, {
+    native_result(Value::Result(Err(Rc::clone(err))))
+});
+
+// ============================================================================
+// Transforming results
+// ============================================================================
+
+// Apply the function to the ok value, errors are passed as is
+// Usage: file.read? :notes.txt $> result.map string.upper
+native_op!(ResultMap, "result.map", [f, res: Result] -> Result, ctx, {
+    match res.expect_result()? {
+        Ok(val) => native_result(Value::Result(Ok(ctx.apply(f, val)?))),
+        Err(_) => Ok(Rc::clone(res)),
+    }
+});
+
+// Continue with another fallible step, the function returns a result for the ok value
+// Usage: shell? "git rev-parse HEAD" $> result.and-then (fn [sha] shell? "git show {sha}")
+native_op!(ResultAndThen, "result.and-then", [f, res: Result] -> Result, ctx, {
+    match res.expect_result()? {
+        Ok(val) => {
+            let next = ctx.apply(f, val)?;
+            match next.as_ref() {
+                Value::Result(_) => Ok(next),
+                _ => native_result(Value::Result(Ok(next))),
+            }
+        }
+        Err(_) => Ok(Rc::clone(res)),
+    }
+});
+
+// ============================================================================
+// Reading results
+// ============================================================================
+
+// Ok value, or the default on error
+// Usage: file.read? :config.json $> result.unwrap-or ""
+native_op!(ResultUnwrapOr, ["result.unwrap-or", "result.read-or"], [default, res: Result], {
+    match res.expect_result()? {
+        Ok(val) => Ok(Rc::clone(val)),
+        Err(_) => Ok(Rc::clone(default)),
+    }
+});
+
+// Ok value, the error is raised
+// Usage: result.unwrap (file.read? :config.json)
+native_op!(ResultUnwrap, "result.unwrap", [res: Result], {
+    match res.expect_result()? {
+        Ok(val) => Ok(Rc::clone(val)),
+        Err(err) => Err(raise(err)),
+    }
+});
+
+// Error payload, or null for ok result
+// Usage: result.error (shell? "make")
+native_op!(ResultError, "result.error", [res: Result], {
+    match res.expect_result()? {
+        Ok(_) => native_result(Value::Null),
+        Err(err) => Ok(Rc::clone(err)),
+    }
+});
+
+// Usage: result.is-ok? (file.read? :notes.txt)
+native_op!(ResultIsOk, "result.is-ok?", [res: Result] -> Bool, {
+    native_result(Value::Bool(res.expect_result()?.is_ok()))
+});
+
+// Usage: result.is-err? (file.read? :notes.txt)
+native_op!(ResultIsErr, "result.is-err?", [res: Result] -> Bool, {
+    native_result(Value::Bool(res.expect_result()?.is_err()))
+});
+
+// Check if value is a result
+// Usage: result? 10
+native_op!(ResultIs, "result?", [val] -> Bool, {
+    native_result(Value::Bool(matches!(val.as_ref(), Value::Result(_))))
+});
+
+// ============================================================================
+// Module Binding
+// ============================================================================
+
+pub fn bind_result_module(env: &EnvRef, inter: Rc<Interpretator>) {
+    define_native!(ResultOk, env, inter);
+    define_native!(ResultErr, env, inter);
+    define_native!(ResultMap, env, inter);
+    define_native!(ResultAndThen, env, inter);
+    define_native!(ResultUnwrapOr, env, inter);
+    define_native!(ResultUnwrap, env, inter);
+    define_native!(ResultError, env, inter);
+    define_native!(ResultIsOk, env, inter);
+    define_native!(ResultIsErr, env, inter);
+    define_native!(ResultIs, env, inter);
+}
@@ -3,21 +3,25 @@ use crate::{
     eval::{
         error::{RuntimeError, ShikError},
         evaluator::Interpretator,
-        native_functions::native_result,
+        integer::Integer,
+        native_functions::{
+            error::error_object,
+            native_result,
+            result::define_fallible,
+        },
+        stream::{Lines, Pulled, Source, Stream},
         value::{
-            EnvRef, NativeClosure, NativeContext, NativeFn, SpecialClosure, SpecialFn, Value,
-            ValueRef, ValueType,
+            EnvRef, List, NativeClosure, NativeContext, NativeFn, Object, SpecialClosure, SpecialFn,
+            Value, ValueRef,
         },
         EvalResult,
     },
     native_op,
-    parser::Expression,
     special_op,
 };
-use std::io::{self, Write};
-use std::collections::HashMap;
+use std::io::{self, BufReader, Write};
 use std::env;
-use std::process::{Command, Stdio};
+use std::process::{Child, ChildStdout, Command, Output, Stdio};
 use std::rc::Rc;
 
 // ============================================================================
@@ -26,7 +30,7 @@ use std::rc::Rc;
 
 // Execute a shell command and return stdout as a string
 // Usage: shell "ls -la"
-native_op!(Shell, "shell", [cmd], {
+native_op!(Shell, "shell", [cmd: String] -> String, {
     let cmd = cmd.expect_string()?;
 
     let output = if cfg!(target_os = "windows") {
@@ -38,7 +42,7 @@ native_op!(Shell, "shell", [cmd], {
     match output {
         Ok(output) => {
             let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-            native_result(Value::String(stdout))
+            native_result(Value::String(stdout.into()))
         }
         Err(e) => Err(ShikError::default_error(format!(
             "shell command failed: {}",
@@ -49,7 +53,7 @@ native_op!(Shell, "shell", [cmd], {
 
 // Execute a shell command, return exit code and show output in terminal
 // Usage: shell.code "ls -la"
-native_op!(ShellExec, "shell!", [cmd], {
+native_op!(ShellExec, "shell!", [cmd: String] -> Integer, {
     let cmd = cmd.expect_string()?;
 
     let status = if cfg!(target_os = "windows") {
@@ -60,8 +64,8 @@ native_op!(ShellExec, "shell!", [cmd], {
 
     match status {
         Ok(status) => {
-            let code = status.code().unwrap_or(-1) as f64;
-            native_result(Value::Number(code))
+            let code = Integer::from(i64::from(status.code().unwrap_or(-1)));
+            native_result(Value::Integer(code))
         }
         Err(e) => Err(ShikError::default_error(format!(
             "shell command failed: {}",
@@ -72,7 +76,7 @@ native_op!(ShellExec, "shell!", [cmd], {
 
 // Execute a shell command and return exit code, discards output
 // Usage: shell.code "ls -la"
-native_op!(ShellCode, "shell.code", [cmd], {
+native_op!(ShellCode, "shell.code", [cmd: String] -> Integer, {
     let cmd = cmd.expect_string()?;
 
     let res = if cfg!(target_os = "windows") {
@@ -83,8 +87,8 @@ native_op!(ShellCode, "shell.code", [cmd], {
 
     match res {
         Ok(output) => {
-            let code = output.status.code().unwrap_or(-1) as f64;
-            native_result(Value::Number(code))
+            let code = Integer::from(i64::from(output.status.code().unwrap_or(-1)));
+            native_result(Value::Integer(code))
         }
         Err(e) => Err(ShikError::default_error(format!(
             "shell command failed: {}",
@@ -95,7 +99,7 @@ native_op!(ShellCode, "shell.code", [cmd], {
 
 // Execute a shell command and return an object with stdout, stderr, and code
 // Usage: shell.full "ls -la"
-native_op!(ShellFull, "shell.full", [cmd], {
+native_op!(ShellFull, "shell.full", [cmd: String] -> Object, {
     let cmd = cmd.expect_string()?;
 
     let output = if cfg!(target_os = "windows") {
@@ -105,22 +109,7 @@ native_op!(ShellFull, "shell.full", [cmd], {
     };
 
     match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            let code = output.status.code().unwrap_or(-1) as f64;
-
-            let mut result: HashMap<String, ValueRef> = HashMap::new();
-            result.insert("stdout".to_string(), Rc::new(Value::String(stdout)));
-            result.insert("stderr".to_string(), Rc::new(Value::String(stderr)));
-            result.insert("code".to_string(), Rc::new(Value::Number(code)));
-            result.insert(
-                "ok".to_string(),
-                Rc::new(Value::Bool(output.status.success())),
-            );
-
-            native_result(Value::Object(result))
-        }
+        Ok(output) => native_result(Value::Object(full(&output))),
         Err(e) => Err(ShikError::default_error(format!(
             "shell command failed: {}",
             e
@@ -128,9 +117,9 @@ native_op!(ShellFull, "shell.full", [cmd], {
     }
 });
 
-// Try to execute a shell command, return null on failure
-// Usage: shell.try "ls -la"
-native_op!(ShellTry, "shell?", [cmd], {
+// Like shell.full, but a command exiting with an error code gives err
+// Usage: shell.full? "make test"
+native_op!(ShellFullTry, "shell.full?", [cmd: String] -> Result, {
     let cmd = cmd.expect_string()?;
 
     let output = if cfg!(target_os = "windows") {
@@ -139,18 +128,65 @@ native_op!(ShellTry, "shell?", [cmd], {
         Command::new("sh").args(["-c", cmd]).output()
     };
 
-    match output {
+    let err = match output {
+        Ok(output) if output.status.success() => {
+            return native_result(Value::Result(Ok(Rc::new(Value::Object(full(&output))))));
+        }
+        Ok(output) => failure(&output),
+        Err(e) => ShikError::default_error(format!("shell command failed: {}", e)),
+    };
+    native_result(Value::Result(Err(error_object(err))))
+});
+
+/// Object with stdout, stderr, code and ok of the finished command
+fn full(output: &Output) -> Object {
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let code = Integer::from(i64::from(output.status.code().unwrap_or(-1)));
+
+    let mut result = Object::new();
+    result.insert("stdout".to_string(), Rc::new(Value::String(stdout.into())));
+    result.insert("stderr".to_string(), Rc::new(Value::String(stderr.into())));
+    result.insert("code".to_string(), Rc::new(Value::Integer(code)));
+    result.insert(
+        "ok".to_string(),
+        Rc::new(Value::Bool(output.status.success())),
+    );
+    result
+}
+
+/// Error of a command which exited with an error code: its stderr and the code
+fn failure(output: &Output) -> RuntimeError {
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let code = Integer::from(i64::from(output.status.code().unwrap_or(-1)));
+    ShikError::raise("ShellError".to_string(), stderr, Rc::new(Value::Integer(code)))
+}
+
+// Try to execute a shell command, return ok with stdout or err with stderr and exit code
+// Usage: shell? "ls -la"
+native_op!(ShellTry, "shell?", [cmd: String] -> Result, {
+    let cmd = cmd.expect_string()?;
+
+    let output = if cfg!(target_os = "windows") {
+        Command::new("cmd").args(["/C", cmd]).output()
+    } else {
+        Command::new("sh").args(["-c", cmd]).output()
+    };
+
+    let err = match output {
         Ok(output) if output.status.success() => {
             let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-            native_result(Value::String(stdout))
+            return native_result(Value::Result(Ok(Rc::new(Value::String(stdout.into())))));
         }
-        _ => native_result(Value::Null),
-    }
+        Ok(output) => failure(&output),
+        Err(e) => ShikError::default_error(format!("shell command failed: {}", e)),
+    };
+    native_result(Value::Result(Err(error_object(err))))
 });
 
 // Execute a shell command silently (discard output), return success boolean
 // Usage: shell.ok "mkdir -p /tmp/test"
-native_op!(ShellOk, "shell.ok?", [cmd], {
+native_op!(ShellOk, "shell.ok?", [cmd: String] -> Bool, {
     let cmd = cmd.expect_string()?;
 
     let status = if cfg!(target_os = "windows") {
@@ -175,7 +211,7 @@ native_op!(ShellOk, "shell.ok?", [cmd], {
 
 // Execute a shell command and return lines as a list
 // Usage: shell.lines "ls"
-native_op!(ShellLines, "shell.lines", [cmd], {
+native_op!(ShellLines, "shell.lines", [cmd: String] -> List, {
     let cmd = cmd.expect_string()?;
 
     let output = if cfg!(target_os = "windows") {
@@ -185,14 +221,7 @@ native_op!(ShellLines, "shell.lines", [cmd], {
     };
 
     match output {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let lines: Vec<ValueRef> = stdout
-                .lines()
-                .map(|line| Rc::new(Value::String(line.to_string())))
-                .collect();
-            native_result(Value::List(lines))
-        }
+        Ok(output) => native_result(Value::List(lines(&output))),
         Err(e) => Err(ShikError::default_error(format!(
             "shell command failed: {}",
             e
@@ -200,6 +229,118 @@ native_op!(ShellLines, "shell.lines", [cmd], {
     }
 });
 
+// Like shell.lines, but a command exiting with an error code gives err
+// Usage: shell.lines? "git ls-files"
+native_op!(ShellLinesTry, "shell.lines?", [cmd: String] -> Result, {
+    let cmd = cmd.expect_string()?;
+
+    let output = if cfg!(target_os = "windows") {
+        Command::new("cmd").args(["/C", cmd]).output()
+    } else {
+        Command::new("sh").args(["-c", cmd]).output()
+    };
+
+    let err = match output {
+        Ok(output) if output.status.success() => {
+            return native_result(Value::Result(Ok(Rc::new(Value::List(lines(&output))))));
+        }
+        Ok(output) => failure(&output),
+        Err(e) => ShikError::default_error(format!("shell command failed: {}", e)),
+    };
+    native_result(Value::Result(Err(error_object(err))))
+});
+
+/// Lines of the command output
+fn lines(output: &Output) -> List {
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| Rc::new(Value::String(line.into())))
+        .collect()
+}
+
+/// Lines of a running command, it is stopped if the stream is dropped before the end
+struct CommandLines {
+    child: Child,
+    lines: Lines<BufReader<ChildStdout>>,
+    // an error code of the finished command is raised at the end of the stream
+    checked: bool,
+}
+
+impl Source for CommandLines {
+    fn next(&mut self, inter: &Interpretator) -> Pulled {
+        let line = self.lines.next(inter)?;
+        if line.is_none() {
+            // reap the finished command
+            let status = self.child.wait();
+            if let Some(status) = status.ok().filter(|status| self.checked && !status.success()) {
+                let code = Integer::from(i64::from(status.code().unwrap_or(-1)));
+                return Err(ShikError::raise(
+                    "ShellError".to_string(),
+                    format!("command exited with code {}", code),
+                    Rc::new(Value::Integer(code)),
+                ));
+            }
+        }
+        Ok(line)
+    }
+}
+
+impl Drop for CommandLines {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+// Execute a shell command and read the lines of its output while it runs
+// Usage: shell.stream-lines "tail -f app.log" $> stream.filter (string.has "ERROR")
+native_op!(ShellStreamLines, "shell.stream-lines", [cmd: String] -> Stream, {
+    let cmd = cmd.expect_string()?;
+    native_result(Value::Stream(command_lines(cmd, false)?))
+});
+
+// Like shell.stream-lines, but a command which cannot be started gives err,
+// and one exiting with an error code raises ShellError at the end of the stream
+// Usage: shell.stream-lines? "make" $> result.map (stream.iterate print)
+native_op!(ShellStreamLinesTry, "shell.stream-lines?", [cmd: String] -> Result, {
+    let cmd = cmd.expect_string()?;
+    let res = match command_lines(cmd, true) {
+        Ok(stream) => Ok(Rc::new(Value::Stream(stream))),
+        Err(err) => Err(error_object(err)),
+    };
+    native_result(Value::Result(res))
+});
+
+/// Stream of the lines of the started command
+fn command_lines(cmd: &str, checked: bool) -> Result<Stream, RuntimeError> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", cmd]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", cmd]);
+        command
+    };
+
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| ShikError::default_error(format!("shell command failed: {}", e)))?;
+    let Some(stdout) = child.stdout.take() else {
+        return Err(ShikError::default_error("shell command has no output".to_string()));
+    };
+
+    Ok(Stream::new(CommandLines {
+        child,
+        lines: Lines(BufReader::new(stdout)),
+        checked,
+    }))
+}
+
 // ============================================================================
 // Input/Output
 // ============================================================================
@@ -213,7 +354,7 @@ special_op!(ShellRead, "shell.ask", args, ctx, {
 
     // Optional prompt
     if args.len() == 1 {
-        let prompt_val = ctx.inter.eval_expr(&args[0], &ctx.env)?;
+        let prompt_val = ctx.inter.eval_arg(&args[0], ctx.env)?;
         let prompt = prompt_val.expect_string()?;
 
         print!("{prompt}");
@@ -235,7 +376,7 @@ special_op!(ShellRead, "shell.ask", args, ctx, {
 
     // Strip trailing newline(s)
     let line = line.trim_end_matches(&['\r', '\n'][..]).to_string();
-    native_result(Value::String(line))
+    native_result(Value::String(line.into()))
 });
 
 // ============================================================================
@@ -244,18 +385,18 @@ special_op!(ShellRead, "shell.ask", args, ctx, {
 
 // Get an environment variable, null if not found
 // Usage: shell.env "HOME"
-native_op!(ShellEnv, "shell.env", [name], {
+native_op!(ShellEnv, "shell.env", [name: String], {
     let name = name.expect_string()?;
 
     match env::var(name) {
-        Ok(value) => native_result(Value::String(value)),
+        Ok(value) => native_result(Value::String(value.into())),
         Err(_) => native_result(Value::Null),
     }
 });
 
 // Set an environment variable (for current process)
 // Usage: shell.set-env "MY_VAR" "value"
-native_op!(ShellSetEnv, "shell.env.set", [name, value], {
+native_op!(ShellSetEnv, "shell.env.set", [name: String, value: String] -> Null, {
     let name = name.expect_string()?;
     let value = value.expect_string()?;
 
@@ -267,7 +408,7 @@ native_op!(ShellSetEnv, "shell.env.set", [name, value], {
 
 // Remove an environment variable
 // Usage: shell.unset-env "MY_VAR"
-native_op!(ShellUnsetEnv, "shell.env.remove", [name], {
+native_op!(ShellUnsetEnv, "shell.env.remove", [name: String] -> Null, {
     let name = name.expect_string()?;
     // SAFETY: We're removing environment variables in a single-threaded context
     // This is safe as long as no other threads are reading environment variables
@@ -277,11 +418,11 @@ native_op!(ShellUnsetEnv, "shell.env.remove", [name], {
 
 // Get all environment variables as an object
 // Usage: shell.env-all
-native_op!(ShellEnvAll, "shell.env.all", [], {
-    let mut result: HashMap<String, ValueRef> = HashMap::new();
+native_op!(ShellEnvAll, "shell.env.all", [] -> Object, {
+    let mut result = Object::new();
 
     for (key, value) in env::vars() {
-        result.insert(key, Rc::new(Value::String(value)));
+        result.insert(key, Rc::new(Value::String(value.into())));
     }
 
     native_result(Value::Object(result))
@@ -293,9 +434,9 @@ native_op!(ShellEnvAll, "shell.env.all", [], {
 
 // Get current working directory
 // Usage: shell.cwd
-native_op!(ShellCwd, "shell.cwd", [], {
+native_op!(ShellCwd, "shell.cwd", [] -> String, {
     match env::current_dir() {
-        Ok(path) => native_result(Value::String(path.to_string_lossy().to_string())),
+        Ok(path) => native_result(Value::String(path.to_string_lossy().to_string().into())),
         Err(e) => Err(ShikError::default_error(format!(
             "cannot get current directory: {}",
             e
@@ -305,7 +446,7 @@ native_op!(ShellCwd, "shell.cwd", [], {
 
 // Change current working directory
 // Usage: shell.cd "/tmp"
-native_op!(ShellCd, "shell.cd", [path], {
+native_op!(ShellCd, "shell.cd", [path: String] -> Null, {
     let path = path.expect_string()?;
 
     match env::set_current_dir(path) {
@@ -319,9 +460,9 @@ native_op!(ShellCd, "shell.cd", [path], {
 
 // Get home directory
 // Usage: shell.home
-native_op!(ShellHome, "shell.home", [], {
+native_op!(ShellHome, "shell.home", [] -> String, {
     match env::var("HOME").or_else(|_| env::var("USERPROFILE")) {
-        Ok(home) => native_result(Value::String(home)),
+        Ok(home) => native_result(Value::String(home.into())),
         Err(_) => Err(ShikError::default_error(
             "cannot determine home directory".to_string(),
         )),
@@ -334,7 +475,7 @@ native_op!(ShellHome, "shell.home", [], {
 
 // Find executable in PATH
 // Usage: shell.which "git"
-native_op!(ShellWhich, "shell.which", [name], {
+native_op!(ShellWhich, "shell.which", [name: String], {
     let name = name.expect_string()?;
 
     let path_var = env::var("PATH").unwrap_or_default();
@@ -347,14 +488,14 @@ native_op!(ShellWhich, "shell.which", [name], {
     for dir in path_var.split(path_sep) {
         let candidate = std::path::Path::new(dir).join(name);
         if candidate.exists() {
-            return native_result(Value::String(candidate.to_string_lossy().to_string()));
+            return native_result(Value::String(candidate.to_string_lossy().to_string().into()));
         }
         // On Windows, also check with common extensions
         if cfg!(target_os = "windows") {
             for ext in &[".exe", ".cmd", ".bat", ".com"] {
                 let with_ext = candidate.with_extension(&ext[1..]);
                 if with_ext.exists() {
-                    return native_result(Value::String(with_ext.to_string_lossy().to_string()));
+                    return native_result(Value::String(with_ext.to_string_lossy().to_string().into()));
                 }
             }
         }
@@ -365,7 +506,7 @@ native_op!(ShellWhich, "shell.which", [name], {
 
 // Check if a command exists in PATH
 // Usage: shell.has "git"
-native_op!(ShellHas, "shell.has", [name], {
+native_op!(ShellHas, "shell.has", [name: String] -> Bool, {
     let name = name.expect_string()?;
 
     let path_var = env::var("PATH").unwrap_or_default();
@@ -399,27 +540,27 @@ native_op!(ShellHas, "shell.has", [name], {
 
 // Get current process ID
 // Usage: process.pid
-native_op!(ProcessPid, "process.pid", [], {
-    native_result(Value::Number(std::process::id() as f64))
+native_op!(ProcessPid, "process.pid", [] -> Integer, {
+    native_result(Value::Integer(Integer::from(i64::from(std::process::id()))))
 });
 
 // Get command line arguments
 // Usage: shell.args
-native_op!(ShellArgs, "shell.args", [], {
-    let args: Vec<ValueRef> = env::args().map(|arg| Rc::new(Value::String(arg))).collect();
+native_op!(ShellArgs, "shell.args", [] -> List, {
+    let args: List = env::args().map(|arg| Rc::new(Value::String(arg.into()))).collect();
     native_result(Value::List(args))
 });
 
 // Get command line arguments, without caller and filename.
 // Usage: process.args
-native_op!(ProcessArgs, "process.args", [], {
+native_op!(ProcessArgs, "process.args", [] -> List, {
     let mut args = env::args();
     if args.len() < 3 {
-        return native_result(Value::List([].to_vec()));
+        return native_result(Value::List(List::new()));
     }
     args.next(); // skip shik
     args.next(); // skip filename
-    let args: Vec<ValueRef> = args.map(|arg| Rc::new(Value::String(arg))).collect();
+    let args: List = args.map(|arg| Rc::new(Value::String(arg.into()))).collect();
     native_result(Value::List(args))
 });
 
@@ -432,19 +573,19 @@ native_op!(ProcessFile, "process.file", [], {
     }
     args.next(); // skip shik
     let name = args.next().unwrap();
-    native_result(Value::String(name))
+    native_result(Value::String(name.into()))
 });
 
 // Get OS name
 // Usage: shell.os
-native_op!(ShellOs, "shell.os", [], {
-    native_result(Value::String(env::consts::OS.to_string()))
+native_op!(ShellOs, "shell.os", [] -> String, {
+    native_result(Value::String(env::consts::OS.into()))
 });
 
 // Get architecture
 // Usage: shell.arch
-native_op!(ShellArch, "shell.arch", [], {
-    native_result(Value::String(env::consts::ARCH.to_string()))
+native_op!(ShellArch, "shell.arch", [] -> String, {
+    native_result(Value::String(env::consts::ARCH.into()))
 });
 
 // ============================================================================
@@ -453,7 +594,7 @@ native_op!(ShellArch, "shell.arch", [], {
 
 // Exit the process with a specific exit code
 // Usage: exit 0
-native_op!(ProcessExit, "exit", [code], {
+native_op!(ProcessExit, "exit", [code: Number], {
     let code = code.expect_number()? as i32;
     std::process::exit(code);
 });
@@ -472,7 +613,7 @@ native_op!(ProcessAbort, "process.abort", [], {
 
 // Sleep for specified milliseconds
 // Usage: process.sleep 1000
-native_op!(ProcessSleep, "process.sleep", [ms], {
+native_op!(ProcessSleep, "process.sleep", [ms: Number] -> Null, {
     let ms = ms.expect_number()? as u64;
     std::thread::sleep(std::time::Duration::from_millis(ms));
     native_result(Value::Null)
@@ -488,9 +629,13 @@ pub fn bind_shell_module(env: &EnvRef, inter: Rc<Interpretator>) {
     define_native!(ShellExec, env, inter);
     define_native!(ShellCode, env, inter);
     define_native!(ShellFull, env, inter);
+    define_native!(ShellFullTry, env, inter);
     define_native!(ShellTry, env, inter);
     define_native!(ShellOk, env, inter);
     define_native!(ShellLines, env, inter);
+    define_native!(ShellLinesTry, env, inter);
+    define_native!(ShellStreamLines, env, inter);
+    define_native!(ShellStreamLinesTry, env, inter);
 
     // Environment variables
     define_native!(ShellEnv, env, inter);
@@ -523,4 +668,7 @@ pub fn bind_shell_module(env: &EnvRef, inter: Rc<Interpretator>) {
     define_native!(ProcessExitSuccess, env, inter);
     define_native!(ProcessAbort, env, inter);
     define_native!(ProcessSleep, env, inter);
+
+    // Fallible variants returning results
+    define_fallible(env, inter, &["shell.cd"]);
 }
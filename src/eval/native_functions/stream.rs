@@ -0,0 +1,142 @@
+use crate::{
+    count_args, define_native,
+    eval::{
+        error::RuntimeError,
+        evaluator::Interpretator,
+        integer::Integer,
+        native_functions::native_result,
+        stream::{Filter, Map, Range, Stream, Take},
+        value::{
+            EnvRef, List, NativeClosure, NativeContext, NativeFn, SpecialClosure, SpecialFn, Value,
+            ValueRef,
+        },
+        EvalResult,
+    },
+    native_op,
+    special_op,
+};
+use std::rc::Rc;
+
+// ============================================================================
+// Sources
+// ============================================================================
+
+// Integers of the range, made one by one, arguments are the same as of list.range
+// Usage: stream.range 1 1000000000
+special_op!(StreamRange, "stream.range", args, ctx, {
+    let mut start = Integer::Small(0);
+    let end;
+    let mut step = Integer::Small(1);
+
+    match args.len() {
+        1 => {
+            end = ctx.inter.eval_arg(&args[0], ctx.env)?.expect_integer()?.clone();
+        }
+        2 => {
+            start = ctx.inter.eval_arg(&args[0], ctx.env)?.expect_integer()?.clone();
+            end = ctx.inter.eval_arg(&args[1], ctx.env)?.expect_integer()?.clone();
+        }
+        3 => {
+            start = ctx.inter.eval_arg(&args[0], ctx.env)?.expect_integer()?.clone();
+            end = ctx.inter.eval_arg(&args[1], ctx.env)?.expect_integer()?.clone();
+            step = ctx.inter.eval_arg(&args[2], ctx.env)?.expect_integer()?.clone();
+        }
+        _ => return Err(RuntimeError::InvalidApplication),
+    }
+
+    if step.is_zero() {
+        return Err(RuntimeError::Arithmetic("range with zero step".to_string()));
+    }
+    native_result(Value::Stream(Stream::new(Range {
+        next: start,
+        end: Some(end),
+        step,
+    })))
+});
+
+// Endless stream of integers counting from the start
+// Usage: stream.from 1 $> stream.take 5
+native_op!(StreamFrom, "stream.from", [start: Integer] -> Stream, {
+    native_result(Value::Stream(Stream::new(Range {
+        next: start.expect_integer()?.clone(),
+        end: None,
+        step: Integer::Small(1),
+    })))
+});
+
+// ============================================================================
+// Transformations, nothing is read until the stream is consumed
+// ============================================================================
+
+native_op!(StreamMap, "stream.map", [func, s: Stream] -> Stream, {
+    s.expect_stream()?;
+    native_result(Value::Stream(Stream::new(Map {
+        func: Rc::clone(func),
+        from: Rc::clone(s),
+    })))
+});
+
+native_op!(StreamFilter, "stream.filter", [func, s: Stream] -> Stream, {
+    s.expect_stream()?;
+    native_result(Value::Stream(Stream::new(Filter {
+        func: Rc::clone(func),
+        from: Rc::clone(s),
+    })))
+});
+
+native_op!(StreamTake, "stream.take", [n: Number, s: Stream] -> Stream, {
+    s.expect_stream()?;
+    native_result(Value::Stream(Stream::new(Take {
+        left: n.expect_number()? as usize,
+        from: Rc::clone(s),
+    })))
+});
+
+// ============================================================================
+// Consuming streams
+// ============================================================================
+
+native_op!(StreamFold, "stream.fold", [init, func, s: Stream], ctx, {
+    let stream = s.expect_stream()?;
+    let mut acc = Rc::clone(init);
+    while let Some(item) = stream.next(ctx.inter)? {
+        let partial = ctx.apply(func, &acc)?;
+        acc = ctx.apply(&partial, &item)?;
+    }
+    Ok(acc)
+});
+
+native_op!(StreamIterate, "stream.iterate", [func, s: Stream] -> Null, ctx, {
+    let stream = s.expect_stream()?;
+    while let Some(item) = stream.next(ctx.inter)? {
+        ctx.apply(func, &item)?;
+    }
+    native_result(Value::Null)
+});
+
+// Values left in the stream as a list, the stream must be finite
+// Usage: stream.range 10 $> stream.collect
+native_op!(StreamCollect, "stream.collect", [s: Stream] -> List, ctx, {
+    let stream = s.expect_stream()?;
+    let mut result = List::new();
+    while let Some(item) = stream.next(ctx.inter)? {
+        result.push_back(item);
+    }
+    native_result(Value::List(result))
+});
+
+native_op!(StreamIs, "stream?", [val] -> Bool, {
+    native_result(Value::Bool(matches!(val.as_ref(), Value::Stream(_))))
+});
+
+pub fn bind_stream_module(env: &EnvRef, inter: Rc<Interpretator>) {
+    define_native!(StreamRange, env, inter);
+    define_native!(StreamFrom, env, inter);
+    define_native!(StreamMap, env, inter);
+    define_native!(StreamFilter, env, inter);
+    define_native!(StreamTake, env, inter);
+    define_native!(StreamFold, env, inter);
+    define_native!(StreamIterate, env, inter);
+    define_native!(StreamCollect, env, inter);
+    define_native!(StreamIs, env, inter);
+}
@@ -1,157 +1,162 @@
 use crate::{
     count_args, define_native,
     eval::{
-        error::RuntimeError,
+        error::{RuntimeError, ShikError},
         evaluator::Interpretator,
+        format,
+        integer::Integer,
         native_functions::native_result,
-        value::{EnvRef, NativeClosure, NativeContext, NativeFn, Value, ValueRef, ValueType},
+        value::{EnvRef, List, NativeClosure, NativeContext, NativeFn, Value, ValueRef, ValueType},
         EvalResult,
     },
     native_op,
+    parser::format::FormatSpec,
 };
+use std::cell::RefCell;
 use std::rc::Rc;
 
-native_op!(MakeString, "string", [x], {
+native_op!(MakeString, "string", [x] -> String, {
     native_result(match x.as_ref() {
-        Value::Number(n) => Value::String(n.to_string()),
         Value::String(s) => Value::String(s.clone()),
-        _ => Value::String(x.to_string()),
+        _ => Value::String(x.to_string().into()),
     })
 });
 
-native_op!(StringSplit, "string.split", [with, str], {
+native_op!(StringSplit, "string.split", [with: String, str: String] -> List, {
     let str = str.expect_string()?;
     let with = with.expect_string()?;
 
     let res = str
-        .split(with)
-        .map(|s| Rc::new(Value::String(s.to_string())))
-        .collect::<Vec<ValueRef>>();
+        .split(with.as_str())
+        .map(|s| Rc::new(Value::String(s.into())))
+        .collect::<List>();
 
     native_result(Value::List(res))
 });
 
-native_op!(StringConcat, "string.+", [a, b], {
+native_op!(StringConcat, "string.+", [a: String, b: String] -> String, {
     let a = a.expect_string()?;
     let b = b.expect_string()?;
-    native_result(Value::String(format!("{}{}", a, b)))
+    native_result(Value::String(a.concat(b)))
 });
 
-native_op!(StringEq, "string.=", [a, b], {
+native_op!(StringEq, "string.=", [a: String, b: String] -> Bool, {
     let a = a.expect_string()?;
     let b = b.expect_string()?;
 
     native_result(Value::Bool(a == b))
 });
 
-native_op!(StringTrim, "string.trim", [s], {
+native_op!(StringTrim, "string.trim", [s: String] -> String, {
     let s = s.expect_string()?;
-    native_result(Value::String(s.trim().to_string()))
+    native_result(Value::String(s.trim().into()))
 });
 
-native_op!(StringTrimStart, "string.trim-start", [s], {
+native_op!(StringTrimStart, "string.trim-start", [s: String] -> String, {
     let s = s.expect_string()?;
-    native_result(Value::String(s.trim_start().to_string()))
+    native_result(Value::String(s.trim_start().into()))
 });
 
-native_op!(StringTrimEnd, "string.trim-end", [s], {
+native_op!(StringTrimEnd, "string.trim-end", [s: String] -> String, {
     let s = s.expect_string()?;
-    native_result(Value::String(s.trim_end().to_string()))
+    native_result(Value::String(s.trim_end().into()))
 });
 
-native_op!(StringUppercase, "string.upper", [s], {
+native_op!(StringUppercase, "string.upper", [s: String] -> String, {
     let s = s.expect_string()?;
-    native_result(Value::String(s.to_uppercase()))
+    native_result(Value::String(s.to_uppercase().into()))
 });
 
-native_op!(StringLowercase, "string.lower", [s], {
+native_op!(StringLowercase, "string.lower", [s: String] -> String, {
     let s = s.expect_string()?;
-    native_result(Value::String(s.to_lowercase()))
+    native_result(Value::String(s.to_lowercase().into()))
 });
 
-native_op!(StringContains, "string.has", [needle, haystack], {
+native_op!(StringContains, "string.has", [needle: String, haystack: String] -> Bool, {
     let haystack = haystack.expect_string()?;
     let needle = needle.expect_string()?;
     native_result(Value::Bool(haystack.contains(needle.as_str())))
 });
 
-native_op!(StringStartsWith, "string.starts-with", [prefix, s], {
+native_op!(StringStartsWith, "string.starts-with", [prefix: String, s: String] -> Bool, {
     let s = s.expect_string()?;
     let prefix = prefix.expect_string()?;
     native_result(Value::Bool(s.starts_with(prefix.as_str())))
 });
 
-native_op!(StringEndsWith, "string.ends-with", [suffix, s], {
+native_op!(StringEndsWith, "string.ends-with", [suffix: String, s: String] -> Bool, {
     let s = s.expect_string()?;
     let suffix = suffix.expect_string()?;
     native_result(Value::Bool(s.ends_with(suffix.as_str())))
 });
 
-native_op!(StringReplace, "string.replace", [from, to, s], {
+native_op!(StringReplace, "string.replace", [from: String, to: String, s: String] -> String, {
     let s = s.expect_string()?;
     let from = from.expect_string()?;
     let to = to.expect_string()?;
-    native_result(Value::String(s.replace(from.as_str(), to.as_str())))
+    native_result(Value::String(s.replace(from.as_str(), to.as_str()).into()))
 });
 
-native_op!(StringLength, "string.len", [s], {
+native_op!(StringLength, "string.len", [s: String] -> Integer, {
     let s = s.expect_string()?;
-    native_result(Value::Number(s.chars().count() as f64))
+    native_result(Value::Integer(Integer::from(s.chars().count() as i64)))
 });
 
-native_op!(StringCharAt, "string.at", [idx, s], {
+native_op!(StringCharAt, "string.at", [idx: Number, s: String], {
     let s = s.expect_string()?;
     let idx = idx.expect_number()? as usize;
     let ch = s.chars().nth(idx);
     match ch {
-        Some(c) => native_result(Value::String(c.to_string())),
+        Some(c) => native_result(Value::String(c.to_string().into())),
         None => native_result(Value::Null),
     }
 });
 
-native_op!(StringSubstring, "string.slice", [start, end, s], {
+native_op!(StringSubstring, "string.slice", [start: Number, end: Number, s: String] -> String, {
     let s = s.expect_string()?;
     let start = start.expect_number()? as usize;
     let end = end.expect_number()? as usize;
     let result: String = s.chars().skip(start).take(end - start).collect();
-    native_result(Value::String(result))
+    native_result(Value::String(result.into()))
 });
 
-native_op!(StringIndexOf, "string.index-of", [needle, haystack], {
+native_op!(StringIndexOf, "string.index-of", [needle: String, haystack: String] -> Integer, {
     let haystack = haystack.expect_string()?;
     let needle = needle.expect_string()?;
     match haystack.find(needle.as_str()) {
-        Some(idx) => native_result(Value::Number(idx as f64)),
-        None => native_result(Value::Number(-1.0)),
+        Some(idx) => native_result(Value::Integer(Integer::from(idx as i64))),
+        None => native_result(Value::Integer(Integer::Small(-1))),
     }
 });
 
-native_op!(StringJoin, "string.join", [sep, lst], {
+native_op!(StringJoin, "string.join", [sep: String, lst: List] -> String, {
     let lst = lst.expect_list()?;
     let sep = sep.expect_string()?;
-    let strings: Result<Vec<String>, _> = lst
-        .iter()
-        .map(|v| v.expect_string().map(|s| s.clone()))
-        .collect();
-    let strings = strings?;
-    native_result(Value::String(strings.join(sep.as_str())))
+    let mut res = String::new();
+    for (i, v) in lst.iter().enumerate() {
+        if i > 0 {
+            res.push_str(sep);
+        }
+        res.push_str(v.expect_string()?);
+    }
+    native_result(Value::String(res.into()))
 });
 
-native_op!(StringLines, "string.lines", [s], {
+native_op!(StringLines, "string.lines", [s: String] -> List, {
     let s = s.expect_string()?;
-    let lines: Vec<ValueRef> = s
+    let lines: List = s
         .lines()
-        .map(|line| Rc::new(Value::String(line.to_string())))
+        .map(|line| Rc::new(Value::String(line.into())))
         .collect();
     native_result(Value::List(lines))
 });
 
 const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
-native_op!(StringBytes, "string.bytes", [b], {
+native_op!(StringBytes, "string.bytes", [b: Number] -> String, {
     let bytes = b.expect_number()?;
 
     if bytes < 1024.0 {
-        return native_result(Value::String("{bytes} B".to_string()));
+        return native_result(Value::String("{bytes} B".into()));
     }
 
     let mut value = bytes;
@@ -164,29 +169,35 @@ native_op!(StringBytes, "string.bytes", [b], {
 
     // Use one decimal place for small values, no decimals for larger ones.
     if value < 10.0 {
-        return native_result(Value::String(format!("{value:.1} {}", UNITS[unit])));
+        return native_result(Value::String(format!("{value:.1} {}", UNITS[unit]).into()));
     } else {
-        return native_result(Value::String(format!("{value:.0} {}", UNITS[unit])));
+        return native_result(Value::String(format!("{value:.0} {}", UNITS[unit]).into()));
     }
 });
 
-native_op!(StringIterate, "string.iterate", [func, str], ctx, {
+native_op!(StringIterate, "string.iterate", [func, str: String] -> Null, ctx, {
     let str = str.expect_string()?;
     for char in str.chars() {
-        let char = Rc::new(Value::String(char.to_string()));
+        let char = Rc::new(Value::String(char.to_string().into()));
         ctx.apply(func, &char)?;
     }
     native_result(Value::Null)
 });
 
-native_op!(StringIterateBackward, ["string.iterate-backward", "string.<iterate"], [func, str], ctx, {
-    let str = str.expect_string()?;
-    for char in str.chars().rev() {
-        let char = Rc::new(Value::String(char.to_string()));
-        ctx.apply(func, &char)?;
+native_op!(
+    StringIterateBackward,
+    ["string.iterate-backward", "string.<iterate"],
+    [func, str: String] -> Null,
+    ctx,
+    {
+        let str = str.expect_string()?;
+        for char in str.chars().rev() {
+            let char = Rc::new(Value::String(char.to_string().into()));
+            ctx.apply(func, &char)?;
+        }
+        native_result(Value::Null)
     }
-    native_result(Value::Null)
-});
+);
 
 // Helper: convert a *character index* into a UTF-8 byte range (start..end)
 fn char_byte_range(s: &str, char_index: usize) -> Option<(usize, usize)> {
@@ -196,7 +207,7 @@ fn char_byte_range(s: &str, char_index: usize) -> Option<(usize, usize)> {
     Some((start, end))
 }
 
-native_op!(StringSet, "string.set", [inx, s, content], {
+native_op!(StringSet, "string.set", [inx: Number, s: String, content: String] -> String, {
     let inx = inx.expect_number()? as usize;
     let replacement = content.expect_string()?; // must be a string
 
@@ -209,8 +220,10 @@ native_op!(StringSet, "string.set", [inx, s, content], {
                 let (start, end) = char_byte_range(st, inx)
                     .ok_or(RuntimeError::IndexOutOfBounds { index: inx })?;
 
-                st.replace_range(start..end, replacement);
-                Ok(Rc::clone(&content))
+                let mut text = st.to_string();
+                text.replace_range(start..end, replacement);
+                *st = text.into();
+                Ok(Rc::clone(content))
             }
             _ => Err(RuntimeError::MissmatchedTypes {
                 got: s.get_type(),
@@ -223,7 +236,7 @@ native_op!(StringSet, "string.set", [inx, s, content], {
 native_op!(
     StringPush,
     ["string.push", "string.push>", "string.push-right"],
-    [s, content],
+    [s: String, content: String] -> String,
     {
         let suffix = content.expect_string()?; // must be a string
 
@@ -232,8 +245,8 @@ native_op!(
         unsafe {
             match &mut *s_ptr {
                 Value::String(st) => {
-                    st.push_str(suffix);
-                    Ok(Rc::clone(&content))
+                    *st = st.concat(suffix);
+                    Ok(Rc::clone(content))
                 }
                 _ => Err(RuntimeError::MissmatchedTypes {
                     got: s.get_type(),
@@ -247,7 +260,7 @@ native_op!(
 native_op!(
     StringPushLeft,
     ["string.<push", "string.push-left"],
-    [s, content],
+    [s: String, content: String] -> String,
     {
         let prefix = content.expect_string()?; // must be a string
 
@@ -256,14 +269,9 @@ native_op!(
         unsafe {
             match &mut *s_ptr {
                 Value::String(st) => {
-                    // Efficient prepend without repeated shifting:
-                    let suffix = std::mem::take(st);
-                    let mut new = String::with_capacity(prefix.len() + suffix.len());
-                    new.push_str(prefix);
-                    new.push_str(&suffix);
-                    *st = new;
-
-                    Ok(Rc::clone(&content))
+                    *st = prefix.concat(st);
+
+                    Ok(Rc::clone(content))
                 }
                 _ => Err(RuntimeError::MissmatchedTypes {
                     got: s.get_type(),
@@ -274,6 +282,50 @@ native_op!(
     }
 );
 
+// Value written by the spec, as it would be by "{val:spec}"
+// Usage: string.format ">10" size
+native_op!(StringFormat, "string.format", [spec: String, val] -> String, {
+    let spec = spec.expect_string()?;
+    let parsed = FormatSpec::parse(spec).ok_or_else(|| {
+        ShikError::default_error(format!("invalid format spec '{}'", spec))
+    })?;
+    native_result(Value::String(format::format(val, &parsed)?.into()))
+});
+
+// ============================================================================
+// Builders
+// ============================================================================
+
+// Empty string, which is appended in place, so a long text is built in linear time
+// Usage: let out string.builder
+native_op!(StringBuilder, "string.builder", [] -> Builder, {
+    native_result(Value::Builder(RefCell::new(String::new())))
+});
+
+// Put the value at the end of the builder, other values than strings are printed
+// Usage: string.append out "line\n"
+native_op!(StringAppend, "string.append", [builder: Builder, val] -> Builder, {
+    let buf = builder.expect_builder()?;
+    match val.as_ref() {
+        Value::String(s) => buf.borrow_mut().push_str(s),
+        Value::Builder(other) if !Rc::ptr_eq(builder, val) => {
+            buf.borrow_mut().push_str(&other.borrow())
+        }
+        other => {
+            let s = other.to_string();
+            buf.borrow_mut().push_str(&s);
+        }
+    }
+    Ok(Rc::clone(builder))
+});
+
+// Built string, the builder may be appended further
+// Usage: string.finish out
+native_op!(StringFinish, "string.finish", [builder: Builder] -> String, {
+    let buf = builder.expect_builder()?;
+    native_result(Value::String(buf.borrow().clone().into()))
+});
+
 pub fn bind_string_module(env: &EnvRef, inter: Rc<Interpretator>) {
     define_native!(MakeString, env, inter);
     define_native!(StringSplit, env, inter);
@@ -300,4 +352,8 @@ pub fn bind_string_module(env: &EnvRef, inter: Rc<Interpretator>) {
     define_native!(StringPushLeft, env, inter);
     define_native!(StringIterate, env, inter);
     define_native!(StringIterateBackward, env, inter);
+    define_native!(StringFormat, env, inter);
+    define_native!(StringBuilder, env, inter);
+    define_native!(StringAppend, env, inter);
+    define_native!(StringFinish, env, inter);
 }
@@ -12,15 +12,16 @@ use crate::{
 use std::rc::Rc;
 
 special_op!(Var, "var", args, ctx, {
-    let mut args_it = args.into_iter();
+    let mut args_it = args.iter();
     let name = args_it.next().ok_or(RuntimeError::InvalidApplication)?;
 
     let val = args_it.next().ok_or(RuntimeError::InvalidApplication)?;
 
-    match name {
+    match name.expr.as_ref() {
         Expression::Identifier(name) => {
-            let val = ctx.inter.eval_expr(val, &ctx.env)?;
+            let val = ctx.inter.eval_arg(val, ctx.env)?;
             ctx.env.define(name.to_string(), Rc::clone(&val));
+            ctx.inter.heap.track_env(ctx.env, &val);
             Ok(val)
         }
         _ => return Err(RuntimeError::InvalidApplication),
@@ -28,15 +29,16 @@ special_op!(Var, "var", args, ctx, {
 });
 
 special_op!(Set, "set", args, ctx, {
-    let mut args_it = args.into_iter();
+    let mut args_it = args.iter();
     let name = args_it.next().ok_or(RuntimeError::InvalidApplication)?;
 
     let val = args_it.next().ok_or(RuntimeError::InvalidApplication)?;
 
-    match name {
+    match name.expr.as_ref() {
         Expression::Identifier(name) => {
-            let val = ctx.inter.eval_expr(val, &ctx.env)?;
+            let val = ctx.inter.eval_arg(val, ctx.env)?;
             ctx.env.assign(name, Rc::clone(&val));
+            ctx.inter.heap.track_env(ctx.env, &val);
             Ok(val)
         }
         _ => return Err(RuntimeError::InvalidApplication),
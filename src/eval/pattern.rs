@@ -0,0 +1,134 @@
+//! Pattern matching and destructuring of runtime values
+
+use crate::{
+    eval::{
+        error::RuntimeError,
+        integer::Integer,
+        value::{EnvRef, Value, ValueRef},
+    },
+    parser::{ast::LiteralPattern, LetPattern, MatchPattern},
+};
+use num_traits::ToPrimitive;
+use std::rc::Rc;
+
+pub type Bindings = Vec<(String, ValueRef)>;
+
+/// Checks whether `value` matches `pattern`, collecting the variables the pattern binds.
+/// On failure `bindings` may contain partial results and should be discarded.
+pub fn match_pattern(pattern: &MatchPattern, value: &ValueRef, bindings: &mut Bindings) -> bool {
+    match pattern {
+        MatchPattern::Wildcard => true,
+        MatchPattern::Identifier(name) | MatchPattern::Typed { name, .. } => {
+            bindings.push((name.clone(), Rc::clone(value)));
+            true
+        }
+        MatchPattern::Literal(literal) => match_literal(literal, value),
+        MatchPattern::List { patterns, rest } => {
+            let Value::List(items) = value.as_ref() else {
+                return false;
+            };
+
+            let fits = match rest {
+                Some(_) => items.len() >= patterns.len(),
+                None => items.len() == patterns.len(),
+            };
+            if !fits {
+                return false;
+            }
+
+            for (pattern, item) in patterns.iter().zip(items.iter()) {
+                if !match_pattern(pattern, item, bindings) {
+                    return false;
+                }
+            }
+
+            if let Some(rest) = rest {
+                let tail = items.skip(patterns.len());
+                bindings.push((rest.clone(), Rc::new(Value::List(tail))));
+            }
+
+            true
+        }
+    }
+}
+
+pub fn match_literal(literal: &LiteralPattern, value: &ValueRef) -> bool {
+    match (literal, value.as_ref()) {
+        (LiteralPattern::Number(expected), _) => value.as_number() == Some(*expected),
+        (LiteralPattern::Integer(expected), Value::Integer(x)) => match x {
+            Integer::Small(x) => expected.to_i64() == Some(*x),
+            Integer::Big(x) => x == expected,
+        },
+        (LiteralPattern::Integer(expected), Value::Number(x)) => expected.to_f64() == Some(*x),
+        (LiteralPattern::String(expected), Value::String(s)) => expected == s.as_str(),
+        _ => false,
+    }
+}
+
+/// Binds `value` to the names of `pattern` in `env`.
+/// Nothing is bound if the value does not have the shape the pattern expects.
+pub fn destructure(pattern: &LetPattern, value: &ValueRef, env: &EnvRef) -> Result<(), RuntimeError> {
+    let mut bindings = Vec::new();
+    collect_let_bindings(pattern, value, &mut bindings)?;
+
+    for (name, val) in bindings {
+        env.define(name, val);
+    }
+    Ok(())
+}
+
+fn collect_let_bindings(
+    pattern: &LetPattern,
+    value: &ValueRef,
+    bindings: &mut Bindings,
+) -> Result<(), RuntimeError> {
+    match pattern {
+        LetPattern::Identifier(name) if name == "_" => Ok(()),
+        LetPattern::Identifier(name) | LetPattern::Typed { name, .. } => {
+            bindings.push((name.clone(), Rc::clone(value)));
+            Ok(())
+        }
+        LetPattern::List { patterns, rest } => {
+            let mismatch = |reason: String| RuntimeError::PatternMismatch {
+                pattern: pattern.to_string(),
+                reason,
+            };
+
+            let Value::List(items) = value.as_ref() else {
+                return Err(mismatch(format!(
+                    "expected List, got {:?}",
+                    value.get_type()
+                )));
+            };
+
+            match rest {
+                Some(_) if items.len() < patterns.len() => {
+                    return Err(mismatch(format!(
+                        "expected at least {} elements, got {}",
+                        patterns.len(),
+                        items.len()
+                    )));
+                }
+                None if items.len() != patterns.len() => {
+                    return Err(mismatch(format!(
+                        "expected {} elements, got {}",
+                        patterns.len(),
+                        items.len()
+                    )));
+                }
+                _ => {}
+            }
+
+            for (pattern, item) in patterns.iter().zip(items.iter()) {
+                collect_let_bindings(pattern, item, bindings)?;
+            }
+
+            if let Some(rest) = rest {
+                let tail = items.skip(patterns.len());
+                bindings.push((rest.clone(), Rc::new(Value::List(tail))));
+            }
+
+            Ok(())
+        }
+    }
+}
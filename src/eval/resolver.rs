@@ -0,0 +1,432 @@
+//! Static name resolution, done once the program is parsed and before it runs
+//!
+//! Local variables of functions, match arms and lazy blocks get slots in their scope,
+//! the compiler addresses them by depth and slot instead of looking them up by name.
+//! Globals stay in named environments, they can be added by imports and macros.
+//!
+//! `check` reports names, which are not defined anywhere, and definitions shadowing builtins.
+
+use crate::{
+    eval::{
+        error::RuntimeError,
+        value::{Env, Value},
+    },
+    parser::{Expression, LetPattern, MatchPattern, Program, Span},
+};
+use std::{collections::HashSet, fmt, iter, mem, path::Path, rc::Rc};
+
+/// Local variables of a function clause, a match arm or a lazy block, in slot order
+#[derive(Debug)]
+pub struct Scope {
+    pub names: Vec<String>,
+    pub parent: Option<Rc<Scope>>,
+}
+
+impl Scope {
+    pub fn slot(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|it| it == name)
+    }
+
+    /// Depth of the scope defining the variable, counting from this one, and the slot in it
+    pub fn resolve(&self, name: &str) -> Option<(usize, usize)> {
+        iter::successors(Some(self), |scope| scope.parent.as_deref())
+            .enumerate()
+            .find_map(|(depth, scope)| Some((depth, scope.slot(name)?)))
+    }
+
+    pub fn lambda(
+        parameters: &[MatchPattern],
+        rest: Option<&String>,
+        body: &Expression,
+        parent: Option<Rc<Scope>>,
+    ) -> Rc<Self> {
+        let mut names = Names::default();
+        parameters.iter().for_each(|p| names.pattern(p));
+        names.extend(rest.cloned());
+        names.body(body);
+        names.scope(parent)
+    }
+
+    pub fn arm(pattern: &MatchPattern, body: &Expression, parent: Option<Rc<Scope>>) -> Rc<Self> {
+        let mut names = Names::default();
+        names.pattern(pattern);
+        names.body(body);
+        names.scope(parent)
+    }
+
+    pub fn block(exprs: &[Expression], parent: Option<Rc<Scope>>) -> Rc<Self> {
+        let mut names = Names::default();
+        exprs.iter().for_each(|expr| names.body(expr));
+        names.scope(parent)
+    }
+}
+
+/// Names a scope defines: bindings of its patterns, then names defined by `let` and `var`
+/// in its body, nested scopes are skipped
+#[derive(Default)]
+struct Names(Vec<String>);
+
+impl Names {
+    fn scope(self, parent: Option<Rc<Scope>>) -> Rc<Scope> {
+        Rc::new(Scope {
+            names: self.0,
+            parent,
+        })
+    }
+
+    fn add(&mut self, name: &str) {
+        if name != "_" && !self.0.iter().any(|it| it == name) {
+            self.0.push(name.to_string());
+        }
+    }
+
+    fn extend(&mut self, names: impl IntoIterator<Item = String>) {
+        names.into_iter().for_each(|name| self.add(&name));
+    }
+
+    fn pattern(&mut self, pattern: &MatchPattern) {
+        match pattern {
+            MatchPattern::Identifier(name) | MatchPattern::Typed { name, .. } => self.add(name),
+            MatchPattern::List { patterns, rest } => {
+                patterns.iter().for_each(|p| self.pattern(p));
+                self.extend(rest.clone());
+            }
+            MatchPattern::Literal(_) | MatchPattern::Wildcard => {}
+        }
+    }
+
+    fn let_pattern(&mut self, pattern: &LetPattern) {
+        match pattern {
+            LetPattern::Identifier(name) | LetPattern::Typed { name, .. } => self.add(name),
+            LetPattern::List { patterns, rest } => {
+                patterns.iter().for_each(|p| self.let_pattern(p));
+                self.extend(rest.clone());
+            }
+        }
+    }
+
+    fn body(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Let { pattern, value } => {
+                self.let_pattern(pattern);
+                self.body(value);
+            }
+            Expression::Application {
+                function, argument, ..
+            } => {
+                if let Some(name) = var_target(function, argument) {
+                    self.add(name);
+                }
+                self.body(function);
+                self.body(argument);
+            }
+            Expression::Match { subject, .. } => self.body(subject),
+            Expression::Lambda { .. }
+            | Expression::MultiLambda { .. }
+            | Expression::Macro(_)
+            | Expression::Lazy(_) => {}
+            _ => children(expr, |child| self.body(child)),
+        }
+    }
+}
+
+/// Name defined by `var NAME`
+fn var_target<'a>(function: &Expression, argument: &'a Expression) -> Option<&'a str> {
+    match (function, argument) {
+        (Expression::Identifier(var), Expression::Identifier(name)) if var == "var" => Some(name),
+        _ => None,
+    }
+}
+
+/// Calls `f` with the direct subexpressions
+fn children(expr: &Expression, mut f: impl FnMut(&Expression)) {
+    match expr {
+        Expression::Number(_)
+        | Expression::Integer(_)
+        | Expression::String(_)
+        | Expression::Identifier(_) => {}
+        Expression::StringInterpolation(info) => {
+            info.entries.iter().for_each(|entry| f(&entry.expression))
+        }
+        Expression::Pipe { left, right, .. }
+        | Expression::Flow { left, right }
+        | Expression::Chain { left, right, .. } => {
+            f(left);
+            f(right);
+        }
+        Expression::Application {
+            function, argument, ..
+        } => {
+            f(function);
+            f(argument);
+        }
+        Expression::List(items) | Expression::Block(items) | Expression::Lazy(items) => {
+            items.iter().for_each(f)
+        }
+        Expression::Object(items) => items.iter().for_each(|item| {
+            f(&item.key);
+            f(&item.value);
+        }),
+        Expression::Let { value, .. } => f(value),
+        Expression::Lambda { body, .. } => f(body),
+        Expression::MultiLambda { clauses } => clauses.iter().for_each(|clause| f(&clause.body)),
+        Expression::Match { subject, arms } => {
+            f(subject);
+            arms.iter().for_each(|arm| f(&arm.body));
+        }
+        Expression::Macro(expr) | Expression::Unquote(expr) | Expression::Parenthesized(expr) => {
+            f(expr)
+        }
+    }
+}
+
+/// Calls `f` with the expression and all expressions inside it
+pub fn walk(expr: &Expression, f: &mut impl FnMut(&Expression)) {
+    f(expr);
+    children(expr, |child| walk(child, f));
+}
+
+/// Name of the function at the head of an application
+fn head(expr: &Expression) -> Option<&str> {
+    match expr {
+        Expression::Identifier(name) => Some(name),
+        Expression::Application { function, .. } => head(function),
+        Expression::Chain { left, .. } => head(left),
+        Expression::Pipe { right, .. } => head(right),
+        Expression::Parenthesized(expr) => head(expr),
+        _ => None,
+    }
+}
+
+/// Problem found before the program runs, which does not stop it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub message: String,
+    pub span: Span,
+    // file the warning was found in, none for the REPL and checked programs
+    pub file: Option<String>,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Warning: {} at line {}, column {}",
+            self.message, self.span.line, self.span.column
+        )?;
+        match &self.file {
+            Some(file) => write!(f, " in {}", file),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Checks names of the program, which is going to run in `env`:
+/// a name, which is not defined anywhere, is an error, and a definition
+/// shadowing a builtin gives a warning.
+///
+/// Names used inside `quote` and in arguments of macros are code, they are not checked.
+/// A program defining macros may define any name with them, so undefined names are not
+/// reported for it.
+pub fn check(program: &Program, env: &Env, builtins: &Env) -> Result<Vec<Warning>, RuntimeError> {
+    let mut checker = Checker {
+        env,
+        builtins,
+        globals: HashSet::new(),
+        macros: HashSet::new(),
+        prefixes: Vec::new(),
+        any_prefix: false,
+        lenient: false,
+        span: Span::default(),
+        warnings: Vec::new(),
+        undefined: None,
+    };
+
+    for stmt in program.statements.iter() {
+        checker.span = Span::new(stmt.line, stmt.column);
+        checker.globals(&stmt.expression);
+        walk(&stmt.expression, &mut |expr| checker.scan(expr));
+    }
+
+    for stmt in program.statements.iter() {
+        checker.span = Span::new(stmt.line, stmt.column);
+        checker.expr(&stmt.expression, None);
+    }
+
+    match checker.undefined {
+        Some(error) => Err(error),
+        None => Ok(checker.warnings),
+    }
+}
+
+struct Checker<'a> {
+    env: &'a Env,
+    builtins: &'a Env,
+    // names defined by statements of the program
+    globals: HashSet<String>,
+    // globals the program defines as macros
+    macros: HashSet<String>,
+    // names of imported files, which are prefixes of their definitions
+    prefixes: Vec<String>,
+    // a file imported with a computed name may define any name with a prefix
+    any_prefix: bool,
+    // the program uses macros, which may define any name
+    lenient: bool,
+    // place of the innermost application, problems are reported there
+    span: Span,
+    warnings: Vec<Warning>,
+    undefined: Option<RuntimeError>,
+}
+
+impl Checker<'_> {
+    /// Names defined at the top level of a statement
+    fn globals(&mut self, expr: &Expression) {
+        let mut names = Names::default();
+        names.body(expr);
+        self.shadowing(&names.0);
+        self.globals.extend(names.0);
+
+        if let Expression::Let {
+            pattern: LetPattern::Identifier(name),
+            value,
+        } = expr
+        {
+            if matches!(unparenthesized(value), Expression::Macro(_)) {
+                self.macros.insert(name.clone());
+            }
+        }
+    }
+
+    /// Finds imports and macros anywhere in the program
+    fn scan(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Macro(_) => self.lenient = true,
+            Expression::Application {
+                function, argument, ..
+            } if head(function) == Some("import") => match unparenthesized(argument) {
+                Expression::String(name) => {
+                    let stem = Path::new(name)
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string());
+                    self.prefixes.push(stem.unwrap_or_else(|| name.clone()));
+                }
+                _ => self.any_prefix = true,
+            },
+            Expression::Application { function, .. } if self.is_live_macro(head(function)) => {
+                self.lenient = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn is_live_macro(&self, name: Option<&str>) -> bool {
+        let val = name.and_then(|name| self.env.lookup(name));
+        matches!(val.as_deref(), Some(Value::SpecialForm(form)) if form.logic.is_macro())
+    }
+
+    /// Whether arguments of the function are code, which is not evaluated
+    fn takes_code(&self, function: &Expression) -> bool {
+        match head(function) {
+            Some("quote") => true,
+            Some(name) => self.macros.contains(name) || self.is_live_macro(Some(name)),
+            None => false,
+        }
+    }
+
+    fn shadowing(&mut self, names: &[String]) {
+        for name in names {
+            if self.builtins.vars.borrow().contains_key(name) {
+                self.warnings.push(Warning {
+                    message: format!("'{}' shadows a builtin", name),
+                    span: self.span,
+                    file: None,
+                });
+            }
+        }
+    }
+
+    fn is_defined(&self, name: &str, scope: Option<&Rc<Scope>>) -> bool {
+        let imported = name.split_once('.').is_some_and(|(prefix, _)| {
+            self.any_prefix || self.prefixes.iter().any(|it| it == prefix)
+        });
+
+        scope.is_some_and(|scope| scope.resolve(name).is_some())
+            || self.globals.contains(name)
+            || self.env.lookup(name).is_some()
+            || imported
+    }
+
+    fn enter(&mut self, scope: Rc<Scope>, body: &Expression) {
+        self.shadowing(&scope.names);
+        self.expr(body, Some(&scope));
+    }
+
+    fn expr(&mut self, expr: &Expression, scope: Option<&Rc<Scope>>) {
+        match expr {
+            Expression::Identifier(name) => {
+                if !self.lenient && self.undefined.is_none() && !self.is_defined(name, scope) {
+                    self.undefined =
+                        Some(RuntimeError::UndefinedVariable(name.clone()).at(self.span));
+                }
+            }
+            Expression::Application {
+                function,
+                argument,
+                span,
+            }
+            | Expression::Chain {
+                left: function,
+                right: argument,
+                span,
+            }
+            | Expression::Pipe {
+                left: argument,
+                right: function,
+                span,
+            } => {
+                let outer = mem::replace(&mut self.span, *span);
+                self.expr(function, scope);
+                if var_target(function, argument).is_none() && !self.takes_code(function) {
+                    self.expr(argument, scope);
+                }
+                self.span = outer;
+            }
+            Expression::Lambda {
+                parameters,
+                rest,
+                body,
+            } => {
+                let inner = Scope::lambda(parameters, rest.as_ref(), body, scope.cloned());
+                self.enter(inner, body);
+            }
+            Expression::MultiLambda { clauses } => {
+                for clause in clauses.iter() {
+                    let parameters = &clause.parameters;
+                    let inner = Scope::lambda(parameters, None, &clause.body, scope.cloned());
+                    self.enter(inner, &clause.body);
+                }
+            }
+            Expression::Match { subject, arms } => {
+                self.expr(subject, scope);
+                for arm in arms.iter() {
+                    self.enter(Scope::arm(&arm.pattern, &arm.body, scope.cloned()), &arm.body);
+                }
+            }
+            Expression::Lazy(exprs) => {
+                let inner = Scope::block(exprs, scope.cloned());
+                self.shadowing(&inner.names);
+                exprs.iter().for_each(|expr| self.expr(expr, Some(&inner)));
+            }
+            // outside of quote it fails as it runs
+            Expression::Unquote(_) => {}
+            _ => children(expr, |child| self.expr(child, scope)),
+        }
+    }
+}
+
+fn unparenthesized(expr: &Expression) -> &Expression {
+    match expr {
+        Expression::Parenthesized(expr) => unparenthesized(expr),
+        _ => expr,
+    }
+}
@@ -0,0 +1,176 @@
+//! Lazy streams: values are pulled one by one from their source when they are needed,
+//! so large files, long command outputs and infinite ranges are never held in memory
+
+use crate::eval::{
+    error::{RuntimeError, ShikError},
+    evaluator::Interpretator,
+    integer::Integer,
+    value::{Value, ValueRef},
+};
+use std::cell::RefCell;
+use std::io::BufRead;
+use std::rc::Rc;
+
+pub type Pulled = Result<Option<ValueRef>, RuntimeError>;
+
+/// Where values of a stream come from
+pub trait Source {
+    /// Next value, `None` once the stream is over
+    fn next(&mut self, inter: &Interpretator) -> Pulled;
+
+    /// Values the source holds, the collector follows them to find cycles
+    fn values(&self) -> Vec<ValueRef> {
+        Vec::new()
+    }
+}
+
+/// Stream value, it is read once: everyone holding it pulls from the same source
+pub struct Stream(RefCell<Box<dyn Source>>);
+
+impl Stream {
+    pub fn new(source: impl Source + 'static) -> Self {
+        Stream(RefCell::new(Box::new(source)))
+    }
+
+    pub fn next(&self, inter: &Interpretator) -> Pulled {
+        // a function given to the stream may try to read it again
+        let mut source = self.0.try_borrow_mut().map_err(|_| {
+            ShikError::default_error("stream is read while it gives a value".to_string())
+        })?;
+        source.next(inter)
+    }
+
+    /// Values held by the source, none while the stream is being read
+    pub fn values(&self) -> Vec<ValueRef> {
+        self.0
+            .try_borrow()
+            .map(|source| source.values())
+            .unwrap_or_default()
+    }
+}
+
+impl std::fmt::Debug for Stream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Stream")
+    }
+}
+
+/// Next value of the stream held by `from`
+fn pull(from: &ValueRef, inter: &Interpretator) -> Pulled {
+    from.expect_stream()?.next(inter)
+}
+
+// ============================================================================
+// Sources
+// ============================================================================
+
+/// Integers from `next` by `step`, up to `end` if there is one
+pub struct Range {
+    pub next: Integer,
+    pub end: Option<Integer>,
+    pub step: Integer,
+}
+
+impl Source for Range {
+    fn next(&mut self, _: &Interpretator) -> Pulled {
+        let ascending = self.step > Integer::Small(0);
+        match &self.end {
+            Some(end) if ascending && self.next >= *end => return Ok(None),
+            Some(end) if !ascending && self.next <= *end => return Ok(None),
+            _ => {}
+        }
+
+        let next = self.next.add(&self.step);
+        let val = std::mem::replace(&mut self.next, next);
+        Ok(Some(Rc::new(Value::Integer(val))))
+    }
+}
+
+/// Lines of the reader, without line endings
+pub struct Lines<R: BufRead>(pub R);
+
+impl<R: BufRead> Source for Lines<R> {
+    fn next(&mut self, _: &Interpretator) -> Pulled {
+        let mut buf = Vec::new();
+        let read = self
+            .0
+            .read_until(b'\n', &mut buf)
+            .map_err(|e| ShikError::default_error(format!("cannot read line - {}", e)))?;
+        if read == 0 {
+            return Ok(None);
+        }
+
+        if buf.ends_with(b"\n") {
+            buf.pop();
+            if buf.ends_with(b"\r") {
+                buf.pop();
+            }
+        }
+        let line = String::from_utf8_lossy(&buf).into_owned();
+        Ok(Some(Rc::new(Value::String(line.into()))))
+    }
+}
+
+// ============================================================================
+// Transformations, each reads the stream it was made of
+// ============================================================================
+
+/// Values of the stream passed through the function
+pub struct Map {
+    pub func: ValueRef,
+    pub from: ValueRef,
+}
+
+impl Source for Map {
+    fn next(&mut self, inter: &Interpretator) -> Pulled {
+        match pull(&self.from, inter)? {
+            Some(val) => Ok(Some(inter.apply_fn(&self.func, &val)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn values(&self) -> Vec<ValueRef> {
+        vec![Rc::clone(&self.func), Rc::clone(&self.from)]
+    }
+}
+
+/// Values of the stream, for which the predicate is true
+pub struct Filter {
+    pub func: ValueRef,
+    pub from: ValueRef,
+}
+
+impl Source for Filter {
+    fn next(&mut self, inter: &Interpretator) -> Pulled {
+        while let Some(val) = pull(&self.from, inter)? {
+            if inter.apply_fn(&self.func, &val)?.expect_bool()? {
+                return Ok(Some(val));
+            }
+        }
+        Ok(None)
+    }
+
+    fn values(&self) -> Vec<ValueRef> {
+        vec![Rc::clone(&self.func), Rc::clone(&self.from)]
+    }
+}
+
+/// First `left` values of the stream, the rest is never read
+pub struct Take {
+    pub left: usize,
+    pub from: ValueRef,
+}
+
+impl Source for Take {
+    fn next(&mut self, inter: &Interpretator) -> Pulled {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        pull(&self.from, inter)
+    }
+
+    fn values(&self) -> Vec<ValueRef> {
+        vec![Rc::clone(&self.from)]
+    }
+}
@@ -0,0 +1,209 @@
+//! Text of string values. Strings are shared, and joining long strings makes a node
+//! referencing both parts instead of copying them, so a text built piece by piece is
+//! copied once, when it is read.
+
+use std::cell::{OnceCell, RefCell};
+use std::cmp::Ordering;
+use std::ffi::OsStr;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::path::Path;
+use std::rc::Rc;
+
+// joined strings shorter than this are copied, a node is not worth it
+pub const MIN_NODE: usize = 256;
+
+/// Immutable string, cloning it is cheap
+#[derive(Clone)]
+pub struct Str(Rc<Node>);
+
+struct Node {
+    len: usize,
+    // the whole text, made from the parts when it is read for the first time
+    flat: OnceCell<Box<str>>,
+    // parts of a joined string, until it is flattened
+    parts: RefCell<Option<(Str, Str)>>,
+}
+
+impl Str {
+    /// Length in bytes, the text is not flattened for it
+    pub fn len(&self) -> usize {
+        self.0.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.len == 0
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.flat.get_or_init(|| self.flatten())
+    }
+
+    /// This string followed by the other one
+    pub fn concat(&self, other: &Str) -> Str {
+        let len = self.len() + other.len();
+        if other.is_empty() {
+            return self.clone();
+        }
+        if self.is_empty() {
+            return other.clone();
+        }
+        if len < MIN_NODE {
+            let mut text = String::with_capacity(len);
+            text.push_str(self);
+            text.push_str(other);
+            return Str::from(text);
+        }
+
+        Str(Rc::new(Node {
+            len,
+            flat: OnceCell::new(),
+            parts: RefCell::new(Some((self.clone(), other.clone()))),
+        }))
+    }
+
+    /// Text of the parts, which are dropped afterwards
+    fn flatten(&self) -> Box<str> {
+        let mut text = String::with_capacity(self.len());
+        let mut pending: Vec<Str> = match self.0.parts.borrow_mut().take() {
+            Some((left, right)) => vec![right, left],
+            None => Vec::new(),
+        };
+
+        // joined strings may be nested deeply, so they are walked without recursion
+        while let Some(part) = pending.pop() {
+            if let Some(flat) = part.0.flat.get() {
+                text.push_str(flat);
+                continue;
+            }
+            if let Some((left, right)) = part.0.parts.borrow().as_ref() {
+                pending.push(right.clone());
+                pending.push(left.clone());
+            }
+        }
+        text.into_boxed_str()
+    }
+}
+
+impl Drop for Node {
+    // a long chain of joined strings would overflow the stack if dropped recursively
+    fn drop(&mut self) {
+        let Some((left, right)) = self.parts.get_mut().take() else {
+            return;
+        };
+
+        let mut pending = vec![left, right];
+        while let Some(part) = pending.pop() {
+            if let Ok(mut node) = Rc::try_unwrap(part.0) {
+                if let Some((left, right)) = node.parts.get_mut().take() {
+                    pending.push(left);
+                    pending.push(right);
+                }
+            }
+        }
+    }
+}
+
+impl Default for Str {
+    fn default() -> Self {
+        Str::from(String::new())
+    }
+}
+
+impl From<String> for Str {
+    fn from(text: String) -> Self {
+        Str(Rc::new(Node {
+            len: text.len(),
+            flat: OnceCell::from(text.into_boxed_str()),
+            parts: RefCell::new(None),
+        }))
+    }
+}
+
+impl From<&str> for Str {
+    fn from(text: &str) -> Self {
+        Str::from(text.to_string())
+    }
+}
+
+impl Deref for Str {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Str {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<[u8]> for Str {
+    fn as_ref(&self) -> &[u8] {
+        self.as_str().as_bytes()
+    }
+}
+
+impl AsRef<Path> for Str {
+    fn as_ref(&self) -> &Path {
+        Path::new(self.as_str())
+    }
+}
+
+impl AsRef<OsStr> for Str {
+    fn as_ref(&self) -> &OsStr {
+        OsStr::new(self.as_str())
+    }
+}
+
+impl PartialEq for Str {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0) || self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Str {}
+
+impl PartialEq<str> for Str {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Str {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialOrd for Str {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Str {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for Str {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl std::fmt::Debug for Str {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl std::fmt::Display for Str {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
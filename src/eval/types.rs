@@ -0,0 +1,443 @@
+//! Static types for `shik check`: annotations, signatures of native functions
+//! and inference of the types of expressions, so mismatches are found before the script runs
+
+use crate::{
+    eval::{
+        error::RuntimeError,
+        resolver::walk,
+        value::{Env, Value, ValueType},
+    },
+    parser::{ast::LiteralPattern, Expression, LetPattern, MatchPattern, Program, Span},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    rc::Rc,
+};
+
+/// Type of a value known before running, `Any` when it cannot be inferred
+#[derive(Debug, Clone)]
+pub enum Type {
+    Any,
+    // float or integer
+    Number,
+    Integer,
+    String,
+    Bool,
+    List,
+    Object,
+    // function with unknown arguments
+    Lambda,
+    Null,
+    Lazy,
+    Result,
+    Ref,
+    Builder,
+    Stream,
+    Code,
+    // function with known types of the arguments and the result
+    Fn(Rc<Signature>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub params: Vec<Type>,
+    pub ret: Type,
+}
+
+impl Type {
+    /// Type written in an annotation, as in `x:Number`
+    pub fn from_name(name: &str) -> Option<Type> {
+        Some(match name {
+            "Any" => Type::Any,
+            "Number" => Type::Number,
+            "Integer" => Type::Integer,
+            "String" => Type::String,
+            "Bool" => Type::Bool,
+            "List" => Type::List,
+            "Object" => Type::Object,
+            "Lambda" => Type::Lambda,
+            "Null" => Type::Null,
+            "Lazy" => Type::Lazy,
+            "Result" => Type::Result,
+            "Ref" => Type::Ref,
+            "Builder" => Type::Builder,
+            "Stream" => Type::Stream,
+            "Code" => Type::Code,
+            _ => return None,
+        })
+    }
+
+    /// Type of the value, native functions have their signatures
+    pub fn of(value: &Value) -> Type {
+        match value {
+            Value::NativeLambda(closure) => match closure.logic.signature() {
+                Some(signature) => Type::Fn(Rc::new(Signature {
+                    params: signature.params[closure.binded.len()..].to_vec(),
+                    ret: signature.ret,
+                })),
+                None => Type::Lambda,
+            },
+            // special forms decide themselves how their arguments are used
+            Value::SpecialForm(_) => Type::Any,
+            Value::Number(_) => Type::Number,
+            Value::Integer(_) => Type::Integer,
+            Value::String(_) => Type::String,
+            Value::Bool(_) => Type::Bool,
+            Value::List(_) => Type::List,
+            Value::Object(_) => Type::Object,
+            Value::Lambda(_) => Type::Lambda,
+            Value::Lazy(_) => Type::Lazy,
+            Value::Result(_) => Type::Result,
+            Value::Ref(_) => Type::Ref,
+            Value::Builder(_) => Type::Builder,
+            Value::Stream(_) => Type::Stream,
+            Value::Code(_) => Type::Code,
+            Value::Null => Type::Null,
+        }
+    }
+
+    /// Type of runtime values, `Any` has none
+    fn value_type(&self) -> Option<ValueType> {
+        Some(match self {
+            Type::Any => return None,
+            Type::Number => ValueType::Number,
+            Type::Integer => ValueType::Integer,
+            Type::String => ValueType::String,
+            Type::Bool => ValueType::Bool,
+            Type::List => ValueType::List,
+            Type::Object => ValueType::Object,
+            Type::Lambda | Type::Fn(_) => ValueType::Lambda,
+            Type::Null => ValueType::Null,
+            Type::Lazy => ValueType::Lazy,
+            Type::Result => ValueType::Result,
+            Type::Ref => ValueType::Ref,
+            Type::Builder => ValueType::Builder,
+            Type::Stream => ValueType::Stream,
+            Type::Code => ValueType::Code,
+        })
+    }
+
+    fn is_function(&self) -> bool {
+        matches!(self, Type::Lambda | Type::Fn(_))
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self, Type::Number | Type::Integer)
+    }
+
+    /// Whether a value of type `got` may be given where this type is expected
+    pub fn accepts(&self, got: &Type) -> bool {
+        match (self, got) {
+            (Type::Any, _) | (_, Type::Any) => true,
+            _ if self.is_function() && got.is_function() => true,
+            (Type::Number, Type::Integer) => true,
+            _ => mem::discriminant(self) == mem::discriminant(got),
+        }
+    }
+
+    /// Type of a value, which is one of the two
+    fn join(self, other: Type) -> Type {
+        match (&self, &other) {
+            _ if self.is_function() && other.is_function() => Type::Lambda,
+            _ if mem::discriminant(&self) == mem::discriminant(&other) => self,
+            _ if self.is_number() && other.is_number() => Type::Number,
+            _ => Type::Any,
+        }
+    }
+}
+
+/// Infers types in the program, which is going to run in `env`, and reports values
+/// of wrong types given to functions with known signatures and to annotated names.
+///
+/// Names changed by `set` may get a value of any type, their types are not inferred.
+pub fn check(program: &Program, env: &Env) -> Vec<RuntimeError> {
+    let mut checker = Checker {
+        env,
+        scopes: vec![HashMap::new()],
+        mutable: HashSet::new(),
+        span: Span::default(),
+        errors: Vec::new(),
+    };
+
+    for stmt in program.statements.iter() {
+        walk(&stmt.expression, &mut |expr| {
+            if let Expression::Application {
+                function,
+                argument,
+                ..
+            } = expr
+            {
+                if let (Expression::Identifier(set), Expression::Identifier(name)) =
+                    (function.as_ref(), argument.as_ref())
+                {
+                    if set == "set" {
+                        checker.mutable.insert(name.clone());
+                    }
+                }
+            }
+        });
+    }
+
+    for stmt in program.statements.iter() {
+        checker.span = Span::new(stmt.line, stmt.column);
+        checker.infer(&stmt.expression);
+    }
+    checker.errors
+}
+
+struct Checker<'a> {
+    env: &'a Env,
+    // types of the names defined by the program, innermost scope last
+    scopes: Vec<HashMap<String, Type>>,
+    // names changed by `set`
+    mutable: HashSet<String>,
+    // place of the innermost application, mismatches are reported there
+    span: Span,
+    errors: Vec<RuntimeError>,
+}
+
+impl Checker<'_> {
+    fn lookup(&self, name: &str) -> Type {
+        let defined = self.scopes.iter().rev().find_map(|scope| scope.get(name));
+        match defined {
+            Some(ty) => ty.clone(),
+            None => self
+                .env
+                .lookup(name)
+                .map(|val| Type::of(&val))
+                .unwrap_or(Type::Any),
+        }
+    }
+
+    fn define(&mut self, name: &str, ty: Type) {
+        let ty = match self.mutable.contains(name) {
+            true => Type::Any,
+            false => ty,
+        };
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), ty);
+        }
+    }
+
+    fn expect(&mut self, expected: &Type, got: &Type) {
+        if expected.accepts(got) {
+            return;
+        }
+        if let (Some(expected), Some(got)) = (expected.value_type(), got.value_type()) {
+            let error = RuntimeError::MissmatchedTypes { got, expected };
+            self.errors.push(error.at(self.span));
+        }
+    }
+
+    fn annotation(&mut self, name: &str) -> Type {
+        Type::from_name(name).unwrap_or_else(|| {
+            let error = RuntimeError::UnknownType(name.to_string());
+            self.errors.push(error.at(self.span));
+            Type::Any
+        })
+    }
+
+    fn infer(&mut self, expr: &Expression) -> Type {
+        match expr {
+            Expression::Number(_) => Type::Number,
+            Expression::Integer(_) => Type::Integer,
+            Expression::String(_) => Type::String,
+            Expression::StringInterpolation(info) => {
+                for entry in info.entries.iter() {
+                    self.infer(&entry.expression);
+                }
+                Type::String
+            }
+            // a function without arguments is called once it is referenced
+            Expression::Identifier(name) => match self.lookup(name) {
+                Type::Fn(signature) if signature.params.is_empty() => signature.ret.clone(),
+                ty => ty,
+            },
+            Expression::Application {
+                function,
+                argument,
+                span,
+            } => self.apply(function, argument, *span),
+            Expression::Pipe { left, right, span } => self.apply(right, left, *span),
+            Expression::Chain { left, right, span } => self.apply(left, right, *span),
+            Expression::Flow { left, right } => {
+                self.infer(left);
+                self.infer(right);
+                Type::Lambda
+            }
+            Expression::List(items) => {
+                for it in items.iter() {
+                    self.infer(it);
+                }
+                Type::List
+            }
+            Expression::Object(items) => {
+                for it in items.iter() {
+                    self.infer(&it.key);
+                    self.infer(&it.value);
+                }
+                Type::Object
+            }
+            Expression::Let { pattern, value } => {
+                let ty = self.infer(value);
+                self.bind_let(pattern, &ty);
+                ty
+            }
+            Expression::Lambda {
+                parameters,
+                rest,
+                body,
+            } => {
+                self.scopes.push(HashMap::new());
+                let params: Vec<Type> = parameters.iter().map(|p| self.bind(p)).collect();
+                if let Some(rest) = rest {
+                    self.define(rest, Type::List);
+                }
+                let ret = self.infer(body);
+                self.scopes.pop();
+
+                match rest {
+                    Some(_) => Type::Lambda,
+                    None => Type::Fn(Rc::new(Signature { params, ret })),
+                }
+            }
+            Expression::MultiLambda { clauses } => {
+                for clause in clauses.iter() {
+                    self.scopes.push(HashMap::new());
+                    clause.parameters.iter().for_each(|p| {
+                        self.bind(p);
+                    });
+                    self.infer(&clause.body);
+                    self.scopes.pop();
+                }
+                Type::Lambda
+            }
+            Expression::Match { subject, arms } => {
+                self.infer(subject);
+
+                let mut res: Option<Type> = None;
+                for arm in arms.iter() {
+                    self.scopes.push(HashMap::new());
+                    self.bind(&arm.pattern);
+                    let ty = self.infer(&arm.body);
+                    self.scopes.pop();
+
+                    res = Some(match res {
+                        Some(res) => res.join(ty),
+                        None => ty,
+                    });
+                }
+                res.unwrap_or(Type::Any)
+            }
+            // macros are special forms, the code they make is not known
+            Expression::Macro(lambda) => {
+                self.infer(lambda);
+                Type::Any
+            }
+            Expression::Unquote(_) => Type::Any,
+            Expression::Parenthesized(expr) => self.infer(expr),
+            Expression::Block(exprs) => {
+                let mut last = Type::Null;
+                for it in exprs.iter() {
+                    last = self.infer(it);
+                }
+                last
+            }
+            Expression::Lazy(exprs) => {
+                self.scopes.push(HashMap::new());
+                for it in exprs.iter() {
+                    self.infer(it);
+                }
+                self.scopes.pop();
+                Type::Lazy
+            }
+        }
+    }
+
+    fn apply(&mut self, function: &Expression, argument: &Expression, span: Span) -> Type {
+        let outer = mem::replace(&mut self.span, span);
+        let res = self.application(function, argument);
+        self.span = outer;
+        res
+    }
+
+    fn application(&mut self, function: &Expression, argument: &Expression) -> Type {
+        match (function, argument) {
+            (Expression::Identifier(quote), _) if quote == "quote" => return Type::Code,
+            (Expression::Identifier(var), Expression::Identifier(name)) if var == "var" => {
+                self.define(name, Type::Any);
+                return Type::Any;
+            }
+            _ => {}
+        }
+
+        let f = self.infer(function);
+        let arg = self.infer(argument);
+
+        match f {
+            Type::Fn(signature) if !signature.params.is_empty() => {
+                self.expect(&signature.params[0], &arg);
+
+                match signature.params.len() {
+                    1 => signature.ret.clone(),
+                    _ => Type::Fn(Rc::new(Signature {
+                        params: signature.params[1..].to_vec(),
+                        ret: signature.ret.clone(),
+                    })),
+                }
+            }
+            _ => Type::Any,
+        }
+    }
+
+    /// Defines the names of the parameter pattern, returns the type of values it takes
+    fn bind(&mut self, pattern: &MatchPattern) -> Type {
+        match pattern {
+            MatchPattern::Identifier(name) => {
+                self.define(name, Type::Any);
+                Type::Any
+            }
+            MatchPattern::Typed { name, ty } => {
+                let ty = self.annotation(ty);
+                self.define(name, ty.clone());
+                ty
+            }
+            // an integer literal matches a float of the same value too
+            MatchPattern::Literal(LiteralPattern::Number(_) | LiteralPattern::Integer(_)) => {
+                Type::Number
+            }
+            MatchPattern::Literal(LiteralPattern::String(_)) => Type::String,
+            MatchPattern::List { patterns, rest } => {
+                for p in patterns.iter() {
+                    self.bind(p);
+                }
+                if let Some(rest) = rest {
+                    self.define(rest, Type::List);
+                }
+                Type::List
+            }
+            MatchPattern::Wildcard => Type::Any,
+        }
+    }
+
+    fn bind_let(&mut self, pattern: &LetPattern, ty: &Type) {
+        match pattern {
+            LetPattern::Identifier(name) => self.define(name, ty.clone()),
+            LetPattern::Typed { name, ty: annotation } => {
+                let expected = self.annotation(annotation);
+                self.expect(&expected, ty);
+                self.define(name, expected);
+            }
+            LetPattern::List { patterns, rest } => {
+                self.expect(&Type::List, ty);
+                for p in patterns.iter() {
+                    self.bind_let(p, &Type::Any);
+                }
+                if let Some(rest) = rest {
+                    self.define(rest, Type::List);
+                }
+            }
+        }
+    }
+}
@@ -1,43 +1,86 @@
 use std::cell::RefCell;
+use im_rc::Vector;
+use indexmap::IndexMap;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::iter;
+use std::ops::Deref;
 use std::rc::Rc;
 
 use crate::eval::evaluator::Interpretator;
 use crate::{
-    eval::error::RuntimeError,
-    parser::{Expression, MatchPattern},
+    eval::{
+        compiler::{compile, Chunk, Clause, LambdaCode},
+        error::RuntimeError,
+        integer::Integer,
+        pattern::{match_pattern, Bindings},
+        resolver::Scope,
+        stream::Stream,
+        text::Str,
+        types::Signature,
+    },
+    parser::Expression,
 };
 
 #[derive(Debug)]
 pub enum ValueType {
     Number,
+    Integer,
     String,
     Bool,
     List,
     Object,
     Lambda,
+    Lazy,
+    Result,
+    Ref,
+    Builder,
+    Stream,
+    Code,
     Null,
 }
 
 #[derive(Debug)]
 pub enum Value {
     Number(f64),
-    String(String),
+    Integer(Integer),
+    String(Str),
     Bool(bool),
-    List(Vec<ValueRef>),
-    Object(HashMap<String, ValueRef>),
+    List(List),
+    Object(Object),
     Lambda(Closure),
 
     NativeLambda(NativeClosure),
     SpecialForm(SpecialClosure),
 
+    Lazy(Thunk),
+
+    // outcome of a fallible operation: ok value or error payload
+    Result(Result<ValueRef, ValueRef>),
+
+    // mutable cell, shared by everyone holding the value
+    Ref(RefCell<ValueRef>),
+
+    // string being built, appended in place, shared by everyone holding the value
+    Builder(RefCell<String>),
+
+    // lazy sequence, its values are pulled from the source once
+    Stream(Stream),
+
+    // unevaluated expression, given to and made by macros
+    Code(Rc<Expression>),
+
     Null,
 }
 
 pub type ValueRef = Rc<Value>;
 
+/// Persistent vector: copies share structure, so slicing and updates are O(log n)
+pub type List = Vector<ValueRef>;
+
+/// Object fields, in the order they were added
+pub type Object = IndexMap<String, ValueRef>;
+
 /// Context passed to native functions, providing access to the interpretator and environment
 pub struct NativeContext<'a> {
     pub inter: &'a Interpretator,
@@ -51,11 +94,70 @@ impl<'a> NativeContext<'a> {
 }
 
 pub trait NativeFn: Debug {
-    fn exec(&self, args: &Vec<ValueRef>, ctx: &NativeContext) -> Result<ValueRef, RuntimeError>;
+    fn exec(&self, args: &[ValueRef], ctx: &NativeContext) -> Result<ValueRef, RuntimeError>;
+
+    /// Types of the arguments and the result, known to `shik check`
+    fn signature(&self) -> Option<Signature> {
+        None
+    }
 }
 
 pub trait SpecialFn: Debug {
-    fn exec(&self, args: &Vec<Expression>, ctx: &NativeContext) -> Result<ValueRef, RuntimeError>;
+    fn exec(&self, args: &[Argument], ctx: &NativeContext) -> Result<ValueRef, RuntimeError>;
+
+    /// Same as `exec`, but the form may leave one of its arguments to be evaluated
+    /// by the caller in tail position, e.g. the taken branch of `if`
+    fn exec_tail(&self, args: &[Argument], ctx: &NativeContext) -> Result<TailArg, RuntimeError> {
+        self.exec(args, ctx).map(TailArg::Value)
+    }
+
+    /// Whether the form runs once the application is over, a form which is not ready
+    /// stays a value, e.g. a macro which is not applied yet
+    fn is_ready(&self, _args: &[Argument]) -> bool {
+        true
+    }
+
+    /// Whether the form is a macro: its arguments are code, and its expansion
+    /// may define any name
+    fn is_macro(&self) -> bool {
+        false
+    }
+}
+
+/// Argument of a special form: the expression, shared with the program, and its compiled code
+#[derive(Debug, Clone)]
+pub struct Argument {
+    pub expr: Rc<Expression>,
+    pub code: Rc<Chunk>,
+}
+
+impl Argument {
+    pub fn new(expr: Expression) -> Self {
+        let code = compile(&expr);
+        Self {
+            expr: Rc::new(expr),
+            code,
+        }
+    }
+
+    pub fn with_code(expr: Rc<Expression>, code: Rc<Chunk>) -> Self {
+        Self { expr, code }
+    }
+}
+
+impl Deref for Argument {
+    type Target = Expression;
+
+    fn deref(&self) -> &Expression {
+        &self.expr
+    }
+}
+
+/// What is left after a special form is executed in tail position
+pub enum TailArg {
+    Value(ValueRef),
+    // index of the argument to evaluate (and force) in the form's environment
+    Arg(usize),
 }
 
 #[derive(Debug)]
@@ -68,7 +170,7 @@ pub struct NativeClosure {
 }
 #[derive(Debug)]
 pub struct SpecialClosure {
-    pub params: Vec<Expression>,
+    pub params: Vec<Argument>,
     pub interpretator: Rc<Interpretator>,
     pub env: EnvRef,
 
@@ -104,6 +206,18 @@ impl SpecialClosure {
         self.logic.exec(&self.params, &ctx)
     }
 
+    pub fn exec_tail(&self) -> Result<TailArg, RuntimeError> {
+        let ctx = NativeContext {
+            inter: &self.interpretator,
+            env: &self.env,
+        };
+        self.logic.exec_tail(&self.params, &ctx)
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.logic.is_ready(&self.params)
+    }
+
     pub fn new(logic: Rc<dyn SpecialFn>, interpretator: Rc<Interpretator>, env: EnvRef) -> Self {
         Self {
             params: Vec::new(),
@@ -114,36 +228,133 @@ impl SpecialClosure {
     }
 }
 
+/// Result of `f #> g`: applies `first`, then `second` to the result
+#[derive(Debug)]
+pub struct Composition {
+    pub first: ValueRef,
+    pub second: ValueRef,
+}
+
+impl NativeFn for Composition {
+    fn exec(&self, args: &[ValueRef], ctx: &NativeContext) -> Result<ValueRef, RuntimeError> {
+        let arg = args.first().ok_or(RuntimeError::InvalidApplication)?;
+        let res = ctx.apply(&self.first, arg)?;
+        ctx.apply(&self.second, &res)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Closure {
-    pub params: Vec<MatchPattern>,
+    pub code: Rc<LambdaCode>,
     pub binded: Vec<ValueRef>,
-    pub body: Box<Expression>,
     pub env: EnvRef,
 }
 
 impl Closure {
-    pub fn new(params: Vec<MatchPattern>, body: Box<Expression>, env: EnvRef) -> Self {
+    pub fn new(code: Rc<LambdaCode>, env: EnvRef) -> Self {
         Self {
-            params,
+            code,
             binded: Vec::new(),
-            body,
             env,
         }
     }
-    pub fn bind_variables(&self) {
-        for (p, v) in self
-            .params
-            .iter()
-            .zip(self.binded.iter())
-            .collect::<Vec<_>>()
-        {
-            match &p {
-                MatchPattern::Identifier(id) => {
-                    self.env.define(id.clone(), Rc::clone(v));
+
+    pub fn rest(&self) -> Option<&String> {
+        self.code.rest.as_ref()
+    }
+
+    pub fn arity(&self) -> usize {
+        self.code.clauses[0].parameters.len()
+    }
+
+    /// `fn [] ...` - evaluated as soon as it is referenced
+    pub fn takes_no_args(&self) -> bool {
+        self.arity() == 0 && self.rest().is_none()
+    }
+
+    /// Variadic lambda, which got all fixed arguments and is called when application ends
+    pub fn is_pending(&self) -> bool {
+        self.rest().is_some() && !self.binded.is_empty() && self.binded.len() >= self.arity()
+    }
+
+    /// Finds the first clause matching binded arguments, returns it with its variables
+    pub fn bind_variables(&self) -> Result<(&Clause, Bindings), RuntimeError> {
+        let mut bindings = Vec::new();
+
+        let clause = match self.code.clauses.as_slice() {
+            [clause] => {
+                for (p, v) in clause.parameters.iter().zip(self.binded.iter()) {
+                    if !match_pattern(p, v, &mut bindings) {
+                        return Err(RuntimeError::PatternMismatch {
+                            pattern: p.to_string(),
+                            reason: format!("argument does not match, got {}", v),
+                        });
+                    }
                 }
-                _ => panic!("not support pattern matching yet"),
+                clause
             }
+            clauses => clauses
+                .iter()
+                .find(|clause| {
+                    bindings.clear();
+                    clause
+                        .parameters
+                        .iter()
+                        .zip(self.binded.iter())
+                        .all(|(p, v)| match_pattern(p, v, &mut bindings))
+                })
+                .ok_or_else(|| {
+                    let args: Vec<String> = self.binded.iter().map(|v| v.to_string()).collect();
+                    RuntimeError::NoMatchingClause(args.join(" "))
+                })?,
+        };
+
+        if let Some(rest) = self.rest() {
+            let others: List = self.binded.iter().skip(self.arity()).cloned().collect();
+            bindings.push((rest.clone(), Rc::new(Value::List(others))));
+        }
+
+        Ok((clause, bindings))
+    }
+}
+
+/// Deferred computation made by `#( ... )`, evaluated only when forced
+#[derive(Debug)]
+pub struct Thunk {
+    pub body: Rc<Chunk>,
+    pub env: EnvRef,
+    // memoized thunk evaluates the body once and keeps the result
+    pub memoized: bool,
+    pub cache: RefCell<Option<ValueRef>>,
+}
+
+impl Thunk {
+    pub fn new(body: Rc<Chunk>, env: EnvRef) -> Self {
+        Self {
+            body,
+            env,
+            memoized: false,
+            cache: RefCell::new(None),
+        }
+    }
+
+    /// Copy of the thunk, which would keep the result after first evaluation
+    pub fn memoize(&self) -> Self {
+        Self {
+            body: Rc::clone(&self.body),
+            env: Rc::clone(&self.env),
+            memoized: true,
+            cache: RefCell::new(None),
+        }
+    }
+
+    pub fn cached(&self) -> Option<ValueRef> {
+        self.cache.borrow().clone()
+    }
+
+    pub fn store(&self, value: &ValueRef) {
+        if self.memoized {
+            *self.cache.borrow_mut() = Some(Rc::clone(value));
         }
     }
 }
@@ -151,6 +362,10 @@ impl Closure {
 #[derive(Debug)]
 pub struct Env {
     pub parent: Option<EnvRef>,
+    // local variables by their slots in the scope, see `resolver`
+    scope: Option<Rc<Scope>>,
+    slots: RefCell<Vec<Option<ValueRef>>>,
+    // globals and variables unknown to the scope, e.g. defined by macros
     pub vars: RefCell<HashMap<String, ValueRef>>,
 }
 
@@ -160,15 +375,23 @@ impl Value {
     pub fn as_number(&self) -> Option<f64> {
         match self {
             Value::Number(x) => Some(*x),
+            Value::Integer(x) => Some(x.to_f64()),
             _ => None,
         }
     }
+    /// Float or integer converted to a float
     pub fn expect_number(&self) -> Result<f64, RuntimeError> {
+        self.as_number().ok_or_else(|| RuntimeError::MissmatchedTypes {
+            got: self.get_type(),
+            expected: ValueType::Number,
+        })
+    }
+    pub fn expect_integer(&self) -> Result<&Integer, RuntimeError> {
         match self {
-            Value::Number(x) => Ok(*x),
+            Value::Integer(x) => Ok(x),
             _ => Err(RuntimeError::MissmatchedTypes {
                 got: self.get_type(),
-                expected: ValueType::Number,
+                expected: ValueType::Integer,
             }),
         }
     }
@@ -181,7 +404,7 @@ impl Value {
             }),
         }
     }
-    pub fn expect_string(&self) -> Result<&String, RuntimeError> {
+    pub fn expect_string(&self) -> Result<&Str, RuntimeError> {
         match self {
             Value::String(s) => Ok(s),
             _ => Err(RuntimeError::MissmatchedTypes {
@@ -190,7 +413,7 @@ impl Value {
             }),
         }
     }
-    pub fn expect_list(&self) -> Result<&Vec<ValueRef>, RuntimeError> {
+    pub fn expect_list(&self) -> Result<&List, RuntimeError> {
         match self {
             Value::List(lst) => Ok(lst),
             _ => Err(RuntimeError::MissmatchedTypes {
@@ -199,7 +422,7 @@ impl Value {
             }),
         }
     }
-    pub fn expect_obj(&self) -> Result<&HashMap<String, ValueRef>, RuntimeError> {
+    pub fn expect_obj(&self) -> Result<&Object, RuntimeError> {
         match self {
             Value::Object(obj) => Ok(obj),
             _ => Err(RuntimeError::MissmatchedTypes {
@@ -208,6 +431,42 @@ impl Value {
             }),
         }
     }
+    pub fn expect_result(&self) -> Result<&Result<ValueRef, ValueRef>, RuntimeError> {
+        match self {
+            Value::Result(res) => Ok(res),
+            _ => Err(RuntimeError::MissmatchedTypes {
+                got: self.get_type(),
+                expected: ValueType::Result,
+            }),
+        }
+    }
+    pub fn expect_ref(&self) -> Result<&RefCell<ValueRef>, RuntimeError> {
+        match self {
+            Value::Ref(cell) => Ok(cell),
+            _ => Err(RuntimeError::MissmatchedTypes {
+                got: self.get_type(),
+                expected: ValueType::Ref,
+            }),
+        }
+    }
+    pub fn expect_builder(&self) -> Result<&RefCell<String>, RuntimeError> {
+        match self {
+            Value::Builder(buf) => Ok(buf),
+            _ => Err(RuntimeError::MissmatchedTypes {
+                got: self.get_type(),
+                expected: ValueType::Builder,
+            }),
+        }
+    }
+    pub fn expect_stream(&self) -> Result<&Stream, RuntimeError> {
+        match self {
+            Value::Stream(stream) => Ok(stream),
+            _ => Err(RuntimeError::MissmatchedTypes {
+                got: self.get_type(),
+                expected: ValueType::Stream,
+            }),
+        }
+    }
     pub fn expect_native_lambda(&self) -> Result<&NativeClosure, RuntimeError> {
         match self {
             Value::NativeLambda(l) => Ok(l),
@@ -219,17 +478,24 @@ impl Value {
     }
 
     pub fn into_string(&self) -> Rc<Value> {
-        Rc::new(Value::String(self.to_string()))
+        Rc::new(Value::String(self.to_string().into()))
     }
 
     pub fn get_type(&self) -> ValueType {
         match self {
             Value::Number(_) => ValueType::Number,
+            Value::Integer(_) => ValueType::Integer,
             Value::String(_) => ValueType::String,
             Value::List(_) => ValueType::List,
             Value::Object(_) => ValueType::Object,
             Value::Lambda(_) | Value::NativeLambda(_) | Value::SpecialForm(_) => ValueType::Lambda,
             Value::Bool(_) => ValueType::Bool,
+            Value::Lazy(_) => ValueType::Lazy,
+            Value::Result(_) => ValueType::Result,
+            Value::Ref(_) => ValueType::Ref,
+            Value::Builder(_) => ValueType::Builder,
+            Value::Stream(_) => ValueType::Stream,
+            Value::Code(_) => ValueType::Code,
             Value::Null => ValueType::Null,
         }
     }
@@ -239,33 +505,124 @@ impl Env {
     pub fn new(parent: Option<EnvRef>) -> Self {
         Self {
             parent,
+            scope: None,
+            slots: RefCell::new(Vec::new()),
             vars: RefCell::new(HashMap::new()),
         }
     }
 
+    /// Environment of a scope, its variables are kept in slots
+    pub fn with_scope(parent: EnvRef, scope: &Rc<Scope>) -> Self {
+        Self {
+            parent: Some(parent),
+            scope: Some(Rc::clone(scope)),
+            slots: RefCell::new(vec![None; scope.names.len()]),
+            vars: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Environment for a body running in the scope, or a plain one for code outside of scopes
+    pub fn child(parent: &EnvRef, scope: Option<&Rc<Scope>>) -> EnvRef {
+        Rc::new(match scope {
+            Some(scope) => Self::with_scope(Rc::clone(parent), scope),
+            None => Self::new(Some(Rc::clone(parent))),
+        })
+    }
+
+    fn slot(&self, name: &str) -> Option<usize> {
+        self.scope.as_ref()?.slot(name)
+    }
+
     pub fn define(&self, name: String, value: ValueRef) {
-        self.vars.borrow_mut().insert(name, value);
+        match self.slot(&name) {
+            Some(i) => self.slots.borrow_mut()[i] = Some(value),
+            None => {
+                self.vars.borrow_mut().insert(name, value);
+            }
+        }
+    }
+
+    /// Variable defined in this environment, a slot is empty until its definition runs
+    fn get(&self, name: &str) -> Option<ValueRef> {
+        let defined = self.slot(name).and_then(|i| self.slots.borrow()[i].clone());
+        defined.or_else(|| self.vars.borrow().get(name).cloned())
     }
 
     pub fn lookup(&self, key: &str) -> Option<ValueRef> {
-        iter::successors(Some(self), |env| env.parent.as_deref())
-            .find_map(|env| env.vars.borrow().get(key).cloned())
+        iter::successors(Some(self), |env| env.parent.as_deref()).find_map(|env| env.get(key))
+    }
+
+    /// Variable at the place given by the resolver, `depth` counts environments of scopes.
+    /// While the slot is empty, the name is looked up around the scope, as with `lookup`.
+    pub fn lookup_slot(&self, depth: usize, slot: usize, name: &str) -> Option<ValueRef> {
+        let mut env = self.scoped();
+        for _ in 0..depth {
+            // the name may be defined in an inner scope when it runs, e.g. by a macro
+            let vars = env.vars.borrow();
+            if !vars.is_empty() {
+                if let Some(val) = vars.get(name) {
+                    return Some(Rc::clone(val));
+                }
+            }
+            env = env.parent.as_deref()?.scoped();
+        }
+
+        let val = env.slots.borrow().get(slot).cloned().flatten();
+        val.or_else(|| env.lookup(name))
+    }
+
+    /// The environment itself, or the closest parent made for a scope.
+    /// Other ones are made at runtime, e.g. for values given to special forms.
+    fn scoped(&self) -> &Env {
+        let mut env = self;
+        while env.scope.is_none() {
+            match env.parent.as_deref() {
+                Some(parent) => env = parent,
+                None => break,
+            }
+        }
+        env
+    }
+
+    /// Values of the variables defined in this environment
+    pub fn values(&self) -> Vec<ValueRef> {
+        let slots = self.slots.borrow();
+        let defined = slots.iter().flatten();
+        defined.chain(self.vars.borrow().values()).cloned().collect()
+    }
+
+    /// Removes all variables, returns their values
+    pub fn clear(&self) -> Vec<ValueRef> {
+        let mut slots = self.slots.borrow_mut();
+        let mut values: Vec<ValueRef> = slots.iter_mut().filter_map(Option::take).collect();
+        values.extend(self.vars.take().into_values());
+        values
     }
 
     pub fn assign(&self, name: &str, value: ValueRef) -> bool {
-        iter::successors(Some(self), |env| env.parent.as_deref())
-            .find(|env| env.vars.borrow().contains_key(name))
-            .map_or(false, |e| {
-                e.vars.borrow_mut().insert(name.to_string(), value);
-                true
-            })
+        for env in iter::successors(Some(self), |env| env.parent.as_deref()) {
+            if let Some(i) = env.slot(name) {
+                let mut slots = env.slots.borrow_mut();
+                if slots[i].is_some() {
+                    slots[i] = Some(value);
+                    return true;
+                }
+            }
+            if let Some(var) = env.vars.borrow_mut().get_mut(name) {
+                *var = value;
+                return true;
+            }
+        }
+        false
     }
 }
 
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Value::Number(x) => write!(f, "{}", x),
+            // floats keep the fraction, so they are not taken for integers
+            Value::Number(x) => write!(f, "{:?}", x),
+            Value::Integer(x) => write!(f, "{}", x),
             Value::String(s) => write!(f, "{}", s),
             Value::Bool(b) => write!(f, "{}", b),
             Value::List(l) => {
@@ -278,13 +635,20 @@ impl std::fmt::Display for Value {
             Value::Object(o) => {
                 write!(f, "{{")?;
                 for (name, value) in o.iter() {
-                    write!(f, "{}: {},\n", name, value)?;
+                    writeln!(f, "{}: {},", name, value)?;
                 }
                 write!(f, "}}")
             }
             Value::NativeLambda(_) | Value::Lambda(_) | Value::SpecialForm(_) => {
                 write!(f, "Lambda function")
             }
+            Value::Lazy(_) => write!(f, "Lazy value"),
+            Value::Result(Ok(val)) => write!(f, "ok({})", val),
+            Value::Result(Err(err)) => write!(f, "err({})", err),
+            Value::Ref(cell) => write!(f, "ref({})", cell.borrow()),
+            Value::Builder(buf) => write!(f, "{}", buf.borrow()),
+            Value::Stream(_) => write!(f, "Stream"),
+            Value::Code(expr) => write!(f, "{}", expr),
 
             Value::Null => write!(f, "null"),
         }
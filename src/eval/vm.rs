@@ -0,0 +1,505 @@
+//! Stack machine running the code made by `compiler`
+//!
+//! Calls of shik functions do not use the Rust stack: a called lambda gets a new frame,
+//! and a call in tail position of a function body replaces the frame of the caller,
+//! so recursive functions run in constant space.
+
+use crate::{
+    eval::{
+        compiler::{Chunk, Op},
+        error::RuntimeError,
+        evaluator::Interpretator,
+        format,
+        native_functions::code::user_macro,
+        pattern::{destructure, match_pattern},
+        text::{self, Str},
+        value::{
+            Closure, Composition, Env, EnvRef, List, NativeClosure, Object, SpecialClosure,
+            TailArg, Thunk, Value, ValueRef,
+        },
+        EvalResult,
+    },
+    parser::Span,
+};
+use std::{collections::VecDeque, mem, rc::Rc};
+
+// callers replaced by tail calls, which are kept for traces
+const TAIL_CALLS: usize = 8;
+
+struct Frame {
+    code: Rc<Chunk>,
+    pc: usize,
+    env: EnvRef,
+    // environments of the code around entered match arms
+    scopes: Vec<EnvRef>,
+    // stack size when the frame was entered
+    base: usize,
+    // body of a function: calls in tail position reuse the frame and the result is expanded
+    body: bool,
+    // the result goes to a consumer of lazy values
+    force: bool,
+    // call sites of the special form, whose argument the frame evaluates in tail position
+    sites: Vec<(Span, String)>,
+    // callers replaced by tail calls, the latest last
+    tail: VecDeque<Caller>,
+    // earlier callers replaced by tail calls, which are not kept
+    elided: usize,
+}
+
+/// Frame replaced by a tail call, where it made the call
+struct Caller {
+    code: Rc<Chunk>,
+    pc: usize,
+    sites: Vec<(Span, String)>,
+}
+
+impl Frame {
+    fn new(code: Rc<Chunk>, env: EnvRef, body: bool) -> Self {
+        Self {
+            code,
+            pc: 0,
+            env,
+            scopes: Vec::new(),
+            base: 0,
+            body,
+            force: false,
+            sites: Vec::new(),
+            tail: VecDeque::new(),
+            elided: 0,
+        }
+    }
+
+    fn in_tail(&self) -> bool {
+        self.body && self.code.is_tail(self.pc)
+    }
+
+    /// Call sites around the instruction at `pc`, innermost first
+    fn sites_at(&self, pc: usize) -> Vec<(Span, String)> {
+        sites_at(&self.code, pc, &self.sites)
+    }
+
+    /// Takes the place of the caller, which made the tail call at `at`
+    fn succeed(&mut self, caller: &mut Frame, at: usize) {
+        self.tail = mem::take(&mut caller.tail);
+        self.elided = caller.elided;
+        if self.tail.len() == TAIL_CALLS {
+            self.tail.pop_front();
+            self.elided += 1;
+        }
+        self.tail.push_back(Caller {
+            code: Rc::clone(&caller.code),
+            pc: at,
+            sites: mem::take(&mut caller.sites),
+        });
+    }
+}
+
+fn sites_at(code: &Chunk, pc: usize, sites: &[(Span, String)]) -> Vec<(Span, String)> {
+    code.sites_at(pc)
+        .map(|site| (site.span, site.callee.clone()))
+        .chain(sites.iter().cloned())
+        .collect()
+}
+
+/// Result of a function body in tail position
+enum Tail {
+    Value(ValueRef),
+    // closure with all arguments binded
+    Call(Closure),
+    // argument of a special form, to evaluate and force in the form's environment
+    Arg { code: Rc<Chunk>, env: EnvRef },
+}
+
+impl Interpretator {
+    /// Runs the code, the result is not expanded
+    pub fn run_code(&self, code: &Rc<Chunk>, env: &EnvRef) -> EvalResult {
+        self.execute(Frame::new(Rc::clone(code), Rc::clone(env), false))
+    }
+
+    /// Evaluates the closure body in a fresh scope with binded arguments
+    pub fn call_closure(&self, closure: &Closure) -> EvalResult {
+        let frame = self.enter(closure)?;
+        self.execute(frame)
+    }
+
+    fn enter(&self, closure: &Closure) -> Result<Frame, RuntimeError> {
+        let (clause, bindings) = closure.bind_variables()?;
+        let env = Env::child(&closure.env, clause.body.scope.as_ref());
+        for (name, val) in bindings {
+            env.define(name, val);
+        }
+        Ok(Frame::new(Rc::clone(&clause.body), env, true))
+    }
+
+    fn execute(&self, frame: Frame) -> EvalResult {
+        let mut frames = vec![frame];
+        let mut stack = Vec::new();
+
+        self.dispatch(&mut frames, &mut stack)
+            .map_err(|error| trace(&frames, error))
+    }
+
+    fn dispatch(&self, frames: &mut Vec<Frame>, stack: &mut Vec<ValueRef>) -> EvalResult {
+        loop {
+            let frame = frames.last_mut().expect("no frame to run");
+            let op = frame.code.code[frame.pc];
+            frame.pc += 1;
+
+            match op {
+                Op::Number(x) => stack.push(Rc::new(Value::Number(x))),
+                Op::Integer(i) => stack.push(Rc::clone(&frame.code.integers[i])),
+                Op::String(i) => {
+                    stack.push(Rc::new(Value::String(frame.code.strings[i].clone())));
+                }
+                Op::Null => stack.push(Rc::new(Value::Null)),
+                Op::Load(i) => {
+                    let name = &frame.code.names[i];
+                    let val = frame
+                        .env
+                        .lookup(name)
+                        .ok_or_else(|| RuntimeError::UndefinedVariable(name.clone()))?;
+                    self.load(frames, stack, val)?;
+                }
+                Op::LoadSlot { depth, slot, name } => {
+                    let name = &frame.code.names[name];
+                    let val = frame
+                        .env
+                        .lookup_slot(depth, slot, name)
+                        .ok_or_else(|| RuntimeError::UndefinedVariable(name.clone()))?;
+                    self.load(frames, stack, val)?;
+                }
+                Op::Pop => {
+                    stack.pop();
+                }
+                Op::Expand => {
+                    // in tail position the value is expanded on return
+                    if !frame.in_tail() {
+                        let val = pop(stack);
+                        stack.push(self.expand(val)?);
+                    }
+                }
+                Op::List(n) => {
+                    let items: List = stack.drain(stack.len() - n..).collect();
+                    stack.push(Rc::new(Value::List(items)));
+                }
+                Op::Object(n) => {
+                    let mut res = Object::new();
+                    let mut fields = stack.drain(stack.len() - 2 * n..);
+
+                    while let (Some(key), Some(val)) = (fields.next(), fields.next()) {
+                        res.insert(key.expect_string()?.to_string(), val);
+                    }
+                    drop(fields);
+                    stack.push(Rc::new(Value::Object(res)));
+                }
+                Op::Interpolate(i) => {
+                    let template = &frame.code.templates[i];
+                    let n = template.positions.len();
+                    let mut str = String::with_capacity(template.string.len());
+                    // long strings are joined as they are, the text around them is collected
+                    let mut joined = Str::default();
+
+                    // values are in the reversed order, each replaces one placeholder character
+                    let values: Vec<ValueRef> = stack.drain(stack.len() - n..).rev().collect();
+                    let mut start = 0;
+                    let entries = template.positions.iter().zip(&template.formats);
+                    for ((&i, spec), val) in entries.zip(values.iter()) {
+                        str.push_str(&template.string[start..i]);
+                        match (spec, val.as_ref()) {
+                            (Some(spec), val) => str.push_str(&format::format(val, spec)?),
+                            (None, Value::String(s)) if s.len() >= text::MIN_NODE => {
+                                joined = joined.concat(&mem::take(&mut str).into());
+                                joined = joined.concat(s);
+                            }
+                            (None, Value::String(s)) => str.push_str(s),
+                            (None, val) => str.push_str(&val.to_string()),
+                        }
+                        start = i + 1;
+                    }
+                    str.push_str(&template.string[start..]);
+
+                    stack.push(Rc::new(Value::String(joined.concat(&str.into()))));
+                }
+                Op::Arg { arg, end } => {
+                    let Value::SpecialForm(closure) = top(stack).as_ref() else {
+                        continue;
+                    };
+
+                    // special forms get the expression, it is not evaluated
+                    let mut curried = SpecialClosure::new(
+                        Rc::clone(&closure.logic),
+                        Rc::clone(&closure.interpretator),
+                        Rc::clone(&frame.env),
+                    );
+                    curried.params.extend_from_slice(&closure.params);
+                    curried.params.push(frame.code.args[arg].argument());
+                    let f = Rc::new(Value::SpecialForm(curried));
+
+                    stack.pop();
+                    let at = frame.pc - 1;
+                    frame.pc = end;
+
+                    if frame.in_tail() {
+                        if let Some(val) = self.finish(frames, stack, f, at)? {
+                            return Ok(val);
+                        }
+                    } else {
+                        stack.push(f);
+                    }
+                }
+                Op::Call => {
+                    let a = pop(stack);
+                    let f = pop(stack);
+
+                    match f.as_ref() {
+                        // variadic lambdas collect arguments until the application is over
+                        Value::Lambda(closure) if closure.rest().is_some() => {
+                            let mut curried = closure.clone();
+                            curried.binded.push(a);
+                            stack.push(Rc::new(Value::Lambda(curried)));
+                        }
+                        Value::Lambda(closure) if closure.takes_no_args() => {
+                            self.call(frames, stack, closure)?;
+                        }
+                        Value::Lambda(closure) => {
+                            let mut curried = closure.clone();
+                            curried.binded.push(a);
+
+                            if curried.binded.len() >= curried.arity() {
+                                self.call(frames, stack, &curried)?;
+                            } else {
+                                stack.push(Rc::new(Value::Lambda(curried)));
+                            }
+                        }
+                        _ => stack.push(self.apply_fn(&f, &a)?),
+                    }
+                }
+                Op::Compose => {
+                    // Special forms are kept curried, they receive the value when composition is applied
+                    let second = pop(stack);
+                    let first = pop(stack);
+
+                    stack.push(Rc::new(Value::NativeLambda(NativeClosure::new(
+                        1,
+                        Rc::new(Composition { first, second }),
+                        self.rc(),
+                        Rc::clone(&frame.env),
+                    ))));
+                }
+                Op::Macro => {
+                    let expander = pop(stack);
+                    stack.push(user_macro(expander, self.rc(), Rc::clone(&frame.env)));
+                }
+                Op::Unquote => {
+                    return Err(RuntimeError::MacroError(
+                        "~ can only be used inside quote".to_string(),
+                    ))
+                }
+                Op::Lambda(i) => {
+                    let code = Rc::clone(&frame.code.lambdas[i]);
+                    let closure = Closure::new(code, Rc::clone(&frame.env));
+                    stack.push(Rc::new(Value::Lambda(closure)));
+                }
+                Op::Thunk(i) => {
+                    let body = Rc::clone(&frame.code.thunks[i]);
+                    let thunk = Thunk::new(body, Rc::clone(&frame.env));
+                    stack.push(Rc::new(Value::Lazy(thunk)));
+                }
+                Op::Let(i) => {
+                    destructure(&frame.code.lets[i], top(stack), &frame.env)?;
+                    self.heap.track_env(&frame.env, top(stack));
+                }
+                Op::Match(i) => {
+                    let value = pop(stack);
+                    let mut bindings = Vec::new();
+
+                    let arm = frame.code.matches[i].iter().find(|arm| {
+                        bindings.clear();
+                        match_pattern(&arm.pattern, &value, &mut bindings)
+                    });
+                    let Some(arm) = arm else {
+                        return Err(RuntimeError::NoMatchingPattern(value.to_string()));
+                    };
+
+                    let body = arm.body;
+                    let env = Env::child(&frame.env, Some(&arm.scope));
+                    for (name, val) in bindings {
+                        env.define(name, val);
+                    }
+                    frame.scopes.push(mem::replace(&mut frame.env, env));
+                    frame.pc = body;
+                }
+                Op::PopEnv => {
+                    frame.env = frame.scopes.pop().expect("no scope to leave");
+                }
+                Op::Jump(to) => frame.pc = to,
+                Op::Return => {
+                    let val = pop(stack);
+                    if !frame.body {
+                        return Ok(val);
+                    }
+
+                    let at = frame.pc - 1;
+                    if let Some(val) = self.finish(frames, stack, val, at)? {
+                        return Ok(val);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Pushes the value of a variable, a function without arguments is called instead
+    fn load(
+        &self,
+        frames: &mut Vec<Frame>,
+        stack: &mut Vec<ValueRef>,
+        val: ValueRef,
+    ) -> Result<(), RuntimeError> {
+        match val.as_ref() {
+            Value::Lambda(closure) if closure.takes_no_args() => self.call(frames, stack, closure)?,
+            Value::NativeLambda(closure) if closure.params_count == 0 => {
+                stack.push(closure.exec()?)
+            }
+            _ => stack.push(val),
+        }
+        Ok(())
+    }
+
+    /// Calls the closure which got all arguments, in tail position the frame is reused
+    fn call(
+        &self,
+        frames: &mut Vec<Frame>,
+        stack: &mut Vec<ValueRef>,
+        closure: &Closure,
+    ) -> Result<(), RuntimeError> {
+        let mut callee = self.enter(closure)?;
+        let caller = frames.last_mut().expect("no caller frame");
+
+        if caller.in_tail() {
+            callee.force = caller.force;
+            let at = caller.pc - 1;
+            callee.succeed(caller, at);
+            replace(frames, stack, callee);
+        } else {
+            callee.base = stack.len();
+            frames.push(callee);
+        }
+        Ok(())
+    }
+
+    /// Completes the frame with the value in tail position, the value of the whole run
+    /// is returned once the first frame is completed.
+    /// `at` is the instruction which produced the value.
+    fn finish(
+        &self,
+        frames: &mut Vec<Frame>,
+        stack: &mut Vec<ValueRef>,
+        val: ValueRef,
+        at: usize,
+    ) -> Result<Option<ValueRef>, RuntimeError> {
+        let frame = frames.last().expect("no frame to finish");
+
+        match self.expand_tail(val)? {
+            Tail::Value(val) => {
+                let val = match val.as_ref() {
+                    // body of a lazy value is evaluated in tail position as well
+                    Value::Lazy(thunk) if frame.force && !thunk.memoized => {
+                        let env = Env::child(&thunk.env, thunk.body.scope.as_ref());
+                        let mut next = Frame::new(Rc::clone(&thunk.body), env, true);
+                        next.force = true;
+                        next.sites = frame.sites_at(at);
+                        replace(frames, stack, next);
+                        return Ok(None);
+                    }
+                    _ if frame.force => self.force(val)?,
+                    _ => val,
+                };
+
+                let frame = frames.pop().expect("no frame to finish");
+                stack.truncate(frame.base);
+
+                if frames.is_empty() {
+                    Ok(Some(val))
+                } else {
+                    stack.push(val);
+                    Ok(None)
+                }
+            }
+            Tail::Call(closure) => {
+                let mut next = self.enter(&closure)?;
+                let caller = frames.last_mut().expect("no frame to finish");
+                next.force = caller.force;
+                next.succeed(caller, at);
+                replace(frames, stack, next);
+                Ok(None)
+            }
+            Tail::Arg { code, env } => {
+                let mut next = Frame::new(code, env, true);
+                next.force = true;
+                next.sites = frame.sites_at(at);
+                replace(frames, stack, next);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Like `expand`, but the last step of a special form or a pending lambda is left to the caller
+    fn expand_tail(&self, v: ValueRef) -> Result<Tail, RuntimeError> {
+        match v.as_ref() {
+            Value::SpecialForm(closure) if closure.is_ready() => match closure.exec_tail()? {
+                TailArg::Value(v) => self.expand_tail(v),
+                TailArg::Arg(i) => Ok(Tail::Arg {
+                    code: Rc::clone(&closure.params[i].code),
+                    env: Rc::clone(&closure.env),
+                }),
+            },
+            Value::Lambda(closure) if closure.is_pending() => Ok(Tail::Call(closure.clone())),
+            _ => Ok(Tail::Value(v)),
+        }
+    }
+}
+
+/// Puts the frame in place of the current one
+fn replace(frames: &mut Vec<Frame>, stack: &mut Vec<ValueRef>, next: Frame) {
+    let mut next = next;
+    let frame = frames.pop().expect("no frame to replace");
+
+    stack.truncate(frame.base);
+    next.base = frame.base;
+    // the frame goes on with the body, e.g. an argument of a special form
+    if next.tail.is_empty() {
+        next.tail = frame.tail;
+        next.elided = frame.elided;
+    }
+    frames.push(next);
+}
+
+/// Adds the place of the error and the calls it went through
+fn trace(frames: &[Frame], error: RuntimeError) -> RuntimeError {
+    let mut error = error;
+
+    for frame in frames.iter().rev() {
+        for (span, callee) in frame.sites_at(frame.pc - 1) {
+            error = error.at_call(span, || callee);
+        }
+        if frame.body {
+            error = error.unwind();
+        }
+        for caller in frame.tail.iter().rev() {
+            for (span, callee) in sites_at(&caller.code, caller.pc, &caller.sites) {
+                error = error.at_call(span, || callee);
+            }
+            error = error.unwind();
+        }
+        error = error.elide(frame.elided);
+    }
+    error
+}
+
+fn pop(stack: &mut Vec<ValueRef>) -> ValueRef {
+    stack.pop().expect("stack is empty")
+}
+
+fn top(stack: &[ValueRef]) -> &ValueRef {
+    stack.last().expect("stack is empty")
+}
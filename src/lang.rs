@@ -2,8 +2,10 @@
 
 use crate::eval::error::RuntimeError;
 use crate::eval::evaluator::Interpretator;
+use crate::eval::{resolver, types};
 use crate::eval::value::{Value, ValueRef};
 use crate::parser::{parse, ParseError};
+use std::path::Path;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -22,7 +24,8 @@ pub fn evaluate(input: &str, interpretator: &Interpretator) -> Result<ValueRef,
     Ok(result)
 }
 
-pub fn print(input: Result<ValueRef, EvalError>, silent: bool) {
+/// Prints the result, runtime errors are reported with the place in `source`
+pub fn print(input: Result<ValueRef, EvalError>, silent: bool, file: &str, source: &str) {
     match input {
         Ok(res) => {
             if !silent {
@@ -32,10 +35,18 @@ pub fn print(input: Result<ValueRef, EvalError>, silent: bool) {
                 };
             }
         }
+        Err(EvalError::Runtime(e)) => println!("{}", e.report(file, source)),
         Err(e) => println!("{}", e),
     }
 }
 
+/// Prints warnings found while the input was evaluated
+fn print_warnings(interpretator: &Interpretator) {
+    for warning in interpretator.take_warnings() {
+        eprintln!("{}", warning);
+    }
+}
+
 pub fn run_repl() {
     use std::io::{self, Write};
 
@@ -71,7 +82,11 @@ pub fn run_repl() {
             "" => {
                 // Empty input, just continue
             }
-            _ => print(evaluate(input, &interpretator), false),
+            _ => {
+                let result = evaluate(input, &interpretator);
+                print_warnings(&interpretator);
+                print(result, false, "<repl>", input)
+            }
         }
     }
 }
@@ -79,8 +94,44 @@ pub fn run_repl() {
 pub fn eval_file(path: String) {
     use std::fs::read_to_string;
 
-    let contents = read_to_string(path).expect("Unable to open the file");
+    let contents = read_to_string(&path).expect("Unable to open the file");
+    let interpretator = Interpretator::new();
+    interpretator.modules.borrow_mut().set_main(Path::new(&path));
+
+    let result = evaluate(&contents, &interpretator);
+    print_warnings(&interpretator);
+    print(result, true, &path, &contents)
+}
+
+/// Checks names and types of the file without running it, returns whether it has no errors
+pub fn check_file(path: String) -> bool {
+    use std::fs::read_to_string;
+
+    let contents = read_to_string(&path).expect("Unable to open the file");
+    let program = match parse(&contents) {
+        Ok(program) => program,
+        Err(e) => {
+            println!("{}", EvalError::Parse(e));
+            return false;
+        }
+    };
+
     let interpretator = Interpretator::new();
+    interpretator.modules.borrow_mut().set_main(Path::new(&path));
 
-    print(evaluate(&contents, &interpretator), true)
+    let mut errors = Vec::new();
+    match resolver::check(&program, &interpretator.ctx, &interpretator.builtins) {
+        Ok(warnings) => warnings.iter().for_each(|w| println!("{}", w)),
+        Err(e) => errors.push(e),
+    }
+    errors.extend(types::check(&program, &interpretator.ctx));
+
+    for e in errors.iter() {
+        println!("{}\n", e.report(&path, &contents));
+    }
+    match errors.len() {
+        0 => println!("{}: no errors found", path),
+        n => println!("{}: {} error(s) found", path, n),
+    }
+    errors.is_empty()
 }
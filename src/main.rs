@@ -1,10 +1,14 @@
-use shik::lang::{eval_file, run_repl};
-use std::env;
+use shik::lang::{check_file, eval_file, run_repl};
+use std::{env, process};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    println!("args: {:?}", args);
-    if args.len() > 1 {
+    if args.len() > 2 && args[1] == "check" {
+        // `shik check script.shk` reports errors found without running the script
+        if !check_file(args[2].clone()) {
+            process::exit(1);
+        }
+    } else if args.len() > 1 {
         eval_file(args[1].clone());
     } else {
         run_repl();
@@ -1,3 +1,7 @@
+use crate::parser::format::FormatSpec;
+use num_bigint::BigInt;
+use std::rc::Rc;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Program {
     pub statements: Vec<Statement>,
@@ -14,26 +18,30 @@ pub struct Statement {
 pub enum Expression {
     // Literals
     Number(f64),
+    Integer(BigInt),
     String(String),
     StringInterpolation(StringInterpolationInfo),
     Identifier(String),
 
     // Binary operations
     Pipe {
-        left: Box<Expression>,
-        right: Box<Expression>,
+        left: Rc<Expression>,
+        right: Rc<Expression>,
+        span: Span,
     },
     Flow {
-        left: Box<Expression>,
-        right: Box<Expression>,
+        left: Rc<Expression>,
+        right: Rc<Expression>,
     },
     Chain {
-        left: Box<Expression>,
-        right: Box<Expression>,
+        left: Rc<Expression>,
+        right: Rc<Expression>,
+        span: Span,
     },
     Application {
-        function: Box<Expression>,
-        argument: Box<Expression>,
+        function: Rc<Expression>,
+        argument: Rc<Expression>,
+        span: Span,
     },
 
     // Collections
@@ -43,20 +51,44 @@ pub enum Expression {
     // Special forms
     Let {
         pattern: LetPattern,
-        value: Box<Expression>,
+        value: Rc<Expression>,
     },
     Lambda {
         parameters: Vec<MatchPattern>,
         rest: Option<String>,
-        body: Box<Expression>,
+        body: Rc<Expression>,
+    },
+    MultiLambda {
+        clauses: Vec<LambdaClause>,
     },
+    Match {
+        subject: Rc<Expression>,
+        arms: Vec<MatchArm>,
+    },
+    // function from argument expressions to code, holds a Lambda or MultiLambda
+    Macro(Rc<Expression>),
+    // place in quoted code filled with the value of the expression
+    Unquote(Rc<Expression>),
 
     // Grouping
-    Parenthesized(Box<Expression>),
+    Parenthesized(Rc<Expression>),
     Block(Vec<Expression>),
     Lazy(Vec<Expression>),
 }
 
+/// Position in the source, where the called function starts
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct StringInterpolationInfo {
     // Interpolated string, with all interpolations replaced with placeholder "_"
@@ -66,6 +98,7 @@ pub struct StringInterpolationInfo {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Interpolation {
     pub expression: Expression,
+    pub format: Option<FormatSpec>,
     pub start: usize,
     pub end: usize,
 
@@ -79,9 +112,23 @@ pub struct ObjectItem {
     pub value: Expression,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct LambdaClause {
+    pub parameters: Vec<MatchPattern>,
+    pub body: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: MatchPattern,
+    pub body: Expression,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum LetPattern {
     Identifier(String),
+    // `name:Type`, the type is checked by `shik check` only
+    Typed { name: String, ty: String },
     List {
         patterns: Vec<LetPattern>,
         rest: Option<String>,
@@ -91,6 +138,8 @@ pub enum LetPattern {
 #[derive(Debug, Clone, PartialEq)]
 pub enum MatchPattern {
     Identifier(String),
+    // `name:Type`, the type is checked by `shik check` only
+    Typed { name: String, ty: String },
     Literal(LiteralPattern),
     List {
         patterns: Vec<MatchPattern>,
@@ -102,14 +151,55 @@ pub enum MatchPattern {
 #[derive(Debug, Clone, PartialEq)]
 pub enum LiteralPattern {
     Number(f64),
+    Integer(BigInt),
     String(String),
 }
 
+fn fmt_list_pattern<T: std::fmt::Display>(
+    f: &mut std::fmt::Formatter<'_>,
+    patterns: &[T],
+    rest: &Option<String>,
+) -> std::fmt::Result {
+    let mut items: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+    if let Some(rest) = rest {
+        items.push(format!("...{}", rest));
+    }
+    write!(f, "[{}]", items.join(" "))
+}
+
+impl std::fmt::Display for LetPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LetPattern::Identifier(name) => write!(f, "{}", name),
+            LetPattern::Typed { name, ty } => write!(f, "{}:{}", name, ty),
+            LetPattern::List { patterns, rest } => fmt_list_pattern(f, patterns, rest),
+        }
+    }
+}
+
+impl std::fmt::Display for MatchPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchPattern::Identifier(name) => write!(f, "{}", name),
+            MatchPattern::Typed { name, ty } => write!(f, "{}:{}", name, ty),
+            MatchPattern::Literal(LiteralPattern::Number(x)) => write!(f, "{:?}", x),
+            MatchPattern::Literal(LiteralPattern::Integer(x)) => write!(f, "{}", x),
+            MatchPattern::Literal(LiteralPattern::String(s)) => write!(f, "{:?}", s),
+            MatchPattern::List { patterns, rest } => fmt_list_pattern(f, patterns, rest),
+            MatchPattern::Wildcard => write!(f, "_"),
+        }
+    }
+}
+
 impl Expression {
     pub fn number(value: f64) -> Self {
         Expression::Number(value)
     }
 
+    pub fn integer(value: BigInt) -> Self {
+        Expression::Integer(value)
+    }
+
     pub fn string(value: String) -> Self {
         Expression::String(value)
     }
@@ -118,34 +208,52 @@ impl Expression {
         Expression::Identifier(name)
     }
 
-    pub fn pipe(left: Expression, right: Expression) -> Self {
+    pub fn pipe(left: Expression, right: Expression, span: Span) -> Self {
         Expression::Pipe {
-            left: Box::new(left),
-            right: Box::new(right),
+            left: Rc::new(left),
+            right: Rc::new(right),
+            span,
         }
     }
 
-    pub fn chain(left: Expression, right: Expression) -> Self {
+    pub fn chain(left: Expression, right: Expression, span: Span) -> Self {
         Expression::Chain {
-            left: Box::new(left),
-            right: Box::new(right),
+            left: Rc::new(left),
+            right: Rc::new(right),
+            span,
         }
     }
 
     pub fn flow(left: Expression, right: Expression) -> Self {
         Expression::Flow {
-            left: Box::new(left),
-            right: Box::new(right),
+            left: Rc::new(left),
+            right: Rc::new(right),
         }
     }
 
-    pub fn application(function: Expression, argument: Expression) -> Self {
+    pub fn application(function: Expression, argument: Expression, span: Span) -> Self {
         Expression::Application {
-            function: Box::new(function),
-            argument: Box::new(argument),
+            function: Rc::new(function),
+            argument: Rc::new(argument),
+            span,
+        }
+    }
+
+    pub fn match_expr(subject: Expression, arms: Vec<MatchArm>) -> Self {
+        Expression::Match {
+            subject: Rc::new(subject),
+            arms,
         }
     }
 
+    pub fn macro_expr(lambda: Expression) -> Self {
+        Expression::Macro(Rc::new(lambda))
+    }
+
+    pub fn unquote(expr: Expression) -> Self {
+        Expression::Unquote(Rc::new(expr))
+    }
+
     pub fn list(items: Vec<Expression>) -> Self {
         Expression::List(items)
     }
@@ -155,7 +263,7 @@ impl Expression {
     }
 
     pub fn parenthesized(expr: Expression) -> Self {
-        Expression::Parenthesized(Box::new(expr))
+        Expression::Parenthesized(Rc::new(expr))
     }
 
     pub fn block(expressions: Vec<Expression>) -> Self {
@@ -166,3 +274,257 @@ impl Expression {
         Expression::Lazy(expressions)
     }
 }
+
+impl Expression {
+    /// Copy of the expression with every `Unquote` replaced by the result of `fill`
+    pub fn fill_unquotes<E>(
+        &self,
+        fill: &mut impl FnMut(&Expression) -> Result<Expression, E>,
+    ) -> Result<Expression, E> {
+        let mut shared = |expr: &Expression| expr.fill_unquotes(fill).map(Rc::new);
+
+        Ok(match self {
+            Expression::Unquote(expr) => return fill(expr),
+            Expression::Number(_)
+            | Expression::Integer(_)
+            | Expression::String(_)
+            | Expression::Identifier(_) => self.clone(),
+            Expression::StringInterpolation(info) => {
+                let mut info = info.clone();
+                for entry in info.entries.iter_mut() {
+                    entry.expression = entry.expression.fill_unquotes(fill)?;
+                }
+                Expression::StringInterpolation(info)
+            }
+            Expression::Pipe { left, right, span } => Expression::Pipe {
+                left: shared(left)?,
+                right: shared(right)?,
+                span: *span,
+            },
+            Expression::Flow { left, right } => Expression::Flow {
+                left: shared(left)?,
+                right: shared(right)?,
+            },
+            Expression::Chain { left, right, span } => Expression::Chain {
+                left: shared(left)?,
+                right: shared(right)?,
+                span: *span,
+            },
+            Expression::Application {
+                function,
+                argument,
+                span,
+            } => Expression::Application {
+                function: shared(function)?,
+                argument: shared(argument)?,
+                span: *span,
+            },
+            Expression::List(items) => Expression::List(fill_all(items, fill)?),
+            Expression::Object(items) => Expression::Object(
+                items
+                    .iter()
+                    .map(|item| {
+                        Ok(ObjectItem {
+                            key: item.key.fill_unquotes(fill)?,
+                            value: item.value.fill_unquotes(fill)?,
+                        })
+                    })
+                    .collect::<Result<_, E>>()?,
+            ),
+            Expression::Let { pattern, value } => Expression::Let {
+                pattern: pattern.clone(),
+                value: shared(value)?,
+            },
+            Expression::Lambda {
+                parameters,
+                rest,
+                body,
+            } => Expression::Lambda {
+                parameters: parameters.clone(),
+                rest: rest.clone(),
+                body: shared(body)?,
+            },
+            Expression::MultiLambda { clauses } => Expression::MultiLambda {
+                clauses: clauses
+                    .iter()
+                    .map(|clause| {
+                        Ok(LambdaClause {
+                            parameters: clause.parameters.clone(),
+                            body: clause.body.fill_unquotes(fill)?,
+                        })
+                    })
+                    .collect::<Result<_, E>>()?,
+            },
+            Expression::Match { subject, arms } => Expression::Match {
+                subject: shared(subject)?,
+                arms: arms
+                    .iter()
+                    .map(|arm| {
+                        Ok(MatchArm {
+                            pattern: arm.pattern.clone(),
+                            body: arm.body.fill_unquotes(fill)?,
+                        })
+                    })
+                    .collect::<Result<_, E>>()?,
+            },
+            Expression::Macro(lambda) => Expression::Macro(shared(lambda)?),
+            Expression::Parenthesized(expr) => Expression::Parenthesized(shared(expr)?),
+            Expression::Block(exprs) => Expression::Block(fill_all(exprs, fill)?),
+            Expression::Lazy(exprs) => Expression::Lazy(fill_all(exprs, fill)?),
+        })
+    }
+}
+
+fn fill_all<E>(
+    exprs: &[Expression],
+    fill: &mut impl FnMut(&Expression) -> Result<Expression, E>,
+) -> Result<Vec<Expression>, E> {
+    exprs.iter().map(|expr| expr.fill_unquotes(fill)).collect()
+}
+
+// ============================================================================
+// Printing expressions back as source code
+// ============================================================================
+
+/// Expression as an operand: anything, which is not a single term, is put into parentheses
+struct Operand<'a>(&'a Expression);
+
+impl std::fmt::Display for Operand<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Expression::Pipe { .. }
+            | Expression::Flow { .. }
+            | Expression::Chain { .. }
+            | Expression::Application { .. }
+            | Expression::Let { .. }
+            | Expression::Lambda { .. }
+            | Expression::MultiLambda { .. }
+            | Expression::Match { .. }
+            | Expression::Macro(_) => write!(f, "({})", self.0),
+            expr => write!(f, "{}", expr),
+        }
+    }
+}
+
+/// Operand of `$>` and `$`, which bind looser than application
+struct Loose<'a>(&'a Expression);
+
+impl std::fmt::Display for Loose<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Expression::Application { .. } => write!(f, "{}", self.0),
+            expr => write!(f, "{}", Operand(expr)),
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::new();
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '{' => out.push_str("\\{"),
+            '}' => out.push_str("\\}"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+fn fmt_lines(f: &mut std::fmt::Formatter<'_>, open: &str, exprs: &[Expression]) -> std::fmt::Result {
+    let lines: Vec<String> = exprs.iter().map(|expr| expr.to_string()).collect();
+    write!(f, "{}{})", open, lines.join("\n"))
+}
+
+impl std::fmt::Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            // floats keep the fraction, so the code is read back as a float
+            Expression::Number(x) => write!(f, "{:?}", x),
+            Expression::Integer(x) => write!(f, "{}", x),
+            Expression::String(s) => write!(f, "\"{}\"", escape(s)),
+            Expression::StringInterpolation(info) => {
+                let mut last = 0;
+                write!(f, "\"")?;
+                for entry in info.entries.iter() {
+                    let text = &info.string[last..entry.position];
+                    write!(f, "{}{{{}", escape(text), entry.expression)?;
+                    match &entry.format {
+                        Some(format) => write!(f, ":{}}}", format)?,
+                        None => write!(f, "}}")?,
+                    }
+                    last = entry.position + 1;
+                }
+                write!(f, "{}\"", escape(&info.string[last..]))
+            }
+            Expression::Identifier(name) => write!(f, "{}", name),
+            Expression::Pipe { left, right, .. } => {
+                write!(f, "{} $> {}", Loose(left), Loose(right))
+            }
+            Expression::Flow { left, right } => {
+                write!(f, "{} #> {}", Operand(left), Operand(right))
+            }
+            Expression::Chain { left, right, .. } => {
+                write!(f, "{} $ {}", Loose(left), Loose(right))
+            }
+            Expression::Application {
+                function, argument, ..
+            } => match function.as_ref() {
+                // curried application reads as one call
+                Expression::Application { .. } => write!(f, "{} {}", function, Operand(argument)),
+                function => write!(f, "{} {}", Operand(function), Operand(argument)),
+            },
+            Expression::List(items) => {
+                let items: Vec<String> = items.iter().map(|it| Operand(it).to_string()).collect();
+                write!(f, "[{}]", items.join(" "))
+            }
+            Expression::Object(items) => {
+                write!(f, "{{")?;
+                for item in items.iter() {
+                    write!(f, " {} {}", Operand(&item.key), Operand(&item.value))?;
+                }
+                write!(f, " }}")
+            }
+            Expression::Let { pattern, value } => write!(f, "let {} {}", pattern, value),
+            Expression::Lambda {
+                parameters,
+                rest,
+                body,
+            } => {
+                write!(f, "fn ")?;
+                fmt_list_pattern(f, parameters, rest)?;
+                write!(f, " {}", body)
+            }
+            Expression::MultiLambda { clauses } => {
+                let clauses: Vec<String> = clauses
+                    .iter()
+                    .map(|clause| {
+                        let params: Vec<String> =
+                            clause.parameters.iter().map(|p| p.to_string()).collect();
+                        format!("[{}] {}", params.join(" "), Operand(&clause.body))
+                    })
+                    .collect();
+                write!(f, "fn {}", clauses.join(" | "))
+            }
+            Expression::Match { subject, arms } => {
+                write!(f, "match {} {{", Operand(subject))?;
+                for arm in arms.iter() {
+                    write!(f, " {} {}", arm.pattern, Operand(&arm.body))?;
+                }
+                write!(f, " }}")
+            }
+            Expression::Macro(lambda) => {
+                let lambda = lambda.to_string();
+                write!(f, "macro {}", lambda.strip_prefix("fn ").unwrap_or(&lambda))
+            }
+            Expression::Unquote(expr) => write!(f, "~{}", Operand(expr)),
+            Expression::Parenthesized(expr) => write!(f, "({})", expr),
+            Expression::Block(exprs) => fmt_lines(f, "'(", exprs),
+            Expression::Lazy(exprs) => fmt_lines(f, "#(", exprs),
+        }
+    }
+}
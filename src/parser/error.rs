@@ -50,6 +50,12 @@ pub enum ParseError {
         line: usize,
         column: usize,
     },
+
+    InvalidInterpolation {
+        message: String,
+        line: usize,
+        column: usize,
+    },
 }
 
 impl ParseError {
@@ -93,6 +99,14 @@ impl ParseError {
         }
     }
 
+    pub fn invalid_interpolation(message: String, line: usize, column: usize) -> Self {
+        Self::InvalidInterpolation {
+            message,
+            line,
+            column,
+        }
+    }
+
     pub fn invalid_escape_sequence(sequence: String, line: usize, column: usize) -> Self {
         Self::InvalidEscapeSequence {
             sequence,
@@ -176,6 +190,17 @@ impl std::fmt::Display for ParseError {
                     message, line, column
                 )
             }
+            ParseError::InvalidInterpolation {
+                message,
+                line,
+                column,
+            } => {
+                write!(
+                    f,
+                    "Invalid interpolation: {} at line {}, column {}",
+                    message, line, column
+                )
+            }
         }
     }
 }
@@ -0,0 +1,172 @@
+//! Format specs of interpolations and `string.format`, as `>10` in "{size:>10}":
+//! `[[fill]align][+][0][width][,][.precision][type]`
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// How a number is written, decimal if none is given
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Notation {
+    // x
+    Hex,
+    // X
+    UpperHex,
+    // o
+    Octal,
+    // b
+    Binary,
+    // e
+    Exponent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatSpec {
+    pub fill: char,
+    // numbers are aligned to the right, other values to the left by default
+    pub align: Option<Align>,
+    // `+` for positive numbers too
+    pub sign: bool,
+    // numbers are padded with zeros after the sign
+    pub zero: bool,
+    pub width: usize,
+    // digits of the integer part are grouped by three with `,`
+    pub thousands: bool,
+    // digits after the point for numbers, characters for other values
+    pub precision: Option<usize>,
+    pub notation: Option<Notation>,
+}
+
+// largest width and precision, larger ones would make huge texts
+const MAX_NUMBER: usize = 10_000;
+
+impl Align {
+    fn from_char(ch: char) -> Option<Align> {
+        match ch {
+            '<' => Some(Align::Left),
+            '>' => Some(Align::Right),
+            '^' => Some(Align::Center),
+            _ => None,
+        }
+    }
+}
+
+impl FormatSpec {
+    /// Spec written in the string, `None` if it is not a valid one
+    pub fn parse(spec: &str) -> Option<FormatSpec> {
+        let chars: Vec<char> = spec.chars().collect();
+        let mut res = FormatSpec {
+            fill: ' ',
+            align: None,
+            sign: false,
+            zero: false,
+            width: 0,
+            thousands: false,
+            precision: None,
+            notation: None,
+        };
+        let mut i = 0;
+
+        if let Some(align) = chars.get(1).and_then(|&ch| Align::from_char(ch)) {
+            res.fill = chars[0];
+            res.align = Some(align);
+            i = 2;
+        } else if let Some(align) = chars.first().and_then(|&ch| Align::from_char(ch)) {
+            res.align = Some(align);
+            i = 1;
+        }
+
+        if chars.get(i) == Some(&'+') {
+            res.sign = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'0') {
+            res.zero = true;
+            i += 1;
+        }
+
+        let (width, next) = digits(&chars, i);
+        if next > i {
+            res.width = width?;
+        }
+        i = next;
+
+        if chars.get(i) == Some(&',') {
+            res.thousands = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'.') {
+            let (precision, next) = digits(&chars, i + 1);
+            res.precision = Some(precision?);
+            i = next;
+        }
+
+        res.notation = match chars.get(i) {
+            Some('x') => Some(Notation::Hex),
+            Some('X') => Some(Notation::UpperHex),
+            Some('o') => Some(Notation::Octal),
+            Some('b') => Some(Notation::Binary),
+            Some('e') => Some(Notation::Exponent),
+            _ => None,
+        };
+        if res.notation.is_some() {
+            i += 1;
+        }
+
+        (i == chars.len()).then_some(res)
+    }
+}
+
+/// Number written from `start`, `None` above `MAX_NUMBER`, and where it ends
+fn digits(chars: &[char], start: usize) -> (Option<usize>, usize) {
+    let end = chars[start.min(chars.len())..]
+        .iter()
+        .position(|ch| !ch.is_ascii_digit())
+        .map_or(chars.len(), |len| start + len);
+
+    let number: String = chars[start.min(end)..end].iter().collect();
+    let number = number.parse().ok().filter(|&n| n <= MAX_NUMBER);
+    (number, end)
+}
+
+impl std::fmt::Display for FormatSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(align) = self.align {
+            if self.fill != ' ' {
+                write!(f, "{}", self.fill)?;
+            }
+            let align = match align {
+                Align::Left => '<',
+                Align::Right => '>',
+                Align::Center => '^',
+            };
+            write!(f, "{}", align)?;
+        }
+        if self.sign {
+            write!(f, "+")?;
+        }
+        if self.zero {
+            write!(f, "0")?;
+        }
+        if self.width > 0 {
+            write!(f, "{}", self.width)?;
+        }
+        if self.thousands {
+            write!(f, ",")?;
+        }
+        if let Some(precision) = self.precision {
+            write!(f, ".{}", precision)?;
+        }
+        match self.notation {
+            Some(Notation::Hex) => write!(f, "x"),
+            Some(Notation::UpperHex) => write!(f, "X"),
+            Some(Notation::Octal) => write!(f, "o"),
+            Some(Notation::Binary) => write!(f, "b"),
+            Some(Notation::Exponent) => write!(f, "e"),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,10 +1,37 @@
 use crate::parser::error::ParseError;
 use crate::parser::tokens::{Interpolation, StringInterpolationInfo, Token, TokenType};
+use crate::parser::format::FormatSpec;
+use num_bigint::BigInt;
 
 pub type TokenizeResult = ParseResult<Vec<Token>>;
 
 fn is_digit(ch: Option<char>) -> bool {
-    ch.map_or(false, |ch| ch.is_ascii_digit())
+    ch.is_some_and(|ch| ch.is_ascii_digit())
+}
+
+/// Expression of the interpolation and its format spec, which follows the last `:`
+/// standing right after a value, as in `{size:>10}`. A `:` after a space starts an inline string.
+fn split_format(input: &str) -> (&str, Option<&str>) {
+    let mut depth = 0;
+    let mut split = None;
+    let mut prev: Option<char> = None;
+
+    for (i, ch) in input.char_indices() {
+        match ch {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ':' if depth == 0 && prev.is_some_and(|p| !p.is_whitespace() && !"([:".contains(p)) => {
+                split = Some(i)
+            }
+            _ => {}
+        }
+        prev = Some(ch);
+    }
+
+    match split {
+        Some(i) => (&input[..i], Some(&input[i + 1..])),
+        None => (input, None),
+    }
 }
 
 const IDENT_START_CHARSET: &str = "!@%^&*-=_+|?<>.$/";
@@ -99,23 +126,16 @@ impl Lexer {
                     Token::hash(line, start_column)
                 }
             }
-            '\'' => {
-                if self.peek() == Some('(') {
-                    self.advance(); // skip (
-                    Token::open_block(line, start_column)
-                } else {
-                    return Err(ParseError::UnexpectedChar {
-                        char: ch,
-                        line,
-                        column: start_column,
-                    });
-                }
+            '~' => Token::new(TokenType::Unquote, "~".to_string(), line, start_column),
+            '\'' if self.peek() == Some('(') => {
+                self.advance(); // skip (
+                Token::open_block(line, start_column)
             }
 
             '-' => {
                 if self
                     .peek()
-                    .map(|x| ('0'..='9').contains(&x))
+                    .map(|x: char| x.is_ascii_digit())
                     .unwrap_or(false)
                 {
                     self.advance();
@@ -158,8 +178,11 @@ impl Lexer {
             self.advance();
         }
 
+        // numbers without a fraction are integers
+        let mut fraction = false;
         if self.peek() == Some('.') && is_digit(self.peek_next()) {
             self.advance(); // skip .
+            fraction = true;
 
             while is_digit(self.peek()) {
                 self.advance();
@@ -168,14 +191,25 @@ impl Lexer {
 
         let lexeme: String = self.input[start..self.current].iter().collect();
 
-        match lexeme.parse::<f64>() {
-            Ok(value) => Ok(Token {
-                token_type: TokenType::Number(if negative { -value } else { value }),
+        let token_type = match fraction {
+            true => lexeme
+                .parse::<f64>()
+                .ok()
+                .map(|value| TokenType::Number(if negative { -value } else { value })),
+            false => lexeme
+                .parse::<BigInt>()
+                .ok()
+                .map(|value| TokenType::Integer(if negative { -value } else { value })),
+        };
+
+        match token_type {
+            Some(token_type) => Ok(Token {
+                token_type,
                 lexeme,
                 line: self.line,
                 column: start_column,
             }),
-            Err(_) => Err(ParseError::invalid_number(lexeme, self.line, start_column)),
+            None => Err(ParseError::invalid_number(lexeme, self.line, start_column)),
         }
     }
 
@@ -374,7 +408,18 @@ impl Lexer {
                     }
 
                     let input: String = self.input[i_start..self.current].iter().collect();
-                    let mut inter_lexer = Lexer::new(input.as_str());
+                    let (input, format) = split_format(&input);
+                    let format = match format {
+                        Some(spec) => Some(FormatSpec::parse(spec).ok_or_else(|| {
+                            ParseError::invalid_interpolation(
+                                format!("invalid format spec '{}'", spec),
+                                self.line,
+                                start_column,
+                            )
+                        })?),
+                        None => None,
+                    };
+                    let mut inter_lexer = Lexer::new(input);
 
                     if interpolation.is_none() {
                         interpolation = Some(StringInterpolationInfo {
@@ -387,6 +432,7 @@ impl Lexer {
                         Ok(tokens) => {
                             interpolation.as_mut().unwrap().entries.push(Interpolation {
                                 tokens,
+                                format,
                                 start: interpolation_start,
                                 end: interpolation_start + 1, // placeholder position
                                 position: interpolation_start,
@@ -534,7 +580,7 @@ impl Lexer {
         }
         let lexeme: String = self.input[start..self.current].iter().collect();
 
-        return Ok(Token::single_line_comment(lexeme, self.line, start_column));
+        Ok(Token::single_line_comment(lexeme, self.line, start_column))
     }
 
     fn ident(&mut self, start_column: usize) -> ParseResult<Token> {
@@ -554,6 +600,8 @@ impl Lexer {
             "let" => Token::new(TokenType::Let, lexeme, self.line, start_column),
             "match" => Token::new(TokenType::Match, lexeme, self.line, start_column),
             "fn" => Token::new(TokenType::Fn, lexeme, self.line, start_column),
+            "macro" => Token::new(TokenType::Macro, lexeme, self.line, start_column),
+            "|" => Token::new(TokenType::Bar, lexeme, self.line, start_column),
             _ => Token::ident(lexeme, self.line, start_column),
         };
         Ok(token)
@@ -635,7 +635,7 @@ mod tests {
             match result.unwrap_err() {
                 ParseError::InvalidEscapeSequence { column, .. } => {
                     // The backslash is at column 5 (1-indexed: "abc\ is positions 1-4)
-                    assert!(column >= 4 && column <= 6, "Column was {}", column);
+                    assert!((4..=6).contains(&column), "Column was {}", column);
                 }
                 e => panic!("Expected InvalidEscapeSequence error, got {:?}", e),
             }
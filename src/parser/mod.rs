@@ -1,16 +1,22 @@
 pub mod ast;
 pub mod error;
+pub mod format;
 pub mod lexer;
+#[allow(clippy::module_inception)]
 pub mod parser;
 pub mod tokens;
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
 #[cfg(test)]
 mod lexer_tests;
 
-pub use ast::{Expression, LetPattern, MatchPattern, ObjectItem, Program, Statement};
+pub use ast::{
+    Expression, LambdaClause, LetPattern, MatchArm, MatchPattern, ObjectItem, Program, Span,
+    Statement,
+};
 pub use error::ParseError;
 pub use lexer::Lexer;
 pub use parser::{Parser, ParseResult};
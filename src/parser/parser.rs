@@ -2,6 +2,7 @@ use crate::parser::ast::*;
 use crate::parser::error::ParseError;
 use crate::parser::tokens::{Token, TokenType};
 use std::collections::VecDeque;
+use std::rc::Rc;
 
 pub type ParseResult<T> = Result<T, ParseError>;
 
@@ -86,6 +87,8 @@ impl Parser {
     }
 
     fn parse_expression(&mut self, precedence: Precedence) -> ParseResult<Expression> {
+        let span = self.current_span();
+
         // Parse prefix/primary expression
         let mut left = self.parse_primary()?;
 
@@ -102,8 +105,9 @@ impl Parser {
                     while self.is_newline() {
                         self.advance();
                     }
+                    let right_span = self.current_span();
                     let right = self.parse_expression(Precedence::Pipe)?;
-                    left = Expression::pipe(left, right);
+                    left = Expression::pipe(left, right, right_span);
                     true
                 }
                 Ok(TokenType::Chain) if precedence < Precedence::Chain => {
@@ -113,7 +117,7 @@ impl Parser {
                         self.advance();
                     }
                     let right = self.parse_expression(Precedence::Chain)?;
-                    left = Expression::chain(left, right);
+                    left = Expression::chain(left, right, span);
                     true
                 }
                 Ok(TokenType::Flow) if precedence < Precedence::Flow => {
@@ -133,7 +137,7 @@ impl Parser {
                 _ => {
                     if self.can_start_primary() && precedence < Precedence::Apply {
                         let arg = self.parse_expression(Precedence::Apply)?;
-                        left = Expression::application(left, arg);
+                        left = Expression::application(left, arg, span);
                         true
                     } else {
                         false
@@ -165,6 +169,11 @@ impl Parser {
                 self.advance();
                 Ok(Expression::number(value))
             }
+            Some(TokenType::Integer(n)) => {
+                let value = n.clone();
+                self.advance();
+                Ok(Expression::integer(value))
+            }
             Some(TokenType::String(s)) => {
                 let value = s.clone();
                 self.advance();
@@ -172,14 +181,27 @@ impl Parser {
             }
             Some(TokenType::StringInterpolation(info)) => {
                 let value = info.clone();
+                let (line, column) = self
+                    .current
+                    .as_ref()
+                    .map_or((0, 0), |token| (token.line, token.column));
                 self.advance();
                 let interpolatons = value
                     .entries
                     .into_iter()
                     .map(|i| {
                         let mut subparser = Parser::new(i.tokens);
-                        subparser.parse().map(|mut prg| Interpolation {
-                            expression: prg.statements.swap_remove(0).expression,
+                        let statement = subparser.parse()?.statements.into_iter().next();
+                        let Some(statement) = statement else {
+                            return Err(ParseError::invalid_interpolation(
+                                "no expression inside {}".to_string(),
+                                line,
+                                column,
+                            ));
+                        };
+                        Ok(Interpolation {
+                            expression: statement.expression,
+                            format: i.format,
                             start: i.start,
                             end: i.end,
                             position: i.position,
@@ -205,6 +227,18 @@ impl Parser {
                 self.advance();
                 self.parse_lambda()
             }
+            Some(TokenType::Macro) => {
+                self.advance();
+                Ok(Expression::macro_expr(self.parse_lambda()?))
+            }
+            Some(TokenType::Unquote) => {
+                self.advance();
+                Ok(Expression::unquote(self.parse_primary()?))
+            }
+            Some(TokenType::Match) => {
+                self.advance();
+                self.parse_match()
+            }
             Some(TokenType::LeftParen) => {
                 self.advance();
                 let expr = self.parse_expression(Precedence::Lowest)?;
@@ -239,7 +273,7 @@ impl Parser {
 
     fn parse_let_expression(&mut self) -> ParseResult<Expression> {
         let pattern = self.parse_let_pattern()?;
-        let value = Box::new(self.parse_expression(Precedence::Lowest)?);
+        let value = Rc::new(self.parse_expression(Precedence::Lowest)?);
 
         Ok(Expression::Let { pattern, value })
     }
@@ -248,8 +282,10 @@ impl Parser {
         match self.current_token_type_ref() {
             Some(TokenType::Ident) => {
                 let name = self.current_lexeme().to_string();
-                self.advance();
-                Ok(LetPattern::Identifier(name))
+                match self.parse_annotation() {
+                    Some(ty) => Ok(LetPattern::Typed { name, ty }),
+                    None => Ok(LetPattern::Identifier(name)),
+                }
             }
             Some(TokenType::LeftBracket) => {
                 self.advance();
@@ -257,9 +293,8 @@ impl Parser {
                 let mut rest = None;
 
                 while !self.check_token(&TokenType::RightBracket) {
-                    if self.check_token(&TokenType::Hash) {
-                        self.advance();
-                        rest = Some(self.expect_identifier()?);
+                    if let Some(name) = self.parse_rest_binding()? {
+                        rest = Some(name);
                         break;
                     }
                     patterns.push(self.parse_let_pattern()?);
@@ -279,42 +314,126 @@ impl Parser {
     }
 
     fn parse_lambda(&mut self) -> ParseResult<Expression> {
+        let line = self.current_line();
+        let column = self.current_column();
+        let (parameters, rest) = self.parse_lambda_parameters()?;
+
+        // Parse the body - this should parse the entire remaining expression
+        let body = self.parse_expression(Precedence::Lowest)?;
+
+        if !self.at_clause_separator() {
+            return Ok(Expression::Lambda {
+                parameters,
+                rest,
+                body: Rc::new(body),
+            });
+        }
+
+        if rest.is_some() {
+            return Err(ParseError::invalid_pattern(
+                "rest parameter is not allowed in a multi-clause function".to_string(),
+                line,
+                column,
+            ));
+        }
+
+        let mut clauses = vec![LambdaClause {
+            parameters,
+            body,
+        }];
+
+        while self.at_clause_separator() {
+            self.advance();
+
+            let line = self.current_line();
+            let column = self.current_column();
+            let (parameters, rest) = self.parse_lambda_parameters()?;
+
+            if rest.is_some() {
+                return Err(ParseError::invalid_pattern(
+                    "rest parameter is not allowed in a multi-clause function".to_string(),
+                    line,
+                    column,
+                ));
+            }
+            if parameters.len() != clauses[0].parameters.len() {
+                return Err(ParseError::invalid_pattern(
+                    format!(
+                        "every clause must take {} parameters, got {}",
+                        clauses[0].parameters.len(),
+                        parameters.len()
+                    ),
+                    line,
+                    column,
+                ));
+            }
+
+            let body = self.parse_expression(Precedence::Lowest)?;
+            clauses.push(LambdaClause { parameters, body });
+        }
+
+        Ok(Expression::MultiLambda { clauses })
+    }
+
+    fn parse_lambda_parameters(&mut self) -> ParseResult<(Vec<MatchPattern>, Option<String>)> {
         self.expect_token(TokenType::LeftBracket)?;
 
         let mut parameters = Vec::new();
         let mut rest = None;
 
         while !self.check_token(&TokenType::RightBracket) {
-            if self.check_token(&TokenType::Hash) {
-                self.advance();
-                rest = Some(self.expect_identifier()?);
+            if let Some(name) = self.parse_rest_binding()? {
+                rest = Some(name);
                 break;
             }
             parameters.push(self.parse_match_pattern()?);
         }
 
         self.expect_token(TokenType::RightBracket)?;
+        Ok((parameters, rest))
+    }
 
-        // Parse the body - this should parse the entire remaining expression
-        let body = Box::new(self.parse_expression(Precedence::Lowest)?);
+    /// Skips the name on the current token and reads the type written right after it,
+    /// as in `x:Number`. With a space between them `x :Number` is two patterns.
+    fn parse_annotation(&mut self) -> Option<String> {
+        let line = self.current_line();
+        let end = self.current_column() + self.current_lexeme().chars().count();
+        self.advance();
 
-        Ok(Expression::Lambda {
-            parameters,
-            rest,
-            body,
-        })
+        let Some(TokenType::String(ty)) = self.current_token_type_ref() else {
+            return None;
+        };
+        if self.current_line() != line || self.current_column() != end {
+            return None;
+        }
+
+        let ty = ty.clone();
+        self.advance();
+        Some(ty)
+    }
+
+    /// Checks for `|` starting the next function clause, which may be placed on the next line
+    fn at_clause_separator(&mut self) -> bool {
+        let next_is_bar = matches!(
+            self.peek.as_ref().map(|t| &t.token_type),
+            Some(TokenType::Bar)
+        );
+        if self.is_newline() && next_is_bar {
+            self.advance();
+        }
+        self.check_token(&TokenType::Bar)
     }
 
     fn parse_match_pattern(&mut self) -> ParseResult<MatchPattern> {
         match self.current_token_type_ref() {
             Some(TokenType::Ident) => {
                 let name = self.current_lexeme().to_string();
-                self.advance();
+                let ty = self.parse_annotation();
 
-                if name == "_" {
-                    Ok(MatchPattern::Wildcard)
-                } else {
-                    Ok(MatchPattern::Identifier(name))
+                match ty {
+                    _ if name == "_" => Ok(MatchPattern::Wildcard),
+                    Some(ty) => Ok(MatchPattern::Typed { name, ty }),
+                    None => Ok(MatchPattern::Identifier(name)),
                 }
             }
             Some(TokenType::Number(n)) => {
@@ -322,6 +441,11 @@ impl Parser {
                 self.advance();
                 Ok(MatchPattern::Literal(LiteralPattern::Number(value)))
             }
+            Some(TokenType::Integer(n)) => {
+                let value = n.clone();
+                self.advance();
+                Ok(MatchPattern::Literal(LiteralPattern::Integer(value)))
+            }
             Some(TokenType::String(s)) => {
                 let value = s.clone();
                 self.advance();
@@ -333,9 +457,8 @@ impl Parser {
                 let mut rest = None;
 
                 while !self.check_token(&TokenType::RightBracket) {
-                    if self.check_token(&TokenType::Hash) {
-                        self.advance();
-                        rest = Some(self.expect_identifier()?);
+                    if let Some(name) = self.parse_rest_binding()? {
+                        rest = Some(name);
                         break;
                     }
                     patterns.push(self.parse_match_pattern()?);
@@ -354,6 +477,51 @@ impl Parser {
         }
     }
 
+    /// Parses a rest binding at the end of a list pattern: `#rest` or `...rest`.
+    /// Returns `None` if the current token does not start one.
+    fn parse_rest_binding(&mut self) -> ParseResult<Option<String>> {
+        if self.check_token(&TokenType::Hash) {
+            self.advance();
+            return self.expect_identifier().map(Some);
+        }
+
+        if let Some(TokenType::Ident) = self.current_token_type_ref() {
+            if let Some(name) = self.current_lexeme().strip_prefix("...") {
+                if !name.is_empty() {
+                    let name = name.to_string();
+                    self.advance();
+                    return Ok(Some(name));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// match <subject> { <pattern> <body> ... }
+    /// Arms are checked top to bottom, the first matching pattern wins.
+    fn parse_match(&mut self) -> ParseResult<Expression> {
+        let subject = self.parse_expression(Precedence::Apply)?;
+        self.expect_token(TokenType::LeftCurlyBracket)?;
+
+        let mut arms = Vec::new();
+        loop {
+            while self.is_newline() {
+                self.advance();
+            }
+            if self.check_token(&TokenType::RightCurlyBracket) || self.is_at_end() {
+                break;
+            }
+
+            let pattern = self.parse_match_pattern()?;
+            let body = self.parse_expression(Precedence::Apply)?;
+            arms.push(MatchArm { pattern, body });
+        }
+
+        self.expect_token(TokenType::RightCurlyBracket)?;
+        Ok(Expression::match_expr(subject, arms))
+    }
+
     fn parse_block(&mut self) -> ParseResult<Expression> {
         let expressions = self.parse_block_contents()?;
         self.expect_token(TokenType::RightParen)?;
@@ -372,6 +540,7 @@ impl Parser {
     fn parse_block_contents(&mut self) -> ParseResult<Vec<Expression>> {
         let mut expressions = Vec::new();
         let mut current_line_expr: Option<Expression> = None;
+        let mut line_span = self.current_span();
         let mut has_newlines = false;
 
         while !self.check_token(&TokenType::RightParen) && !self.is_at_end() {
@@ -386,13 +555,17 @@ impl Parser {
                 continue;
             }
 
+            if current_line_expr.is_none() {
+                line_span = self.current_span();
+            }
+
             // Parse a primary expression
             let primary = self.parse_primary()?;
 
             // Build up the current line expression
             current_line_expr = Some(match current_line_expr.take() {
                 None => primary,
-                Some(left) => Expression::application(left, primary),
+                Some(left) => Expression::application(left, primary, line_span),
             });
 
             // Check if there's an operator that continues the expression
@@ -403,7 +576,7 @@ impl Parser {
             ) {
                 // Continue parsing with the accumulated expression as the left side
                 let left = current_line_expr.take().unwrap();
-                let full_expr = self.continue_expression(left, Precedence::Lowest)?;
+                let full_expr = self.continue_expression(left, line_span, Precedence::Lowest)?;
 
                 if has_newlines {
                     current_line_expr = Some(full_expr);
@@ -426,6 +599,7 @@ impl Parser {
     fn continue_expression(
         &mut self,
         mut left: Expression,
+        span: Span,
         precedence: Precedence,
     ) -> ParseResult<Expression> {
         // Continue parsing an expression with operators
@@ -441,8 +615,9 @@ impl Parser {
                     while self.is_newline() {
                         self.advance();
                     }
+                    let right_span = self.current_span();
                     let right = self.parse_expression(Precedence::Pipe)?;
-                    left = Expression::pipe(left, right);
+                    left = Expression::pipe(left, right, right_span);
                     true
                 }
                 Ok(TokenType::Chain) if precedence < Precedence::Chain => {
@@ -452,7 +627,7 @@ impl Parser {
                         self.advance();
                     }
                     let right = self.parse_expression(Precedence::Chain)?;
-                    left = Expression::chain(left, right);
+                    left = Expression::chain(left, right, span);
                     true
                 }
                 Ok(TokenType::Flow) if precedence < Precedence::Flow => {
@@ -580,20 +755,24 @@ impl Parser {
     }
 
     fn can_start_primary(&self) -> bool {
-        match self.current.as_ref().map(|t| &t.token_type) {
+        matches!(
+            self.current.as_ref().map(|t| &t.token_type),
             Some(TokenType::Number(_))
-            | Some(TokenType::String(_))
-            | Some(TokenType::StringInterpolation(_))
-            | Some(TokenType::Ident)
-            | Some(TokenType::Let)
-            | Some(TokenType::Fn)
-            | Some(TokenType::LeftParen)
-            | Some(TokenType::OpenBlock)
-            | Some(TokenType::OpenLazy)
-            | Some(TokenType::LeftBracket)
-            | Some(TokenType::LeftCurlyBracket) => true,
-            _ => false,
-        }
+                | Some(TokenType::Integer(_))
+                | Some(TokenType::String(_))
+                | Some(TokenType::StringInterpolation(_))
+                | Some(TokenType::Ident)
+                | Some(TokenType::Let)
+                | Some(TokenType::Fn)
+                | Some(TokenType::Macro)
+                | Some(TokenType::Unquote)
+                | Some(TokenType::Match)
+                | Some(TokenType::LeftParen)
+                | Some(TokenType::OpenBlock)
+                | Some(TokenType::OpenLazy)
+                | Some(TokenType::LeftBracket)
+                | Some(TokenType::LeftCurlyBracket)
+        )
     }
 
     fn is_at_end(&self) -> bool {
@@ -618,4 +797,8 @@ impl Parser {
     fn current_column(&self) -> usize {
         self.current.as_ref().map(|t| t.column).unwrap_or(0)
     }
+
+    fn current_span(&self) -> Span {
+        Span::new(self.current_line(), self.current_column())
+    }
 }
@@ -1,6 +1,9 @@
 #[cfg(test)]
 mod tests {
-    use crate::parser::{parse, Expression};
+    use crate::parser::ast::LiteralPattern;
+    use crate::parser::format::{Align, FormatSpec, Notation};
+    use crate::parser::{parse, Expression, LetPattern, MatchPattern, ParseError, Span};
+    use num_bigint::BigInt;
 
     #[test]
     fn test_parse_number() {
@@ -9,9 +12,14 @@ mod tests {
         assert_eq!(result.statements.len(), 1);
 
         match &result.statements[0].expression {
-            Expression::Number(n) => assert_eq!(*n, 42.0),
-            _ => panic!("Expected number"),
+            Expression::Integer(n) => assert_eq!(*n, BigInt::from(42)),
+            _ => panic!("Expected integer"),
         }
+
+        let result = parse("4.5\n-123456789012345678901234567890").unwrap();
+        assert_eq!(result.statements[0].expression, Expression::Number(4.5));
+        let big: BigInt = "-123456789012345678901234567890".parse().unwrap();
+        assert_eq!(result.statements[1].expression, Expression::Integer(big));
     }
 
     #[test]
@@ -60,7 +68,7 @@ mod tests {
             Expression::List(items) => {
                 assert_eq!(items.len(), 3);
                 match &items[0] {
-                    Expression::Number(n) => assert_eq!(*n, 1.0),
+                    Expression::Integer(n) => assert_eq!(*n, BigInt::from(1)),
                     _ => panic!("Expected number in list"),
                 }
             }
@@ -82,7 +90,7 @@ mod tests {
                     _ => panic!("Expected string key"),
                 }
                 match &items[0].value {
-                    Expression::Number(n) => assert_eq!(*n, 10.0),
+                    Expression::Integer(n) => assert_eq!(*n, BigInt::from(10)),
                     _ => panic!("Expected number value"),
                 }
             }
@@ -103,7 +111,7 @@ mod tests {
                     _ => panic!("Expected identifier pattern"),
                 }
                 match &**value {
-                    Expression::Number(n) => assert_eq!(*n, 10.0),
+                    Expression::Integer(n) => assert_eq!(*n, BigInt::from(10)),
                     _ => panic!("Expected number value"),
                 }
             }
@@ -142,7 +150,7 @@ mod tests {
         assert_eq!(result.statements.len(), 1);
 
         match &result.statements[0].expression {
-            Expression::Pipe { left, right } => {
+            Expression::Pipe { left, right, .. } => {
                 match &**left {
                     Expression::Identifier(name) => assert_eq!(name, "x"),
                     _ => panic!("Expected identifier on left"),
@@ -184,7 +192,7 @@ mod tests {
         assert_eq!(result.statements.len(), 1);
 
         match &result.statements[0].expression {
-            Expression::Application { function, argument } => {
+            Expression::Application { function, argument, .. } => {
                 match &**function {
                     Expression::Identifier(name) => assert_eq!(name, "f"),
                     _ => panic!("Expected identifier as function"),
@@ -209,7 +217,7 @@ mod tests {
             Expression::Block(exprs) => {
                 assert_eq!(exprs.len(), 1);
                 match &exprs[0] {
-                    Expression::Application { function, argument } => {
+                    Expression::Application { function, argument, .. } => {
                         // argument should be z
                         match &**argument {
                             Expression::Identifier(name) => assert_eq!(name, "z"),
@@ -220,6 +228,7 @@ mod tests {
                             Expression::Application {
                                 function: inner_fn,
                                 argument: inner_arg,
+                                ..
                             } => {
                                 match &**inner_fn {
                                     Expression::Identifier(name) => assert_eq!(name, "x"),
@@ -251,7 +260,7 @@ mod tests {
             Expression::Lazy(exprs) => {
                 assert_eq!(exprs.len(), 1);
                 match &exprs[0] {
-                    Expression::Application { function, argument } => {
+                    Expression::Application { function, argument, .. } => {
                         match &**function {
                             Expression::Identifier(name) => assert_eq!(name, "x"),
                             _ => panic!("Expected identifier x"),
@@ -290,7 +299,7 @@ mod tests {
 
         // Should parse as: x $> (f y)
         match &result.statements[0].expression {
-            Expression::Pipe { left, right } => {
+            Expression::Pipe { left, right, .. } => {
                 match &**left {
                     Expression::Identifier(name) => assert_eq!(name, "x"),
                     _ => panic!("Expected identifier on left"),
@@ -311,7 +320,7 @@ mod tests {
 
         // Should parse as: (a #> b) $> c
         match &result.statements[0].expression {
-            Expression::Pipe { left, right } => {
+            Expression::Pipe { left, right, .. } => {
                 match &**left {
                     Expression::Flow { .. } => {}
                     _ => panic!("Expected flow on left"),
@@ -457,7 +466,7 @@ mod tests {
         assert_eq!(result.statements.len(), 2);
 
         match &result.statements[0].expression {
-            Expression::Pipe { left, right } => {
+            Expression::Pipe { left, right, .. } => {
                 match &**left {
                     Expression::Identifier(name) => assert_eq!(name, "x"),
                     _ => panic!("Expected identifier x"),
@@ -502,7 +511,7 @@ mod tests {
 
                 // First line: x $> f
                 match &exprs[0] {
-                    Expression::Pipe { left, right } => {
+                    Expression::Pipe { left, right, .. } => {
                         match &**left {
                             Expression::Identifier(name) => assert_eq!(name, "x"),
                             _ => panic!("Expected identifier x"),
@@ -517,7 +526,7 @@ mod tests {
 
                 // Second line: y $> g
                 match &exprs[1] {
-                    Expression::Pipe { left, right } => {
+                    Expression::Pipe { left, right, .. } => {
                         match &**left {
                             Expression::Identifier(name) => assert_eq!(name, "y"),
                             _ => panic!("Expected identifier y"),
@@ -579,10 +588,10 @@ mod tests {
                 assert_eq!(exprs.len(), 1);
 
                 match &exprs[0] {
-                    Expression::Chain { left, right } => {
+                    Expression::Chain { left, right, .. } => {
                         // Left side should be: Application(if, true)
                         match &**left {
-                            Expression::Application { function, argument } => {
+                            Expression::Application { function, argument, .. } => {
                                 match &**function {
                                     Expression::Identifier(name) => assert_eq!(name, "if"),
                                     _ => panic!("Expected 'if' identifier as function"),
@@ -599,13 +608,13 @@ mod tests {
 
                         // Right side should be: Application(print, 1)
                         match &**right {
-                            Expression::Application { function, argument } => {
+                            Expression::Application { function, argument, .. } => {
                                 match &**function {
                                     Expression::Identifier(name) => assert_eq!(name, "print"),
                                     _ => panic!("Expected 'print' identifier as function"),
                                 }
                                 match &**argument {
-                                    Expression::Number(n) => assert_eq!(*n, 1.0),
+                                    Expression::Integer(n) => assert_eq!(*n, BigInt::from(1)),
                                     _ => panic!("Expected number 1 as argument"),
                                 }
                             }
@@ -635,10 +644,10 @@ mod tests {
                 assert_eq!(exprs.len(), 1);
 
                 match &exprs[0] {
-                    Expression::Chain { left, right } => {
+                    Expression::Chain { left, right, .. } => {
                         // Left: Application(a, b)
                         match &**left {
-                            Expression::Application { function, argument } => {
+                            Expression::Application { function, argument, .. } => {
                                 match &**function {
                                     Expression::Identifier(name) => assert_eq!(name, "a"),
                                     _ => panic!("Expected 'a' identifier"),
@@ -653,7 +662,7 @@ mod tests {
 
                         // Right: Application(c, d)
                         match &**right {
-                            Expression::Application { function, argument } => {
+                            Expression::Application { function, argument, .. } => {
                                 match &**function {
                                     Expression::Identifier(name) => assert_eq!(name, "c"),
                                     _ => panic!("Expected 'c' identifier"),
@@ -686,7 +695,7 @@ mod tests {
                 assert_eq!(exprs.len(), 1);
 
                 match &exprs[0] {
-                    Expression::Chain { left, right } => {
+                    Expression::Chain { left, right, .. } => {
                         match &**left {
                             Expression::Application { .. } => {}
                             _ => panic!("Expected Application on left side of Chain"),
@@ -717,7 +726,7 @@ mod tests {
                 assert_eq!(exprs.len(), 1);
 
                 match &exprs[0] {
-                    Expression::Pipe { left, right } => {
+                    Expression::Pipe { left, right, .. } => {
                         // Left should be Chain { left: App(a,b), right: App(c,d) }
                         match &**left {
                             Expression::Chain { .. } => {}
@@ -735,4 +744,237 @@ mod tests {
             _ => panic!("Expected Block expression"),
         }
     }
+
+    #[test]
+    fn test_parse_match() {
+        let input = "match \"{os}-{arch}\" {\n  :Darwin-arm64 :macos-aarch64\n  [x ...rest] x\n  pl pl\n}";
+        let result = parse(input).unwrap();
+        assert_eq!(result.statements.len(), 1);
+
+        match &result.statements[0].expression {
+            Expression::Match { subject, arms } => {
+                match &**subject {
+                    Expression::StringInterpolation(_) => {}
+                    _ => panic!("Expected interpolated string as match subject"),
+                }
+                assert_eq!(arms.len(), 3);
+                assert_eq!(
+                    arms[0].pattern,
+                    MatchPattern::Literal(LiteralPattern::String("Darwin-arm64".to_string()))
+                );
+                assert_eq!(
+                    arms[1].pattern,
+                    MatchPattern::List {
+                        patterns: vec![MatchPattern::Identifier("x".to_string())],
+                        rest: Some("rest".to_string()),
+                    }
+                );
+                assert_eq!(arms[2].pattern, MatchPattern::Identifier("pl".to_string()));
+            }
+            _ => panic!("Expected match expression"),
+        }
+    }
+
+    #[test]
+    fn test_parse_match_arm_body_is_single_argument() {
+        let input = "match x { 0 (f 1) _ :other }";
+        let result = parse(input).unwrap();
+
+        match &result.statements[0].expression {
+            Expression::Match { arms, .. } => {
+                assert_eq!(arms.len(), 2);
+                match &arms[0].body {
+                    Expression::Parenthesized(_) => {}
+                    _ => panic!("Expected parenthesized arm body"),
+                }
+                assert_eq!(arms[1].pattern, MatchPattern::Wildcard);
+            }
+            _ => panic!("Expected match expression"),
+        }
+    }
+
+    #[test]
+    fn test_parse_let_rest_binding() {
+        let input = "let [head #tail] lst\nlet [a ...others] lst";
+        let result = parse(input).unwrap();
+        assert_eq!(result.statements.len(), 2);
+
+        for stmt in result.statements.iter() {
+            match &stmt.expression {
+                Expression::Let {
+                    pattern: LetPattern::List { patterns, rest },
+                    ..
+                } => {
+                    assert_eq!(patterns.len(), 1);
+                    assert!(rest.is_some());
+                }
+                _ => panic!("Expected list let pattern"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_type_annotations() {
+        let result = parse("let n:Number 5\nfn [s:String x :Bool] x").unwrap();
+
+        match &result.statements[0].expression {
+            Expression::Let { pattern, .. } => assert_eq!(
+                *pattern,
+                LetPattern::Typed {
+                    name: "n".to_string(),
+                    ty: "Number".to_string()
+                }
+            ),
+            _ => panic!("Expected let"),
+        }
+
+        match &result.statements[1].expression {
+            Expression::Lambda { parameters, .. } => assert_eq!(
+                *parameters,
+                vec![
+                    MatchPattern::Typed {
+                        name: "s".to_string(),
+                        ty: "String".to_string()
+                    },
+                    MatchPattern::Identifier("x".to_string()),
+                    MatchPattern::Literal(LiteralPattern::String("Bool".to_string())),
+                ]
+            ),
+            _ => panic!("Expected lambda"),
+        }
+    }
+
+    #[test]
+    fn test_parse_multi_clause_lambda() {
+        let input = "fn [0] 1 | [n] * n (fact (- 1 n))\nfn [[]] 0\n  | [[_ ...xs]] + 1 (len xs)";
+        let result = parse(input).unwrap();
+        assert_eq!(result.statements.len(), 2);
+
+        for stmt in result.statements.iter() {
+            match &stmt.expression {
+                Expression::MultiLambda { clauses } => {
+                    assert_eq!(clauses.len(), 2);
+                    assert!(clauses.iter().all(|c| c.parameters.len() == 1));
+                }
+                _ => panic!("Expected multi-clause lambda"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_multi_clause_lambda_arity_mismatch() {
+        let result = parse("fn [0] 1 | [a b] a");
+        assert!(matches!(result, Err(ParseError::InvalidPattern { .. })));
+
+        let result = parse("fn [a ...rest] 1 | [b] b");
+        assert!(matches!(result, Err(ParseError::InvalidPattern { .. })));
+    }
+
+    #[test]
+    fn test_parse_call_spans() {
+        let input = "print :start\n  list.map f $> g";
+        let result = parse(input).unwrap();
+
+        match &result.statements[1].expression {
+            Expression::Pipe { left, span, .. } => {
+                assert_eq!(*span, Span::new(2, 17));
+                match &**left {
+                    Expression::Application { span, .. } => assert_eq!(*span, Span::new(2, 3)),
+                    _ => panic!("Expected application on the left of pipe"),
+                }
+            }
+            _ => panic!("Expected pipe"),
+        }
+    }
+
+    #[test]
+    fn test_parse_macro_and_unquote() {
+        let result = parse("macro [cond body] quote (if (not ~cond) ~body)").unwrap();
+
+        match &result.statements[0].expression {
+            Expression::Macro(lambda) => match lambda.as_ref() {
+                Expression::Lambda { parameters, .. } => assert_eq!(parameters.len(), 2),
+                _ => panic!("Expected lambda inside macro"),
+            },
+            _ => panic!("Expected macro"),
+        }
+    }
+
+    #[test]
+    fn test_print_expression_as_source() {
+        let inputs = [
+            "list.map (fn [x] * x 2) [1 2 3] $> list.sum",
+            "let [a ...rest] (shell.lines \"ls {dir}\")",
+            "match x { 0 :zero [a _] (+ a 1) _ \"many \\{braces\\}\" }",
+            "fn [0] 1 | [n] * n (fact (- 1 n))",
+            "macro [c b] quote (if ~c ~b)",
+            "'(\n  print 1\n  #(print 2)\n)",
+            "let total:Number (fn [xs:List n] + n (list.sum xs))",
+            "print \"{size:>10} {ratio:.2} {x:*^8}\"",
+        ];
+
+        for input in inputs {
+            let printed = parse(input).unwrap().statements[0].expression.to_string();
+            let reparsed = parse(&printed).unwrap().statements[0].expression.to_string();
+            assert_eq!(printed, reparsed, "printing {:?} is not stable", input);
+        }
+
+        let printed = parse("list.map (fn [x] * x 2) [1 2 3] $> list.sum").unwrap();
+        assert_eq!(
+            printed.statements[0].expression.to_string(),
+            "list.map (fn [x] * x 2) [1 2 3] $> list.sum"
+        );
+    }
+
+    #[test]
+    fn test_parse_interpolation_format() {
+        let result = parse("\"{size:>10} {:ratio:.2} {x}\"").unwrap();
+        let Expression::StringInterpolation(si) = &result.statements[0].expression else {
+            panic!("Expected interpolated string");
+        };
+
+        assert_eq!(si.entries[0].expression, Expression::Identifier("size".to_string()));
+        let format = si.entries[0].format.as_ref().unwrap();
+        assert_eq!(format.align, Some(Align::Right));
+        assert_eq!(format.width, 10);
+
+        assert_eq!(si.entries[1].expression, Expression::String("ratio".to_string()));
+        assert_eq!(si.entries[1].format.as_ref().unwrap().precision, Some(2));
+        assert_eq!(si.entries[2].format, None);
+    }
+
+    #[test]
+    fn test_parse_format_spec() {
+        let spec = FormatSpec::parse("*^+012,.3x").unwrap();
+        assert_eq!(spec.fill, '*');
+        assert_eq!(spec.align, Some(Align::Center));
+        assert!(spec.sign && spec.zero && spec.thousands);
+        assert_eq!(spec.width, 12);
+        assert_eq!(spec.precision, Some(3));
+        assert_eq!(spec.notation, Some(Notation::Hex));
+        assert_eq!(spec.to_string(), "*^+012,.3x");
+
+        assert_eq!(FormatSpec::parse("").unwrap().width, 0);
+        assert_eq!(FormatSpec::parse("0>5").unwrap().fill, '0');
+        assert!(FormatSpec::parse("10.").is_none());
+        assert!(FormatSpec::parse("abc").is_none());
+
+        assert_eq!(FormatSpec::parse(".10000").unwrap().precision, Some(10000));
+        assert!(FormatSpec::parse(".99999").is_none());
+        assert!(FormatSpec::parse("99999999").is_none());
+        assert!(FormatSpec::parse("99999999999999999999999").is_none());
+    }
+
+    #[test]
+    fn test_parse_invalid_interpolation() {
+        for input in ["\"{}\"", "\"a { } b\"", "\"{x:zz}\"", "\"{x:.99999}\""] {
+            let result = parse(input);
+            assert!(
+                matches!(result, Err(ParseError::InvalidInterpolation { .. })),
+                "{:?} gave {:?}",
+                input,
+                result
+            );
+        }
+    }
 }
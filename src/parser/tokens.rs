@@ -1,7 +1,11 @@
+use crate::parser::format::FormatSpec;
+use num_bigint::BigInt;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     // Literals
     Number(f64),
+    Integer(BigInt),
     String(String),
     StringInterpolation(StringInterpolationInfo),
     Ident,
@@ -10,9 +14,12 @@ pub enum TokenType {
     Let,
     Match,
     Fn,
+    Macro,
     Pipe, // $>
     Flow, // #>
     Chain, // $
+    Bar, // | - separates function clauses
+    Unquote, // ~ - inserts a value into quoted code
 
     // Delimiters
     LeftParen,         // (
@@ -42,8 +49,8 @@ pub enum TokenType {
 StringInterpolationInfo {
     string: "hello _, what is the _"
     entries: [
-        Interpolation { tokens: ["." "user" ":name"], start: 6, end: 19, position: 6 },
-        Interpolation { tokens: ["random-question"], start: 34, end: 50, position: 21 },
+        Interpolation { tokens: ["." "user" ":name"], format: None, start: 6, end: 19, position: 6 },
+        Interpolation { tokens: ["random-question"], format: None, start: 34, end: 50, position: 21 },
     ]
 }
  */
@@ -56,6 +63,8 @@ pub struct StringInterpolationInfo {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Interpolation {
     pub tokens: Vec<Token>,
+    // spec after the last `:` standing right after the value, as in "{size:>10}"
+    pub format: Option<FormatSpec>,
     pub start: usize,
     pub end: usize,
 
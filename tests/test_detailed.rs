@@ -43,12 +43,12 @@ fn print_expr(expr: &shik::parser::Expression, indent: usize) {
             print_expr(left, indent + 2);
             print_expr(right, indent + 2);
         }
-        Expression::Pipe { left, right } => {
+        Expression::Pipe { left, right, .. } => {
             println!("{}Pipe:", prefix);
             print_expr(left, indent + 2);
             print_expr(right, indent + 2);
         }
-        Expression::Application { function, argument } => {
+        Expression::Application { function, argument, .. } => {
             println!("{}App:", prefix);
             print_expr(function, indent + 2);
             print_expr(argument, indent + 2);
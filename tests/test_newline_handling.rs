@@ -1,4 +1,4 @@
-use shik::parser::{parse, Expression, Statement};
+use shik::parser::{parse, Expression};
 
 fn main() {
     println!("Testing newline handling in parser...\n");